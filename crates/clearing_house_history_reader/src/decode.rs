@@ -0,0 +1,478 @@
+use std::fmt;
+use std::mem::{offset_of, size_of};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use bytemuck::Pod;
+use clearing_house::controller::position::PositionDirection;
+use clearing_house::state::history::curve_history::{CurveHistory, CurveRecord};
+use clearing_house::state::history::deposit_history::{
+    DepositDirection, DepositHistory, DepositRecord,
+};
+use clearing_house::state::history::funding_payment_history::{
+    FundingPaymentHistory, FundingPaymentRecord,
+};
+use clearing_house::state::history::funding_rate_history::{FundingRateHistory, FundingRateRecord};
+use clearing_house::state::history::liquidation_history::{LiquidationHistory, LiquidationRecord};
+use clearing_house::state::history::order_history::{OrderAction, OrderHistory, OrderRecord};
+use clearing_house::state::history::ring_buffer::RingBuffer;
+use clearing_house::state::history::trade_history::{TradeHistory, TradeRecord};
+use clearing_house::state::user_orders::{
+    OrderDiscountTier, OrderStatus, OrderTriggerCondition, OrderTriggerPriceSource, OrderType,
+};
+
+use crate::export::ExportFormat;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    // 账户不属于clearing_house program
+    InvalidOwner(Pubkey),
+    // 账户数据不足8字节，无法读取discriminator
+    MissingDiscriminator,
+    // discriminator不属于任何history账户
+    UnknownDiscriminator([u8; 8]),
+    // discriminator与期望的history类型不符
+    DiscriminatorMismatch {
+        expected: [u8; 8],
+        actual: [u8; 8],
+    },
+    // 账户数据长度与history类型的大小不符
+    InvalidSize {
+        expected: usize,
+        actual: usize,
+    },
+    // 记录中的枚举字段不是合法的判别值
+    InvalidEnumValue {
+        record_index: usize,
+        offset: usize,
+        value: u8,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidOwner(owner) => {
+                write!(f, "account is owned by {} instead of clearing house", owner)
+            }
+            DecodeError::MissingDiscriminator => {
+                write!(f, "account data is shorter than the discriminator")
+            }
+            DecodeError::UnknownDiscriminator(actual) => {
+                write!(f, "unknown history discriminator {:?}", actual)
+            }
+            DecodeError::DiscriminatorMismatch { expected, actual } => write!(
+                f,
+                "discriminator mismatch: expected {:?}, found {:?}",
+                expected, actual
+            ),
+            DecodeError::InvalidSize { expected, actual } => write!(
+                f,
+                "invalid account size: expected {} bytes, found {}",
+                expected, actual
+            ),
+            DecodeError::InvalidEnumValue {
+                record_index,
+                offset,
+                value,
+            } => write!(
+                f,
+                "invalid enum value {} at offset {} of record {}",
+                value, offset, record_index
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryKind {
+    Trade,
+    Deposit,
+    FundingPayment,
+    FundingRate,
+    Liquidation,
+    Curve,
+    Order,
+}
+
+impl HistoryKind {
+    pub const ALL: [HistoryKind; 7] = [
+        HistoryKind::Trade,
+        HistoryKind::Deposit,
+        HistoryKind::FundingPayment,
+        HistoryKind::FundingRate,
+        HistoryKind::Liquidation,
+        HistoryKind::Curve,
+        HistoryKind::Order,
+    ];
+
+    pub fn discriminator(&self) -> [u8; 8] {
+        match self {
+            HistoryKind::Trade => TradeHistory::DISCRIMINATOR,
+            HistoryKind::Deposit => DepositHistory::DISCRIMINATOR,
+            HistoryKind::FundingPayment => FundingPaymentHistory::DISCRIMINATOR,
+            HistoryKind::FundingRate => FundingRateHistory::DISCRIMINATOR,
+            HistoryKind::Liquidation => LiquidationHistory::DISCRIMINATOR,
+            HistoryKind::Curve => CurveHistory::DISCRIMINATOR,
+            HistoryKind::Order => OrderHistory::DISCRIMINATOR,
+        }
+    }
+
+    // 根据账户数据的前8字节判断history类型
+    pub fn from_account_data(data: &[u8]) -> Result<HistoryKind, DecodeError> {
+        let actual = read_discriminator(data)?;
+        HistoryKind::ALL
+            .into_iter()
+            .find(|kind| kind.discriminator() == actual)
+            .ok_or(DecodeError::UnknownDiscriminator(actual))
+    }
+}
+
+// 记录中的一个枚举字段：字段在记录中的偏移，以及判断该字节是否为合法判别值的函数
+pub type EnumField = (usize, fn(u8) -> bool);
+
+// 列出记录中所有的枚举字段
+// 枚举只有声明过的判别值是合法的，直接把账户数据复制进带枚举的Pod类型前必须逐个校验
+pub trait RecordEnumFields {
+    const ENUM_FIELDS: &'static [EnumField];
+}
+
+fn is_valid_variant<E: AnchorDeserialize>(value: u8) -> bool {
+    E::try_from_slice(&[value]).is_ok()
+}
+
+impl RecordEnumFields for TradeRecord {
+    const ENUM_FIELDS: &'static [EnumField] = &[(
+        offset_of!(TradeRecord, direction),
+        is_valid_variant::<PositionDirection>,
+    )];
+}
+
+impl RecordEnumFields for DepositRecord {
+    const ENUM_FIELDS: &'static [EnumField] = &[(
+        offset_of!(DepositRecord, direction),
+        is_valid_variant::<DepositDirection>,
+    )];
+}
+
+impl RecordEnumFields for OrderRecord {
+    const ENUM_FIELDS: &'static [EnumField] = &[
+        (
+            offset_of!(OrderRecord, action),
+            is_valid_variant::<OrderAction>,
+        ),
+        (
+            offset_of!(OrderRecord, order.status),
+            is_valid_variant::<OrderStatus>,
+        ),
+        (
+            offset_of!(OrderRecord, order.order_type),
+            is_valid_variant::<OrderType>,
+        ),
+        (
+            offset_of!(OrderRecord, order.direction),
+            is_valid_variant::<PositionDirection>,
+        ),
+        (
+            offset_of!(OrderRecord, order.discount_tier),
+            is_valid_variant::<OrderDiscountTier>,
+        ),
+        (
+            offset_of!(OrderRecord, order.trigger_condition),
+            is_valid_variant::<OrderTriggerCondition>,
+        ),
+        (
+            offset_of!(OrderRecord, order.trigger_price_source),
+            is_valid_variant::<OrderTriggerPriceSource>,
+        ),
+    ];
+}
+
+// 以下记录没有枚举字段
+impl RecordEnumFields for FundingPaymentRecord {
+    const ENUM_FIELDS: &'static [EnumField] = &[];
+}
+
+impl RecordEnumFields for FundingRateRecord {
+    const ENUM_FIELDS: &'static [EnumField] = &[];
+}
+
+impl RecordEnumFields for LiquidationRecord {
+    const ENUM_FIELDS: &'static [EnumField] = &[];
+}
+
+impl RecordEnumFields for CurveRecord {
+    const ENUM_FIELDS: &'static [EnumField] = &[];
+}
+
+// 解码后的history账户
+pub enum HistoryAccount {
+    Trade(Box<TradeHistory>),
+    Deposit(Box<DepositHistory>),
+    FundingPayment(Box<FundingPaymentHistory>),
+    FundingRate(Box<FundingRateHistory>),
+    Liquidation(Box<LiquidationHistory>),
+    Curve(Box<CurveHistory>),
+    Order(Box<OrderHistory>),
+}
+
+impl HistoryAccount {
+    // 根据discriminator自动识别history类型并解码
+    pub fn decode(data: &[u8]) -> Result<HistoryAccount, DecodeError> {
+        let history = match HistoryKind::from_account_data(data)? {
+            HistoryKind::Trade => HistoryAccount::Trade(decode_history(data)?),
+            HistoryKind::Deposit => HistoryAccount::Deposit(decode_history(data)?),
+            HistoryKind::FundingPayment => HistoryAccount::FundingPayment(decode_history(data)?),
+            HistoryKind::FundingRate => HistoryAccount::FundingRate(decode_history(data)?),
+            HistoryKind::Liquidation => HistoryAccount::Liquidation(decode_history(data)?),
+            HistoryKind::Curve => HistoryAccount::Curve(decode_history(data)?),
+            HistoryKind::Order => HistoryAccount::Order(decode_history(data)?),
+        };
+        Ok(history)
+    }
+
+    // 从RPC等能拿到账户owner的来源读取时，先确认账户属于clearing_house program再解码
+    pub fn decode_account(owner: &Pubkey, data: &[u8]) -> Result<HistoryAccount, DecodeError> {
+        if !owner.eq(&clearing_house::ID) {
+            return Err(DecodeError::InvalidOwner(*owner));
+        }
+        HistoryAccount::decode(data)
+    }
+
+    pub fn kind(&self) -> HistoryKind {
+        match self {
+            HistoryAccount::Trade(_) => HistoryKind::Trade,
+            HistoryAccount::Deposit(_) => HistoryKind::Deposit,
+            HistoryAccount::FundingPayment(_) => HistoryKind::FundingPayment,
+            HistoryAccount::FundingRate(_) => HistoryKind::FundingRate,
+            HistoryAccount::Liquidation(_) => HistoryKind::Liquidation,
+            HistoryAccount::Curve(_) => HistoryKind::Curve,
+            HistoryAccount::Order(_) => HistoryKind::Order,
+        }
+    }
+
+    // 按时间顺序（从最旧的记录开始）导出所有已写入的记录
+    pub fn export(
+        &self,
+        format: ExportFormat,
+        writer: &mut impl std::io::Write,
+    ) -> std::io::Result<()> {
+        match self {
+            HistoryAccount::Trade(history) => format.write(history.iter(), writer),
+            HistoryAccount::Deposit(history) => format.write(history.iter(), writer),
+            HistoryAccount::FundingPayment(history) => format.write(history.iter(), writer),
+            HistoryAccount::FundingRate(history) => format.write(history.iter(), writer),
+            HistoryAccount::Liquidation(history) => format.write(history.iter(), writer),
+            HistoryAccount::Curve(history) => format.write(history.iter(), writer),
+            HistoryAccount::Order(history) => format.write(history.iter(), writer),
+        }
+    }
+}
+
+// 校验discriminator、账户大小与记录中的枚举字段后把账户数据解码为T
+// history账户都很大（OrderHistory超过450KB），所以直接在堆上解码，并且不要求账户数据按T对齐
+pub fn decode_history<T>(data: &[u8]) -> Result<Box<T>, DecodeError>
+where
+    T: RingBuffer + Discriminator + Pod,
+    T::Record: RecordEnumFields,
+{
+    let actual = read_discriminator(data)?;
+    if actual != T::DISCRIMINATOR {
+        return Err(DecodeError::DiscriminatorMismatch {
+            expected: T::DISCRIMINATOR,
+            actual,
+        });
+    }
+
+    let expected_size = 8 + size_of::<T>();
+    if data.len() != expected_size {
+        return Err(DecodeError::InvalidSize {
+            expected: expected_size,
+            actual: data.len(),
+        });
+    }
+
+    // 记录数组是history的私有字段，通过全零的T（全零对所有枚举都是合法值）得到记录数组的位置与长度
+    let mut history: Box<T> = bytemuck::zeroed_box();
+    let records = history.records();
+    let records_offset = records.as_ptr() as usize - history.as_ref() as *const T as usize;
+    let record_size = size_of::<T::Record>();
+    for record_index in 0..records.len() {
+        let record_offset = 8 + records_offset + record_index * record_size;
+        for (offset, is_valid) in T::Record::ENUM_FIELDS {
+            let value = data[record_offset + offset];
+            if !is_valid(value) {
+                return Err(DecodeError::InvalidEnumValue {
+                    record_index,
+                    offset: *offset,
+                    value,
+                });
+            }
+        }
+    }
+
+    bytemuck::bytes_of_mut(history.as_mut()).copy_from_slice(&data[8..]);
+    Ok(history)
+}
+
+fn read_discriminator(data: &[u8]) -> Result<[u8; 8], DecodeError> {
+    data.get(..8)
+        .and_then(|discriminator| discriminator.try_into().ok())
+        .ok_or(DecodeError::MissingDiscriminator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+    use clearing_house::state::history::deposit_history::{DepositDirection, DepositRecord};
+    use clearing_house::state::history::order_history::OrderRecord;
+
+    // 模拟链上账户数据：discriminator + 账户内容
+    fn account_data<T: Discriminator + Pod>(history: &T) -> Vec<u8> {
+        let mut data = T::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(history));
+        data
+    }
+
+    fn deposit_history_with_records(count: u64) -> Box<DepositHistory> {
+        let mut history: Box<DepositHistory> = bytemuck::zeroed_box();
+        for amount in 1..=count {
+            let record_id = history.next_record_id();
+            history.append(
+                DepositRecord {
+                    record_id,
+                    amount,
+                    direction: DepositDirection::Deposit,
+                    ..DepositRecord::zeroed()
+                },
+                true,
+            );
+        }
+        history
+    }
+
+    #[test]
+    fn detect_kind() {
+        for kind in HistoryKind::ALL {
+            let mut data = kind.discriminator().to_vec();
+            data.extend_from_slice(&[0; 16]);
+            assert_eq!(HistoryKind::from_account_data(&data), Ok(kind));
+        }
+        assert_eq!(
+            HistoryKind::from_account_data(&[0; 4]),
+            Err(DecodeError::MissingDiscriminator)
+        );
+        assert_eq!(
+            HistoryKind::from_account_data(&[0; 16]),
+            Err(DecodeError::UnknownDiscriminator([0; 8]))
+        );
+    }
+
+    #[test]
+    fn reject_invalid_size() {
+        let history = deposit_history_with_records(1);
+        let mut data = account_data(history.as_ref());
+        data.pop();
+        assert_eq!(
+            decode_history::<DepositHistory>(&data).err(),
+            Some(DecodeError::InvalidSize {
+                expected: 8 + 147472,
+                actual: 8 + 147471,
+            })
+        );
+    }
+
+    #[test]
+    fn reject_discriminator_mismatch() {
+        let history = deposit_history_with_records(1);
+        let data = account_data(history.as_ref());
+        assert!(matches!(
+            decode_history::<OrderHistory>(&data),
+            Err(DecodeError::DiscriminatorMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn reject_invalid_enum_value() {
+        let history = deposit_history_with_records(2);
+        let mut data = account_data(history.as_ref());
+        // 把第2条记录的direction改为不存在的判别值
+        let offset = 8 + 16 + size_of::<DepositRecord>() + offset_of!(DepositRecord, direction);
+        data[offset] = 2;
+        assert_eq!(
+            decode_history::<DepositHistory>(&data).err(),
+            Some(DecodeError::InvalidEnumValue {
+                record_index: 1,
+                offset: offset_of!(DepositRecord, direction),
+                value: 2,
+            })
+        );
+
+        let mut history: Box<OrderHistory> = bytemuck::zeroed_box();
+        let record_id = history.next_record_id();
+        history.append(
+            OrderRecord {
+                record_id,
+                ..OrderRecord::zeroed()
+            },
+            true,
+        );
+        let mut data = account_data(history.as_ref());
+        let offset = 8 + 32 + offset_of!(OrderRecord, order.status);
+        data[offset] = 0xff;
+        assert!(matches!(
+            HistoryAccount::decode(&data),
+            Err(DecodeError::InvalidEnumValue {
+                record_index: 0,
+                value: 0xff,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn decode_chronological_records() {
+        // 写满并覆盖最旧的3条记录
+        let history = deposit_history_with_records(1027);
+        let data = account_data(history.as_ref());
+
+        let decoded = match HistoryAccount::decode(&data).unwrap() {
+            HistoryAccount::Deposit(history) => history,
+            _ => panic!("expected deposit history"),
+        };
+        let record_ids: Vec<u128> = decoded.iter().map(|record| record.record_id).collect();
+        assert_eq!(record_ids.len(), 1024);
+        assert_eq!(record_ids[0], 4);
+        assert_eq!(record_ids[1023], 1027);
+        assert!(record_ids.windows(2).all(|ids| ids[0] + 1 == ids[1]));
+    }
+
+    #[test]
+    fn decode_order_history() {
+        let mut history: Box<OrderHistory> = bytemuck::zeroed_box();
+        let record_id = history.next_record_id();
+        history.append(
+            OrderRecord {
+                record_id,
+                ..OrderRecord::zeroed()
+            },
+            true,
+        );
+        let data = account_data(history.as_ref());
+
+        assert!(matches!(
+            HistoryAccount::decode_account(&Pubkey::default(), &data),
+            Err(DecodeError::InvalidOwner(_))
+        ));
+        let decoded = HistoryAccount::decode_account(&clearing_house::ID, &data).unwrap();
+        assert_eq!(decoded.kind(), HistoryKind::Order);
+        let mut output = Vec::new();
+        decoded
+            .export(ExportFormat::JsonLines, &mut output)
+            .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap().lines().count(), 1);
+    }
+}
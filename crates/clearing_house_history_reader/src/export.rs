@@ -0,0 +1,595 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use anchor_lang::prelude::Pubkey;
+use clearing_house::controller::position::PositionDirection;
+use clearing_house::math::constant::{
+    AMM_RESERVE_PRECISION, MARGIN_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION, QUOTE_PRECISION,
+};
+use clearing_house::state::history::curve_history::CurveRecord;
+use clearing_house::state::history::deposit_history::{DepositDirection, DepositRecord};
+use clearing_house::state::history::funding_payment_history::FundingPaymentRecord;
+use clearing_house::state::history::funding_rate_history::FundingRateRecord;
+use clearing_house::state::history::liquidation_history::LiquidationRecord;
+use clearing_house::state::history::order_history::{OrderAction, OrderRecord};
+use clearing_house::state::history::trade_history::TradeRecord;
+use clearing_house::state::user_orders::{OrderStatus, OrderType};
+
+use crate::precision::format_unsigned_scaled;
+
+// 导出的数值：符号与绝对值分开保存，u128与i128的全部取值都可以原样输出
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Number {
+    negative: bool,
+    abs: u128,
+}
+
+impl From<u128> for Number {
+    fn from(value: u128) -> Number {
+        Number {
+            negative: false,
+            abs: value,
+        }
+    }
+}
+
+impl From<i128> for Number {
+    fn from(value: i128) -> Number {
+        Number {
+            negative: value < 0,
+            abs: value.unsigned_abs(),
+        }
+    }
+}
+
+macro_rules! impl_number_from {
+    ($($from:ty),*) => {
+        $(
+            impl From<$from> for Number {
+                fn from(value: $from) -> Number {
+                    Number::from(i128::from(value))
+                }
+            }
+        )*
+    };
+}
+
+impl_number_from!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl Number {
+    fn sign(&self) -> &'static str {
+        if self.negative {
+            "-"
+        } else {
+            ""
+        }
+    }
+}
+
+// 导出的单个字段
+pub enum Field {
+    // 原样输出的整数（时间戳、索引、record_id等）
+    Integer(Number),
+    // 按precision缩放后的十进制数（价格、数量等）
+    Decimal(Number, u128),
+    // 文本（地址、枚举等）
+    Text(String),
+}
+
+impl Field {
+    fn decimal(value: impl Into<Number>, precision: u128) -> Field {
+        Field::Decimal(value.into(), precision)
+    }
+
+    fn integer(value: impl Into<Number>) -> Field {
+        Field::Integer(value.into())
+    }
+
+    fn pubkey(value: &Pubkey) -> Field {
+        Field::Text(value.to_string())
+    }
+
+    fn text(value: &str) -> Field {
+        Field::Text(value.to_string())
+    }
+
+    // CSV单元格：包含分隔符、引号或换行的文本需要用引号包裹
+    fn to_csv(&self) -> String {
+        match self {
+            Field::Text(text) if text.contains([',', '"', '\n', '\r']) => {
+                format!("\"{}\"", text.replace('"', "\"\""))
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    // JSON值：数字直接输出以保留全部精度，文本输出为JSON字符串
+    fn to_json(&self) -> String {
+        match self {
+            Field::Text(text) => serde_json::Value::from(text.as_str()).to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Field::Integer(value) => write!(f, "{}{}", value.sign(), value.abs),
+            Field::Decimal(value, precision) => write!(
+                f,
+                "{}{}",
+                value.sign(),
+                format_unsigned_scaled(value.abs, *precision)
+            ),
+            Field::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+// 可以导出的历史记录，COLUMNS与fields()返回的字段一一对应
+pub trait ExportRecord {
+    const COLUMNS: &'static [&'static str];
+
+    fn fields(&self) -> Vec<Field>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat {
+    pub fn write<'a, R: ExportRecord + 'a>(
+        &self,
+        records: impl Iterator<Item = &'a R>,
+        writer: &mut impl Write,
+    ) -> io::Result<()> {
+        match self {
+            ExportFormat::Csv => write_csv(records, writer),
+            ExportFormat::JsonLines => write_json_lines(records, writer),
+        }
+    }
+}
+
+// 第一行为表头，之后每行一条记录
+pub fn write_csv<'a, R: ExportRecord + 'a>(
+    records: impl Iterator<Item = &'a R>,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(writer, "{}", R::COLUMNS.join(","))?;
+    for record in records {
+        let cells: Vec<String> = record.fields().iter().map(Field::to_csv).collect();
+        writeln!(writer, "{}", cells.join(","))?;
+    }
+    Ok(())
+}
+
+// 每行一个JSON对象
+pub fn write_json_lines<'a, R: ExportRecord + 'a>(
+    records: impl Iterator<Item = &'a R>,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    for record in records {
+        let entries: Vec<String> = R::COLUMNS
+            .iter()
+            .zip(record.fields())
+            .map(|(column, field)| format!("\"{}\":{}", column, field.to_json()))
+            .collect();
+        writeln!(writer, "{{{}}}", entries.join(","))?;
+    }
+    Ok(())
+}
+
+fn position_direction(direction: PositionDirection) -> Field {
+    Field::text(match direction {
+        PositionDirection::Long => "long",
+        PositionDirection::Short => "short",
+    })
+}
+
+impl ExportRecord for TradeRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "ts",
+        "record_id",
+        "market_index",
+        "user_authority",
+        "user",
+        "direction",
+        "base_asset_amount",
+        "quote_asset_amount",
+        "mark_price_before",
+        "mark_price_after",
+        "oracle_price",
+        "fee",
+        "referee_discount",
+        "token_discount",
+        "quote_asset_amount_surplus",
+        "liquidation",
+        "settlement",
+    ];
+
+    fn fields(&self) -> Vec<Field> {
+        vec![
+            Field::integer(self.ts),
+            Field::integer(self.record_id),
+            Field::integer(self.market_index),
+            Field::pubkey(&self.user_authority),
+            Field::pubkey(&self.user),
+            position_direction(self.direction),
+            Field::decimal(self.base_asset_amount, AMM_RESERVE_PRECISION),
+            Field::decimal(self.quote_asset_amount, QUOTE_PRECISION),
+            Field::decimal(self.mark_price_before, MARK_PRICE_PRECISION),
+            Field::decimal(self.mark_price_after, MARK_PRICE_PRECISION),
+            Field::decimal(self.oracle_price, MARK_PRICE_PRECISION),
+            Field::decimal(self.fee, QUOTE_PRECISION),
+            Field::decimal(self.referee_discount, QUOTE_PRECISION),
+            Field::decimal(self.token_discount, QUOTE_PRECISION),
+            Field::decimal(self.quote_asset_amount_surplus, QUOTE_PRECISION),
+            Field::integer(self.liquidation),
+            Field::integer(self.settlement),
+        ]
+    }
+}
+
+impl ExportRecord for DepositRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "ts",
+        "record_id",
+        "user_authority",
+        "user",
+        "direction",
+        "amount",
+        "collateral_before",
+        "cumulative_deposits_before",
+    ];
+
+    fn fields(&self) -> Vec<Field> {
+        vec![
+            Field::integer(self.ts),
+            Field::integer(self.record_id),
+            Field::pubkey(&self.user_authority),
+            Field::pubkey(&self.user),
+            Field::text(match self.direction {
+                DepositDirection::Deposit => "deposit",
+                DepositDirection::Withdraw => "withdraw",
+            }),
+            Field::decimal(self.amount, QUOTE_PRECISION),
+            Field::decimal(self.collateral_before, QUOTE_PRECISION),
+            Field::decimal(self.cumulative_deposits_before, QUOTE_PRECISION),
+        ]
+    }
+}
+
+impl ExportRecord for FundingPaymentRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "ts",
+        "record_id",
+        "market_index",
+        "user_authority",
+        "user",
+        "funding_payment",
+        "base_asset_amount",
+        "amm_cumulative_funding_long",
+        "amm_cumulative_funding_short",
+        "user_last_cumulative_funding",
+        "user_last_funding_rate_ts",
+    ];
+
+    fn fields(&self) -> Vec<Field> {
+        vec![
+            Field::integer(self.ts),
+            Field::integer(self.record_id),
+            Field::integer(self.market_index),
+            Field::pubkey(&self.user_authority),
+            Field::pubkey(&self.user),
+            Field::decimal(self.funding_payment, QUOTE_PRECISION),
+            Field::decimal(self.base_asset_amount, AMM_RESERVE_PRECISION),
+            Field::integer(self.amm_cumulative_funding_long),
+            Field::integer(self.amm_cumulative_funding_short),
+            Field::integer(self.user_last_cumulative_funding),
+            Field::integer(self.user_last_funding_rate_ts),
+        ]
+    }
+}
+
+impl ExportRecord for FundingRateRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "ts",
+        "record_id",
+        "market_index",
+        "funding_rate",
+        "cumulative_funding_rate_long",
+        "cumulative_funding_rate_short",
+        "oracle_price_twap",
+        "mark_price_twap",
+    ];
+
+    fn fields(&self) -> Vec<Field> {
+        vec![
+            Field::integer(self.ts),
+            Field::integer(self.record_id),
+            Field::integer(self.market_index),
+            Field::integer(self.funding_rate),
+            Field::integer(self.cumulative_funding_rate_long),
+            Field::integer(self.cumulative_funding_rate_short),
+            Field::decimal(self.oracle_price_twap, MARK_PRICE_PRECISION),
+            Field::decimal(self.mark_price_twap, MARK_PRICE_PRECISION),
+        ]
+    }
+}
+
+impl ExportRecord for LiquidationRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "ts",
+        "record_id",
+        "user_authority",
+        "user",
+        "liquidator",
+        "partial",
+        "base_asset_value",
+        "base_asset_value_closed",
+        "liquidation_fee",
+        "fee_to_liquidator",
+        "fee_to_insurance_fund",
+        "total_collateral",
+        "collateral",
+        "unrealized_pnl",
+        "margin_ratio",
+    ];
+
+    fn fields(&self) -> Vec<Field> {
+        vec![
+            Field::integer(self.ts),
+            Field::integer(self.record_id),
+            Field::pubkey(&self.user_authority),
+            Field::pubkey(&self.user),
+            Field::pubkey(&self.liquidator),
+            Field::integer(self.partial),
+            Field::decimal(self.base_asset_value, QUOTE_PRECISION),
+            Field::decimal(self.base_asset_value_closed, QUOTE_PRECISION),
+            Field::decimal(self.liquidation_fee, QUOTE_PRECISION),
+            Field::decimal(self.fee_to_liquidator, QUOTE_PRECISION),
+            Field::decimal(self.fee_to_insurance_fund, QUOTE_PRECISION),
+            Field::decimal(self.total_collateral, QUOTE_PRECISION),
+            Field::decimal(self.collateral, QUOTE_PRECISION),
+            Field::decimal(self.unrealized_pnl, QUOTE_PRECISION),
+            Field::decimal(self.margin_ratio, MARGIN_PRECISION),
+        ]
+    }
+}
+
+impl ExportRecord for CurveRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "ts",
+        "record_id",
+        "market_index",
+        "peg_multiplier_before",
+        "base_asset_reserve_before",
+        "quote_asset_reserve_before",
+        "sqrt_k_before",
+        "peg_multiplier_after",
+        "base_asset_reserve_after",
+        "quote_asset_reserve_after",
+        "sqrt_k_after",
+        "base_asset_amount_long",
+        "base_asset_amount_short",
+        "base_asset_amount",
+        "open_interest",
+        "total_fee",
+        "total_fee_minus_distributions",
+        "adjustment_cost",
+        "oracle_price",
+        "trade_record",
+    ];
+
+    fn fields(&self) -> Vec<Field> {
+        vec![
+            Field::integer(self.ts),
+            Field::integer(self.record_id),
+            Field::integer(self.market_index),
+            Field::decimal(self.peg_multiplier_before, PEG_PRECISION),
+            Field::decimal(self.base_asset_reserve_before, AMM_RESERVE_PRECISION),
+            Field::decimal(self.quote_asset_reserve_before, AMM_RESERVE_PRECISION),
+            Field::decimal(self.sqrt_k_before, AMM_RESERVE_PRECISION),
+            Field::decimal(self.peg_multiplier_after, PEG_PRECISION),
+            Field::decimal(self.base_asset_reserve_after, AMM_RESERVE_PRECISION),
+            Field::decimal(self.quote_asset_reserve_after, AMM_RESERVE_PRECISION),
+            Field::decimal(self.sqrt_k_after, AMM_RESERVE_PRECISION),
+            Field::decimal(self.base_asset_amount_long, AMM_RESERVE_PRECISION),
+            Field::decimal(self.base_asset_amount_short, AMM_RESERVE_PRECISION),
+            Field::decimal(self.base_asset_amount, AMM_RESERVE_PRECISION),
+            Field::integer(self.open_interest),
+            Field::decimal(self.total_fee, QUOTE_PRECISION),
+            Field::decimal(self.total_fee_minus_distributions, QUOTE_PRECISION),
+            Field::decimal(self.adjustment_cost, QUOTE_PRECISION),
+            Field::decimal(self.oracle_price, MARK_PRICE_PRECISION),
+            Field::integer(self.trade_record),
+        ]
+    }
+}
+
+impl ExportRecord for OrderRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "ts",
+        "record_id",
+        "action",
+        "user",
+        "authority",
+        "filler",
+        "order_id",
+        "user_order_id",
+        "market_index",
+        "order_type",
+        "status",
+        "direction",
+        "price",
+        "oracle_price_offset",
+        "trigger_price",
+        "base_asset_amount",
+        "quote_asset_amount",
+        "reduce_only",
+        "post_only",
+        "immediate_or_cancel",
+        "trade_record_id",
+        "base_asset_amount_filled",
+        "quote_asset_amount_filled",
+        "fee",
+        "filler_reward",
+        "quote_asset_amount_surplus",
+    ];
+
+    fn fields(&self) -> Vec<Field> {
+        let order = &self.order;
+        vec![
+            Field::integer(self.ts),
+            Field::integer(self.record_id),
+            Field::text(match self.action {
+                OrderAction::Place => "place",
+                OrderAction::Cancel => "cancel",
+                OrderAction::Fill => "fill",
+                OrderAction::Expire => "expire",
+            }),
+            Field::pubkey(&self.user),
+            Field::pubkey(&self.authority),
+            Field::pubkey(&self.filler),
+            Field::integer(order.order_id),
+            Field::integer(order.user_order_id),
+            Field::integer(order.market_index),
+            Field::text(match order.order_type {
+                OrderType::Market => "market",
+                OrderType::Limit => "limit",
+                OrderType::TriggerMarket => "trigger_market",
+                OrderType::TriggerLimit => "trigger_limit",
+            }),
+            Field::text(match order.status {
+                OrderStatus::Init => "init",
+                OrderStatus::Open => "open",
+            }),
+            position_direction(order.direction),
+            Field::decimal(order.price, MARK_PRICE_PRECISION),
+            Field::decimal(order.oracle_price_offset, MARK_PRICE_PRECISION),
+            Field::decimal(order.trigger_price, MARK_PRICE_PRECISION),
+            Field::decimal(order.base_asset_amount, AMM_RESERVE_PRECISION),
+            Field::decimal(order.quote_asset_amount, QUOTE_PRECISION),
+            Field::integer(order.reduce_only),
+            Field::integer(order.post_only),
+            Field::integer(order.immediate_or_cancel),
+            Field::integer(self.trade_record_id),
+            Field::decimal(self.base_asset_amount_filled, AMM_RESERVE_PRECISION),
+            Field::decimal(self.quote_asset_amount_filled, QUOTE_PRECISION),
+            Field::decimal(self.fee, QUOTE_PRECISION),
+            Field::decimal(self.filler_reward, QUOTE_PRECISION),
+            Field::decimal(self.quote_asset_amount_surplus, QUOTE_PRECISION),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    fn trade_record() -> TradeRecord {
+        TradeRecord {
+            ts: 1_700_000_000,
+            record_id: 7,
+            base_asset_amount: 15_000_000_000_000,
+            quote_asset_amount: 75_000_000,
+            mark_price_before: 500_000_000_000,
+            mark_price_after: 500_000_500_000,
+            oracle_price: -1,
+            fee: 75_000,
+            direction: PositionDirection::Short,
+            ..TradeRecord::zeroed()
+        }
+    }
+
+    #[test]
+    fn columns_match_fields() {
+        assert_eq!(
+            TradeRecord::COLUMNS.len(),
+            TradeRecord::zeroed().fields().len()
+        );
+        assert_eq!(
+            DepositRecord::COLUMNS.len(),
+            DepositRecord::zeroed().fields().len()
+        );
+        assert_eq!(
+            FundingPaymentRecord::COLUMNS.len(),
+            FundingPaymentRecord::zeroed().fields().len()
+        );
+        assert_eq!(
+            FundingRateRecord::COLUMNS.len(),
+            FundingRateRecord::zeroed().fields().len()
+        );
+        assert_eq!(
+            LiquidationRecord::COLUMNS.len(),
+            LiquidationRecord::zeroed().fields().len()
+        );
+        assert_eq!(
+            CurveRecord::COLUMNS.len(),
+            CurveRecord::zeroed().fields().len()
+        );
+        assert_eq!(
+            OrderRecord::COLUMNS.len(),
+            OrderRecord::zeroed().fields().len()
+        );
+    }
+
+    #[test]
+    fn export_csv() {
+        let records = [trade_record()];
+        let mut output = Vec::new();
+        write_csv(records.iter(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(lines.next().unwrap(), TradeRecord::COLUMNS.join(","));
+        let cells: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(cells[0], "1700000000");
+        assert_eq!(cells[1], "7");
+        assert_eq!(cells[5], "short");
+        assert_eq!(cells[6], "1.5000000000000");
+        assert_eq!(cells[7], "75.000000");
+        assert_eq!(cells[9], "50.0000500000");
+        assert_eq!(cells[10], "-0.0000000001");
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn export_json_lines() {
+        let records = [trade_record(), trade_record()];
+        let mut output = Vec::new();
+        write_json_lines(records.iter(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 2);
+        let value: serde_json::Value =
+            serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        assert_eq!(value["record_id"], 7);
+        assert_eq!(value["direction"], "short");
+        assert_eq!(value["user"], Pubkey::default().to_string());
+        assert_eq!(value["quote_asset_amount"], 75.0);
+    }
+
+    #[test]
+    fn export_u128_beyond_i128() {
+        assert_eq!(
+            Field::integer(u128::MAX).to_string(),
+            "340282366920938463463374607431768211455"
+        );
+        assert_eq!(
+            Field::decimal(u128::MAX, QUOTE_PRECISION).to_string(),
+            "340282366920938463463374607431768.211455"
+        );
+        assert_eq!(Field::integer(i128::MIN).to_string(), i128::MIN.to_string());
+    }
+
+    #[test]
+    fn quote_csv_text() {
+        assert_eq!(Field::text("a,\"b\"").to_csv(), "\"a,\"\"b\"\"\"");
+        assert_eq!(Field::text("plain").to_csv(), "plain");
+    }
+}
@@ -0,0 +1,7 @@
+// 链下读取clearing_house的各history账户：校验账户数据，按时间顺序解出记录，并导出为CSV或JSON lines
+pub mod decode;
+pub mod export;
+pub mod precision;
+
+pub use decode::{decode_history, DecodeError, HistoryAccount, HistoryKind};
+pub use export::{write_csv, write_json_lines, ExportFormat, ExportRecord, Field};
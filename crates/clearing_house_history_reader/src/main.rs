@@ -0,0 +1,45 @@
+use std::process::ExitCode;
+
+use clearing_house_history_reader::{ExportFormat, HistoryAccount};
+
+const USAGE: &str = "usage: clearing_house_history_reader <account-data-file> [csv|json]
+
+account-data-file: raw account data, e.g. written by `solana account <address> --output-file <file>`
+format:            csv (default) or json (JSON lines)";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (path, format) = match args.as_slice() {
+        [path] => (path, ExportFormat::Csv),
+        [path, format] if format == "csv" => (path, ExportFormat::Csv),
+        [path, format] if format == "json" => (path, ExportFormat::JsonLines),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("failed to read {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let history = match HistoryAccount::decode(&data) {
+        Ok(history) => history,
+        Err(err) => {
+            eprintln!("failed to decode {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    if let Err(err) = history.export(format, &mut stdout) {
+        eprintln!("failed to export {:?} history: {}", history.kind(), err);
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,56 @@
+// 把按precision放大后的整数还原成十进制字符串，小数位数为precision的位数减1
+// 例如 format_scaled(-1_500_000, 1_000_000) == "-1.500000"
+pub fn format_scaled(value: i128, precision: u128) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    format!(
+        "{}{}",
+        sign,
+        format_unsigned_scaled(value.unsigned_abs(), precision)
+    )
+}
+
+// 与format_scaled相同，但接受u128，超出i128范围的值也能原样还原
+pub fn format_unsigned_scaled(value: u128, precision: u128) -> String {
+    let decimals = precision.to_string().len() - 1;
+    let integer = value / precision;
+    let fraction = value % precision;
+    if decimals == 0 {
+        format!("{}", integer)
+    } else {
+        format!("{}.{:0width$}", integer, fraction, width = decimals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clearing_house::math::constant::{MARK_PRICE_PRECISION, QUOTE_PRECISION};
+
+    #[test]
+    fn format_positive() {
+        assert_eq!(
+            format_scaled(500_000_500_000, MARK_PRICE_PRECISION),
+            "50.0000500000"
+        );
+        assert_eq!(format_scaled(1, QUOTE_PRECISION), "0.000001");
+    }
+
+    #[test]
+    fn format_negative() {
+        assert_eq!(format_scaled(-1_500_000, QUOTE_PRECISION), "-1.500000");
+        assert_eq!(format_scaled(-1, QUOTE_PRECISION), "-0.000001");
+    }
+
+    #[test]
+    fn format_u128_max() {
+        assert_eq!(
+            format_unsigned_scaled(u128::MAX, QUOTE_PRECISION),
+            "340282366920938463463374607431768.211455"
+        );
+    }
+
+    #[test]
+    fn format_without_decimals() {
+        assert_eq!(format_scaled(42, 1), "42");
+    }
+}
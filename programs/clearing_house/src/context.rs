@@ -1,3 +1,5 @@
+use crate::controller::position::PositionDirection;
+use crate::errors::Errors;
 use crate::state::{
     history::{
         curve_history::CurveHistory, deposit_history::DepositHistory,
@@ -5,11 +7,14 @@ use crate::state::{
         liquidation_history::LiquidationHistory, order_history::OrderHistory,
         trade_history::TradeHistory,
     },
-    market::Markets,
+    market::{MarketAccount, Markets},
     order_state::OrderState,
     state::State,
+    user::{User, UserPositions},
+    user_orders::{OrderTriggerCondition, OrderTriggerPriceSource, OrderType, UserOrders},
 };
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 use std::mem::size_of;
 
@@ -18,8 +23,14 @@ pub struct Initialize<'info> {
     // 该signer会成为State中的admin
     #[account(mut)]
     pub admin: Signer<'info>,
-    // 1. 创建pda，用于存储State
-    #[account(zero)]
+    // 1. 创建pda，用于存储State（seeds为[b"clearing_house"]，每个program只有一个State）
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<State>(),
+        seeds = [b"clearing_house".as_ref()],
+        bump,
+    )]
     pub state: AccountLoader<'info, State>,
     // 2. 抵押品mint
     pub collateral_mint: Box<Account<'info, Mint>>,
@@ -49,19 +60,110 @@ pub struct Initialize<'info> {
     // 6. 保证金vault的authority
     /// CHECK: checked in `initialize`
     pub insurance_vault_authority: UncheckedAccount<'info>,
-    // 7. markets账户
-    #[account(zero)]
-    pub markets: AccountLoader<'info, Markets>,
+    // 7. 创建markets pda（seeds为[b"markets"]）
+    // Markets超过了CPI创建账户的大小上限，这里只分配MAX_PERMITTED_DATA_INCREASE字节，
+    // 之后由admin调用resize_markets扩容到完整大小后再初始化
+    /// CHECK: initialized in `resize_markets`
+    #[account(
+        init,
+        payer = admin,
+        space = MAX_PERMITTED_DATA_INCREASE,
+        seeds = [b"markets".as_ref()],
+        bump,
+    )]
+    pub markets: UncheckedAccount<'info>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct ResizeMarkets<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    /// CHECK: checked in `resize_markets`
+    #[account(
+        mut,
+        seeds = [b"markets".as_ref()],
+        bump = state.load()?.markets_bump,
+    )]
+    pub markets: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateState<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    // 旧版本的State可能小于当前的State，不能用AccountLoader加载，admin在`migrate_state`中检查
+    /// CHECK: checked in `migrate_state`
+    #[account(
+        mut,
+        seeds = [b"clearing_house".as_ref()],
+        bump,
+    )]
+    pub state: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMarkets<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    /// CHECK: checked in `migrate_markets`
+    #[account(
+        mut,
+        seeds = [b"markets".as_ref()],
+        bump = state.load()?.markets_bump,
+    )]
+    pub markets: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateOrderState<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        has_one = order_state,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    /// CHECK: checked in `migrate_order_state`
+    #[account(
+        mut,
+        seeds = [b"order_state".as_ref()],
+        bump,
+    )]
+    pub order_state: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct InitializeHistory<'info> {
     pub admin: Signer<'info>,
     #[account(
         mut,
-        has_one = admin
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
     )]
     pub state: AccountLoader<'info, State>,
     #[account(zero)]
@@ -78,13 +180,70 @@ pub struct InitializeHistory<'info> {
     pub curve_history: AccountLoader<'info, CurveHistory>,
 }
 
+#[derive(Accounts)]
+pub struct InitializeExtendedCurveHistory<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    // 记录格式与curve_history相同
+    #[account(zero)]
+    pub extended_curve_history: AccountLoader<'info, CurveHistory>,
+}
+
+// 可以被rotate_history替换的history账户类型
+#[derive(Clone, Copy, AnchorSerialize, AnchorDeserialize)]
+pub enum HistoryType {
+    Trade,
+    Deposit,
+    FundingPayment,
+    FundingRate,
+    Liquidation,
+    Curve,
+    ExtendedCurve,
+    Order,
+}
+
+#[derive(Accounts)]
+pub struct RotateHistory<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    /// CHECK: checked in `rotate_history`
+    pub old_history: UncheckedAccount<'info>,
+    /// CHECK: checked in `rotate_history`
+    #[account(mut)]
+    pub new_history: UncheckedAccount<'info>,
+    // order history的地址记录在OrderState中，只有替换order history时需要传入
+    #[account(
+        mut,
+        constraint = order_state.key().eq(&state.load()?.order_state) @ Errors::InvalidHistoryAccount,
+        constraint = order_state.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub order_state: Option<Box<Account<'info, OrderState>>>,
+}
+
 #[derive(Accounts)]
 pub struct InitializeOrderState<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
     #[account(
         mut,
-        has_one = admin
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
     )]
     pub state: AccountLoader<'info, State>,
     #[account(
@@ -99,3 +258,644 @@ pub struct InitializeOrderState<'info> {
     pub order_history: AccountLoader<'info, OrderHistory>,
     pub system_program: Program<'info, System>,
 }
+
+#[derive(Accounts)]
+pub struct InitializeMarket<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        seeds = [b"markets".as_ref()],
+        bump = state.load()?.markets_bump,
+        constraint = Markets::is_current_version(&markets.to_account_info())? @ Errors::AccountVersionMismatch,
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    /// CHECK: checked in `initialize_market`
+    pub oracle: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u64)]
+pub struct InitializeMarketAccount<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<MarketAccount>(),
+        seeds = [b"market".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub market_account: AccountLoader<'info, MarketAccount>,
+    /// CHECK: checked in `initialize_market_account`
+    pub oracle: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u64)]
+pub struct MigrateMarketToAccount<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        seeds = [b"markets".as_ref()],
+        bump = state.load()?.markets_bump,
+        constraint = Markets::is_current_version(&markets.to_account_info())? @ Errors::AccountVersionMismatch,
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<MarketAccount>(),
+        seeds = [b"market".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub market_account: AccountLoader<'info, MarketAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdminUpdateState<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub pending_admin: Signer<'info>,
+    #[account(
+        mut,
+        constraint = state.load()?.pending_admin.eq(pending_admin.key) @ Errors::NotPendingAdmin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+}
+
+#[derive(Accounts)]
+pub struct AdminUpdateMarket<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    // 涉及的market都已迁移到MarketAccount（通过remaining_accounts传入）时可以不传markets
+    #[account(
+        mut,
+        seeds = [b"markets".as_ref()],
+        bump = state.load()?.markets_bump,
+        constraint = Markets::is_current_version(&markets.to_account_info())? @ Errors::AccountVersionMismatch,
+    )]
+    pub markets: Option<AccountLoader<'info, Markets>>,
+}
+
+#[derive(Accounts)]
+pub struct SettleMarket<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        has_one = order_state,
+        has_one = trade_history,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    // 涉及的market都已迁移到MarketAccount（通过remaining_accounts传入）时可以不传markets
+    #[account(
+        mut,
+        seeds = [b"markets".as_ref()],
+        bump = state.load()?.markets_bump,
+        constraint = Markets::is_current_version(&markets.to_account_info())? @ Errors::AccountVersionMismatch,
+    )]
+    pub markets: Option<AccountLoader<'info, Markets>>,
+    #[account(mut)]
+    pub trade_history: AccountLoader<'info, TradeHistory>,
+    #[account(
+        constraint = order_state.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    // 重置market时读取最近的order_id，重置前下的订单不能在重新初始化的market上成交
+    #[account(
+        constraint = order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+    /// CHECK: checked in `settle_market`
+    pub oracle: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AdminUpdateMarketOracle<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    // 涉及的market都已迁移到MarketAccount（通过remaining_accounts传入）时可以不传markets
+    #[account(
+        mut,
+        seeds = [b"markets".as_ref()],
+        bump = state.load()?.markets_bump,
+        constraint = Markets::is_current_version(&markets.to_account_info())? @ Errors::AccountVersionMismatch,
+    )]
+    pub markets: Option<AccountLoader<'info, Markets>>,
+    /// CHECK: checked in `update_market_oracle`
+    pub oracle: UncheckedAccount<'info>,
+    // market当前使用的oracle，用于读取实时的oracle TWAP
+    /// CHECK: checked in `update_market_oracle`
+    pub current_oracle: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUser<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + size_of::<User>(),
+        seeds = [b"user".as_ref(), authority.key.as_ref()],
+        bump,
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + size_of::<UserPositions>(),
+        seeds = [b"user_positions".as_ref(), authority.key.as_ref()],
+        bump,
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    // 当state中设置了whitelist_mint时，必须传入authority持有的该mint的token account
+    pub whitelist_token: Option<Box<Account<'info, TokenAccount>>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUserOrders<'info> {
+    #[account(
+        has_one = authority,
+        seeds = [b"user".as_ref(), authority.key.as_ref()],
+        bump,
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + size_of::<UserOrders>(),
+        seeds = [b"user_orders".as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeleteUserOrders<'info> {
+    #[account(
+        has_one = authority,
+        seeds = [b"user".as_ref(), authority.key.as_ref()],
+        bump,
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        has_one = user,
+        close = authority,
+        seeds = [b"user_orders".as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct OrderParams {
+    pub order_type: OrderType,
+    pub direction: PositionDirection,
+    pub user_order_id: u8,
+    pub quote_asset_amount: u128,
+    pub base_asset_amount: u128,
+    pub price: u128,
+    pub market_index: u64,
+    pub reduce_only: bool,
+    pub post_only: bool,
+    pub immediate_or_cancel: bool,
+    pub trigger_price: u128,
+    pub trigger_condition: OrderTriggerCondition,
+    pub trigger_price_source: OrderTriggerPriceSource,
+    pub oracle_price_offset: i128,
+    // 订单的最长存活时间（秒），为None时使用OrderState中的默认值，为Some(0)时永不过期
+    pub max_age: Option<u32>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceOrder<'info> {
+    #[account(
+        has_one = order_state,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"user".as_ref(), authority.key.as_ref()],
+        bump,
+    )]
+    pub user: Box<Account<'info, User>>,
+    pub authority: Signer<'info>,
+    // 涉及的market都已迁移到MarketAccount（通过remaining_accounts传入）时可以不传markets
+    #[account(
+        seeds = [b"markets".as_ref()],
+        bump = state.load()?.markets_bump,
+        constraint = Markets::is_current_version(&markets.to_account_info())? @ Errors::AccountVersionMismatch,
+    )]
+    pub markets: Option<AccountLoader<'info, Markets>>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        constraint = order_state.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        mut,
+        constraint = order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+    /// CHECK: checked in `place_order`
+    pub oracle: UncheckedAccount<'info>,
+    // 可选：用户持有的折扣代币账户，用于确定订单的手续费折扣等级
+    #[account(
+        constraint = discount_token.mint.eq(&state.load()?.discount_mint) @ Errors::InvalidDiscountToken,
+        constraint = discount_token.owner.eq(authority.key) @ Errors::InvalidDiscountToken
+    )]
+    pub discount_token: Option<Box<Account<'info, TokenAccount>>>,
+    // 可选：用户的推荐人（不能是用户自己）
+    #[account(
+        constraint = !referrer.key().eq(&user.key()) @ Errors::InvalidReferrer
+    )]
+    pub referrer: Option<Box<Account<'info, User>>>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceAndFillOrder<'info> {
+    #[account(
+        has_one = order_state,
+        has_one = trade_history,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"user".as_ref(), authority.key.as_ref()],
+        bump,
+    )]
+    pub user: Box<Account<'info, User>>,
+    pub authority: Signer<'info>,
+    // 涉及的market都已迁移到MarketAccount（通过remaining_accounts传入）时可以不传markets
+    #[account(
+        mut,
+        seeds = [b"markets".as_ref()],
+        bump = state.load()?.markets_bump,
+        constraint = Markets::is_current_version(&markets.to_account_info())? @ Errors::AccountVersionMismatch,
+    )]
+    pub markets: Option<AccountLoader<'info, Markets>>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        constraint = order_state.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        mut,
+        constraint = order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+    #[account(mut)]
+    pub trade_history: AccountLoader<'info, TradeHistory>,
+    /// CHECK: checked in `place_order`
+    pub oracle: UncheckedAccount<'info>,
+    // 可选：用户持有的折扣代币账户，用于确定订单的手续费折扣等级
+    #[account(
+        constraint = discount_token.mint.eq(&state.load()?.discount_mint) @ Errors::InvalidDiscountToken,
+        constraint = discount_token.owner.eq(authority.key) @ Errors::InvalidDiscountToken
+    )]
+    pub discount_token: Option<Box<Account<'info, TokenAccount>>>,
+    // 可选：用户的推荐人（不能是用户自己），同时接收本次成交的推荐人奖励
+    #[account(
+        mut,
+        constraint = !referrer.key().eq(&user.key()) @ Errors::InvalidReferrer
+    )]
+    pub referrer: Option<Box<Account<'info, User>>>,
+}
+
+#[derive(Accounts)]
+pub struct SettlePosition<'info> {
+    #[account(
+        has_one = order_state,
+        has_one = trade_history,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"user".as_ref(), authority.key.as_ref()],
+        bump,
+    )]
+    pub user: Box<Account<'info, User>>,
+    pub authority: Signer<'info>,
+    // 涉及的market都已迁移到MarketAccount（通过remaining_accounts传入）时可以不传markets
+    #[account(
+        mut,
+        seeds = [b"markets".as_ref()],
+        bump = state.load()?.markets_bump,
+        constraint = Markets::is_current_version(&markets.to_account_info())? @ Errors::AccountVersionMismatch,
+    )]
+    pub markets: Option<AccountLoader<'info, Markets>>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        constraint = order_state.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        mut,
+        constraint = order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+    #[account(mut)]
+    pub trade_history: AccountLoader<'info, TradeHistory>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(
+        has_one = order_state,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        has_one = authority,
+        seeds = [b"user".as_ref(), authority.key.as_ref()],
+        bump,
+    )]
+    pub user: Box<Account<'info, User>>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        constraint = order_state.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        mut,
+        constraint = order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+}
+
+#[derive(Accounts)]
+pub struct DepositCollateral<'info> {
+    #[account(
+        has_one = collateral_vault,
+        has_one = deposit_history,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"user".as_ref(), authority.key.as_ref()],
+        bump,
+    )]
+    pub user: Box<Account<'info, User>>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub collateral_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = user_collateral_account.mint.eq(&collateral_vault.mint)
+    )]
+    pub user_collateral_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub deposit_history: AccountLoader<'info, DepositHistory>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct FillOrder<'info> {
+    #[account(
+        has_one = order_state,
+        has_one = trade_history,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    pub authority: Signer<'info>,
+    // filler的User账户，用于接收filler奖励（用户成交自己的订单需使用place_and_fill_order）
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = !filler.key().eq(&user.key()) @ Errors::InvalidFiller
+    )]
+    pub filler: Box<Account<'info, User>>,
+    #[account(mut)]
+    pub user: Box<Account<'info, User>>,
+    // 涉及的market都已迁移到MarketAccount（通过remaining_accounts传入）时可以不传markets
+    #[account(
+        mut,
+        seeds = [b"markets".as_ref()],
+        bump = state.load()?.markets_bump,
+        constraint = Markets::is_current_version(&markets.to_account_info())? @ Errors::AccountVersionMismatch,
+    )]
+    pub markets: Option<AccountLoader<'info, Markets>>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        constraint = order_state.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        mut,
+        constraint = order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+    #[account(mut)]
+    pub trade_history: AccountLoader<'info, TradeHistory>,
+    /// CHECK: checked in `fill_order`
+    pub oracle: UncheckedAccount<'info>,
+    // 订单下单时锁定了推荐人时必须传入，用于接收推荐人奖励（不能与filler是同一个账户）
+    #[account(
+        mut,
+        constraint = !referrer.key().eq(&user.key()) @ Errors::InvalidReferrer,
+        constraint = !referrer.key().eq(&filler.key()) @ Errors::InvalidReferrer
+    )]
+    pub referrer: Option<Box<Account<'info, User>>>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireOrders<'info> {
+    #[account(
+        has_one = order_state,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    pub authority: Signer<'info>,
+    // 调用者的User账户，用于接收清理过期订单的奖励
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = !filler.key().eq(&user.key()) @ Errors::InvalidFiller
+    )]
+    pub filler: Box<Account<'info, User>>,
+    #[account(mut)]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        constraint = order_state.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        mut,
+        constraint = order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+}
+
+#[derive(Accounts)]
+pub struct AdminUpdateOrderState<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin,
+        has_one = order_state,
+        seeds = [b"clearing_house".as_ref()],
+        bump = state.load()?.bump,
+        constraint = state.load()?.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        constraint = order_state.is_current_version() @ Errors::AccountVersionMismatch,
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+}
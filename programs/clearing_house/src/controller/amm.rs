@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::controller::position::PositionDirection;
+use crate::math::amm::{calculate_swap_output, reserve_to_asset_amount, SwapDirection};
+use crate::state::market::AMM;
+
+// 用base_asset_swap_amount数量的base资产按direction与AMM交易，更新AMM储备并返回对应的quote资产数量（QUOTE_PRECISION精度）
+pub fn swap_base_asset(
+    amm: &mut AMM,
+    base_asset_swap_amount: u128,
+    direction: PositionDirection,
+) -> Result<u128> {
+    // 做多时从AMM中取出base，做空时向AMM中放入base
+    let swap_direction = match direction {
+        PositionDirection::Long => SwapDirection::Remove,
+        PositionDirection::Short => SwapDirection::Add,
+    };
+    let (new_quote_asset_reserve, new_base_asset_reserve) = calculate_swap_output(
+        base_asset_swap_amount,
+        amm.base_asset_reserve,
+        swap_direction,
+        amm.sqrt_k,
+    )?;
+
+    let quote_asset_reserve_change = new_quote_asset_reserve.abs_diff(amm.quote_asset_reserve);
+    let quote_asset_amount =
+        reserve_to_asset_amount(quote_asset_reserve_change, amm.peg_multiplier)?;
+
+    amm.base_asset_reserve = new_base_asset_reserve;
+    amm.quote_asset_reserve = new_quote_asset_reserve;
+
+    Ok(quote_asset_amount)
+}
@@ -0,0 +1,51 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+use anchor_lang::{Owner, ZeroCopy};
+
+use crate::errors::Errors;
+use crate::state::history::ring_buffer::RingBuffer;
+
+// 用一个新的空history账户替换旧的history账户：旧账户保持不变作为存档，
+// 新账户写入discriminator，并从旧账户最后一条记录的record_id继续编号
+// carry_over用于迁移record_id以外需要延续的数据（例如OrderHistory的last_order_id）
+pub fn rotate_history<T: RingBuffer + ZeroCopy + Owner>(
+    old_history: &AccountInfo,
+    new_history: &AccountInfo,
+    carry_over: impl FnOnce(&T, &mut T),
+) -> Result<()> {
+    let old_data = old_history.try_borrow_data()?;
+    if old_history.owner != &T::owner() {
+        return err!(ErrorCode::AccountOwnedByWrongProgram);
+    }
+    if old_data.len() != 8 + size_of::<T>() || old_data[..8] != T::DISCRIMINATOR {
+        return err!(Errors::InvalidHistoryAccount);
+    }
+    let old: &T = bytemuck::from_bytes(&old_data[8..]);
+
+    // 与#[account(zero)]相同的检查：新账户必须属于本program、未被初始化且免租
+    if new_history.owner != &T::owner() {
+        return err!(ErrorCode::AccountOwnedByWrongProgram);
+    }
+    if !new_history.is_writable {
+        return err!(ErrorCode::AccountNotMutable);
+    }
+    if !Rent::get()?.is_exempt(new_history.lamports(), new_history.data_len()) {
+        return err!(ErrorCode::ConstraintRentExempt);
+    }
+    let mut new_data = new_history.try_borrow_mut_data()?;
+    if new_data.len() != 8 + size_of::<T>() {
+        return err!(Errors::InvalidHistoryAccount);
+    }
+    if new_data[..8] != [0; 8] {
+        return err!(ErrorCode::ConstraintZero);
+    }
+
+    new_data[..8].copy_from_slice(&T::DISCRIMINATOR);
+    let new: &mut T = bytemuck::from_bytes_mut(&mut new_data[8..]);
+    let last_record_id = old.next_record_id() - 1;
+    new.set_last_record_id(last_record_id.try_into().map_err(|_| Errors::MathError)?);
+    carry_over(old, new);
+
+    Ok(())
+}
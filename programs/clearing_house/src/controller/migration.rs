@@ -0,0 +1,133 @@
+use std::mem::{offset_of, size_of};
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+
+use crate::errors::Errors;
+use crate::state::market::{Markets, MARKETS_VERSION};
+use crate::state::order_state::{OrderState, ORDER_STATE_VERSION};
+use crate::state::state::{State, STATE_VERSION};
+
+// 把program拥有的账户扩容到new_len字节（新增部分置零），不足免租的lamports由payer补齐
+// 单条指令最多只能扩容MAX_PERMITTED_DATA_INCREASE字节
+pub fn realloc_account<'info>(
+    account: &AccountInfo<'info>,
+    new_len: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    if account.data_len() >= new_len {
+        return Ok(());
+    }
+
+    let rent_shortfall = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(account.lamports());
+    if rent_shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            rent_shortfall,
+        )?;
+    }
+    account.realloc(new_len, true)?;
+
+    Ok(())
+}
+
+// 把State账户迁移到STATE_VERSION：先扩容到当前State的大小，再按版本依次初始化新增字段
+// 新增字段只会追加在末尾，所以扩容前按偏移量从旧账户中读取admin与version
+pub fn migrate_state<'info>(
+    state: &AccountInfo<'info>,
+    admin: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    check_discriminator::<State>(state)?;
+    {
+        let data = state.try_borrow_data()?;
+        let admin_offset = 8 + offset_of!(State, admin);
+        let version_offset = 8 + offset_of!(State, version);
+        if data.len() <= version_offset {
+            return err!(ErrorCode::AccountDidNotDeserialize);
+        }
+
+        let state_admin = Pubkey::try_from(&data[admin_offset..admin_offset + 32])
+            .map_err(|_| ErrorCode::AccountDidNotDeserialize)?;
+        require_keys_eq!(state_admin, admin.key(), ErrorCode::ConstraintHasOne);
+        if data[version_offset] >= STATE_VERSION && data.len() >= 8 + size_of::<State>() {
+            return err!(Errors::AccountAlreadyMigrated);
+        }
+    }
+    realloc_account(state, 8 + size_of::<State>(), admin, system_program)?;
+
+    let mut data = state.try_borrow_mut_data()?;
+    let state: &mut State = bytemuck::from_bytes_mut(&mut data[8..8 + size_of::<State>()]);
+    // 版本0 -> 1：布局不变，只是开始使用version字段（原padding0中的一个字节，值为0）
+    state.version = STATE_VERSION;
+
+    Ok(())
+}
+
+// 把Markets账户迁移到MARKETS_VERSION：旧版本的账户是当前布局的前缀，扩容后新增部分为0
+pub fn migrate_markets<'info>(
+    markets: &AccountInfo<'info>,
+    admin: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    check_discriminator::<Markets>(markets)?;
+    if Markets::is_current_version(markets)? {
+        return err!(Errors::AccountAlreadyMigrated);
+    }
+    realloc_account(markets, 8 + size_of::<Markets>(), admin, system_program)?;
+
+    let mut data = markets.try_borrow_mut_data()?;
+    let markets: &mut Markets = bytemuck::from_bytes_mut(&mut data[8..]);
+    // 版本0 -> 1：在末尾追加version和padding，64个Market的数据保持不变
+    markets.version = MARKETS_VERSION;
+
+    Ok(())
+}
+
+// 把OrderState账户迁移到ORDER_STATE_VERSION
+// OrderState是borsh序列化的账户，扩容后重新反序列化，新增字段从账户末尾的0字节中读出
+pub fn migrate_order_state<'info>(
+    order_state: &AccountInfo<'info>,
+    admin: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    realloc_account(
+        order_state,
+        8 + size_of::<OrderState>(),
+        admin,
+        system_program,
+    )?;
+
+    let mut data = order_state.try_borrow_mut_data()?;
+    let mut migrated = OrderState::try_deserialize(&mut &data[..])?;
+    if migrated.version >= ORDER_STATE_VERSION {
+        return err!(Errors::AccountAlreadyMigrated);
+    }
+
+    // 版本0 -> 1：只是开始使用version字段
+    migrated.version = ORDER_STATE_VERSION;
+    migrated.try_serialize(&mut &mut data[..])?;
+
+    Ok(())
+}
+
+fn check_discriminator<T: Discriminator>(account: &AccountInfo) -> Result<()> {
+    let data = account.try_borrow_data()?;
+    if data.len() < 8 {
+        return err!(ErrorCode::AccountDiscriminatorNotFound);
+    }
+    if data[..8] != T::DISCRIMINATOR {
+        return err!(ErrorCode::AccountDiscriminatorMismatch);
+    }
+    Ok(())
+}
@@ -1 +1,7 @@
+pub mod amm;
+pub mod history;
+pub mod migration;
+pub mod orders;
 pub mod position;
+pub mod settlement;
+pub mod token;
@@ -0,0 +1,618 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+use bytemuck::Zeroable;
+
+use crate::context::OrderParams;
+use crate::controller::amm::swap_base_asset;
+use crate::controller::position::{
+    get_position_update_type, signed_base_asset_amount, update_position_with_base_asset_amount,
+    PositionDirection,
+};
+use crate::errors::Errors;
+use crate::math::amm::{
+    calculate_base_asset_amount_for_quote, calculate_base_asset_amount_to_trade_to_price,
+    calculate_price,
+};
+use crate::math::fees::{calculate_fee_for_order, calculate_order_fee_tier};
+use crate::math::margin::meets_initial_margin_requirement;
+use crate::math::oracle::is_oracle_valid;
+use crate::math::orders::{calculate_limit_price, get_trigger_reference_price};
+use crate::state::history::order_history::{OrderAction, OrderHistory, OrderRecord};
+use crate::state::history::ring_buffer::RingBuffer;
+use crate::state::history::trade_history::{TradeHistory, TradeRecord};
+use crate::state::market_map::MarketMap;
+use crate::state::order_state::OrderState;
+use crate::state::state::State;
+use crate::state::user::{User, UserPositions};
+use crate::state::user_orders::{Order, OrderStatus, OrderType, UserOrders};
+use crate::validation::order::validate_order;
+
+// 用户第一次带推荐人下单时把推荐人锁定到User上，之后的订单只能使用同一个推荐人，
+// 避免用户在不同订单间切换推荐人（推荐人也不能属于用户自己的authority）
+pub fn lock_referrer(user: &mut User, referrer: Option<&Account<User>>) -> Result<Option<Pubkey>> {
+    let referrer = match referrer {
+        Some(referrer) => referrer,
+        None => return Ok(None),
+    };
+    if referrer.authority.eq(&user.authority) {
+        return err!(Errors::InvalidReferrer);
+    }
+
+    if user.referrer.eq(&Pubkey::default()) {
+        user.referrer = referrer.key();
+    } else if !user.referrer.eq(&referrer.key()) {
+        return err!(Errors::InvalidReferrer);
+    }
+
+    Ok(Some(referrer.key()))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn place_order(
+    state: &State,
+    order_state: &OrderState,
+    user: Pubkey,
+    authority: Pubkey,
+    user_positions: &mut UserPositions,
+    markets: &MarketMap,
+    user_orders: &mut UserOrders,
+    order_history: &mut OrderHistory,
+    oracle: &AccountInfo,
+    discount_token: Option<&TokenAccount>,
+    referrer: Option<Pubkey>,
+    clock: &Clock,
+    params: OrderParams,
+) -> Result<()> {
+    let now = clock.unix_timestamp;
+
+    let market = markets.get_market(params.market_index)?;
+    market.validate_status(params.reduce_only)?;
+    require_keys_eq!(oracle.key(), market.amm.oracle, Errors::InvalidOracle);
+
+    // user_order_id为0表示用户未设置，不做唯一性检查
+    if params.user_order_id != 0
+        && user_orders
+            .get_order_index_by_user_order_id(params.user_order_id)
+            .is_ok()
+    {
+        return err!(Errors::DuplicateUserOrderId);
+    }
+    let new_order_index = user_orders.get_unused_order_index()?;
+
+    // 挂单也会占用用户在该market上的头寸槽位，直到订单完成或取消
+    let position_index = user_positions.get_or_add_position_index(params.market_index)?;
+
+    // 下单时就锁定手续费折扣等级与推荐人，成交时不再重新计算
+    let discount_tier = calculate_order_fee_tier(&state.fee_structure, discount_token);
+    let referrer = referrer.unwrap_or_default();
+
+    // 触发单在满足触发条件之前保持Init状态，其余订单直接可被成交
+    let status = match params.order_type {
+        OrderType::TriggerMarket | OrderType::TriggerLimit => OrderStatus::Init,
+        OrderType::Market | OrderType::Limit => OrderStatus::Open,
+    };
+
+    let user_base_asset_amount =
+        signed_base_asset_amount(params.base_asset_amount, params.direction)?;
+
+    let new_order = Order {
+        status,
+        order_type: params.order_type,
+        direction: params.direction,
+        user_order_id: params.user_order_id,
+        reduce_only: params.reduce_only as u8,
+        post_only: params.post_only as u8,
+        immediate_or_cancel: params.immediate_or_cancel as u8,
+        discount_tier,
+        trigger_condition: params.trigger_condition,
+        trigger_price_source: params.trigger_price_source,
+        padding: [0; 2],
+        max_age: params.max_age.unwrap_or(order_state.default_max_order_age),
+        ts: now,
+        market_index: params.market_index,
+        order_id: order_history.next_order_id(),
+        price: params.price,
+        user_base_asset_amount,
+        quote_asset_amount: params.quote_asset_amount,
+        base_asset_amount: params.base_asset_amount,
+        base_asset_amount_filled: 0,
+        quote_asset_amount_filled: 0,
+        fee: 0,
+        trigger_price: params.trigger_price,
+        referrer,
+        oracle_price_offset: params.oracle_price_offset,
+    };
+
+    let mark_price = calculate_price(
+        market.amm.quote_asset_reserve,
+        market.amm.base_asset_reserve,
+        market.amm.peg_multiplier,
+    )?;
+    let oracle_price = market.amm.get_oracle_price(oracle, clock.slot)?.price;
+    validate_order(&new_order, market, order_state, mark_price, oracle_price)?;
+
+    user_orders.orders[new_order_index] = new_order;
+    user_positions.positions[position_index].open_orders += 1;
+
+    let record_id = order_history.next_record_id();
+    order_history.append(
+        OrderRecord {
+            ts: now,
+            action: OrderAction::Place,
+            padding: [0; 7],
+            record_id,
+            user,
+            authority,
+            order: new_order,
+            filler: Pubkey::default(),
+            trade_record_id: 0,
+            base_asset_amount_filled: 0,
+            quote_asset_amount_filled: 0,
+            fee: 0,
+            filler_reward: 0,
+            quote_asset_amount_surplus: 0,
+        },
+        state.is_history_ring_buffer_enabled(),
+    );
+
+    Ok(())
+}
+
+// 取消order_index上的订单：清空订单槽位，释放其在头寸上占用的挂单计数，并记录OrderRecord
+#[allow(clippy::too_many_arguments)]
+pub fn cancel_order(
+    state: &State,
+    order_index: usize,
+    user: Pubkey,
+    authority: Pubkey,
+    user_positions: &mut UserPositions,
+    user_orders: &mut UserOrders,
+    order_history: &mut OrderHistory,
+    clock: &Clock,
+) -> Result<()> {
+    let order = user_orders.orders[order_index];
+    if order.is_available() {
+        return err!(Errors::OrderDoesNotExist);
+    }
+
+    let position_index = user_positions.get_position_index(order.market_index)?;
+    let position = &mut user_positions.positions[position_index];
+    position.open_orders = position
+        .open_orders
+        .checked_sub(1)
+        .ok_or(Errors::MathError)?;
+
+    user_orders.orders[order_index] = Order::zeroed();
+
+    let record_id = order_history.next_record_id();
+    order_history.append(
+        OrderRecord {
+            ts: clock.unix_timestamp,
+            action: OrderAction::Cancel,
+            padding: [0; 7],
+            record_id,
+            user,
+            authority,
+            order,
+            filler: Pubkey::default(),
+            trade_record_id: 0,
+            base_asset_amount_filled: 0,
+            quote_asset_amount_filled: 0,
+            fee: 0,
+            filler_reward: 0,
+            quote_asset_amount_surplus: 0,
+        },
+        state.is_history_ring_buffer_enabled(),
+    );
+
+    Ok(())
+}
+
+// 清除用户所有已超过最长存活时间的订单，每清除一个订单从用户的collateral中
+// 支付time_based_reward_lower_bound给filler（不超过用户剩余的collateral），并记录OrderRecord
+#[allow(clippy::too_many_arguments)]
+pub fn expire_orders(
+    state: &State,
+    order_state: &OrderState,
+    user: &mut Account<User>,
+    user_positions: &mut UserPositions,
+    user_orders: &mut UserOrders,
+    filler: &mut Account<User>,
+    order_history: &mut OrderHistory,
+    clock: &Clock,
+) -> Result<()> {
+    let now = clock.unix_timestamp;
+
+    for order_index in 0..user_orders.orders.len() {
+        let order = user_orders.orders[order_index];
+        if order.is_available() || !order.is_expired(now) {
+            continue;
+        }
+
+        let position_index = user_positions.get_position_index(order.market_index)?;
+        let position = &mut user_positions.positions[position_index];
+        position.open_orders = position
+            .open_orders
+            .checked_sub(1)
+            .ok_or(Errors::MathError)?;
+
+        user_orders.orders[order_index] = Order::zeroed();
+
+        let filler_reward = order_state
+            .order_filler_reward_structure
+            .time_based_reward_lower_bound
+            .min(user.collateral);
+        user.collateral = user
+            .collateral
+            .checked_sub(filler_reward)
+            .ok_or(Errors::MathError)?;
+        filler.collateral = filler
+            .collateral
+            .checked_add(filler_reward)
+            .ok_or(Errors::MathError)?;
+
+        let record_id = order_history.next_record_id();
+        order_history.append(
+            OrderRecord {
+                ts: now,
+                action: OrderAction::Expire,
+                padding: [0; 7],
+                record_id,
+                user: user.key(),
+                authority: filler.authority,
+                order,
+                filler: filler.key(),
+                trade_record_id: 0,
+                base_asset_amount_filled: 0,
+                quote_asset_amount_filled: 0,
+                fee: 0,
+                filler_reward,
+                quote_asset_amount_surplus: 0,
+            },
+            state.is_history_ring_buffer_enabled(),
+        );
+    }
+
+    Ok(())
+}
+
+// 由filler执行order_id对应的订单：与AMM成交不超过使成交价格不差于Order.price的数量，
+// 更新用户头寸、订单的成交数量与手续费，并记录TradeRecord与OrderRecord
+// filler为None时表示用户自己成交自己的订单（没有filler奖励），返回本次成交的base数量
+#[allow(clippy::too_many_arguments)]
+pub fn fill_order(
+    order_id: u128,
+    state: &State,
+    order_state: &OrderState,
+    user: &mut Account<User>,
+    user_positions: &mut UserPositions,
+    user_orders: &mut UserOrders,
+    markets: &mut MarketMap,
+    oracle: &AccountInfo,
+    filler: Option<&mut Account<User>>,
+    referrer: Option<&mut Account<User>>,
+    trade_history: &mut TradeHistory,
+    order_history: &mut OrderHistory,
+    clock: &Clock,
+) -> Result<u128> {
+    let now = clock.unix_timestamp;
+    let order_index = user_orders.get_order_index(order_id)?;
+    let mut order = user_orders.orders[order_index];
+
+    // exchange暂停时只允许成交reduce only订单
+    if state.is_exchange_paused() && order.reduce_only == 0 {
+        return err!(Errors::ExchangePaused);
+    }
+
+    // 下单时锁定了推荐人的订单，必须传入对应的推荐人账户
+    let has_referrer = !order.referrer.eq(&Pubkey::default());
+    if has_referrer
+        && !referrer
+            .as_ref()
+            .is_some_and(|referrer| referrer.key().eq(&order.referrer))
+    {
+        return err!(Errors::InvalidReferrer);
+    }
+
+    let position_index = user_positions.get_position_index(order.market_index)?;
+
+    let (oracle_price, mark_price_before) = {
+        let market = markets.get_market(order.market_index)?;
+        // market重置并重新初始化之前下的订单不能在新market上成交
+        if order.order_id < market.min_order_id {
+            return err!(Errors::OrderPlacedBeforeMarketReset);
+        }
+        require_keys_eq!(oracle.key(), market.amm.oracle, Errors::InvalidOracle);
+        // oracle价格无效（过期、波动过大或置信区间过宽）时拒绝成交
+        let oracle_price_data = market.amm.get_oracle_price(oracle, clock.slot)?;
+        if !is_oracle_valid(
+            &market.amm,
+            &oracle_price_data,
+            &state.oracle_guard_rails.validity,
+        )? {
+            return err!(Errors::InvalidOracle);
+        }
+        let oracle_price = oracle_price_data.price;
+        let mark_price = calculate_price(
+            market.amm.quote_asset_reserve,
+            market.amm.base_asset_reserve,
+            market.amm.peg_multiplier,
+        )?;
+        (oracle_price, mark_price)
+    };
+
+    // 触发单在触发条件满足时转为Open后立即成交，未满足时整笔交易失败，订单保持Init状态
+    let mut triggered = false;
+    if order.status == OrderStatus::Init && order.is_trigger_order() {
+        let price = get_trigger_reference_price(&order, mark_price_before, oracle_price)?;
+        if !order.is_trigger_condition_met(price) {
+            return err!(Errors::OrderNotTriggerable);
+        }
+        order.status = OrderStatus::Open;
+        triggered = true;
+    }
+    if order.status != OrderStatus::Open {
+        return err!(Errors::OrderNotOpen);
+    }
+
+    let (filler_key, filler_authority) = match &filler {
+        Some(filler) => (filler.key(), filler.authority),
+        None => (user.key(), user.authority),
+    };
+
+    // reduce only订单只能减少已有头寸：没有头寸或者方向与头寸相同时直接取消订单
+    let position_base_asset_amount = user_positions.positions[position_index].base_asset_amount;
+    let reduces_position = position_base_asset_amount != 0
+        && (position_base_asset_amount > 0) != (order.direction == PositionDirection::Long);
+    if order.reduce_only != 0 && !reduces_position {
+        cancel_order(
+            state,
+            order_index,
+            user.key(),
+            filler_authority,
+            user_positions,
+            user_orders,
+            order_history,
+            clock,
+        )?;
+        return Ok(0);
+    }
+
+    let limit_price = calculate_limit_price(&order, oracle_price)?;
+
+    let (base_asset_amount, quote_asset_amount, update_type, fully_filled, mark_price_after) = {
+        let market = markets.get_market_mut(order.market_index)?;
+
+        // 订单剩余未成交的base数量（按quote数量下单的市价单按当前AMM换算）
+        let remaining_base_asset_amount = if order.base_asset_amount > 0 {
+            order
+                .base_asset_amount
+                .checked_sub(order.base_asset_amount_filled)
+                .ok_or(Errors::MathError)?
+        } else {
+            calculate_base_asset_amount_for_quote(
+                &market.amm,
+                order
+                    .quote_asset_amount
+                    .checked_sub(order.quote_asset_amount_filled)
+                    .ok_or(Errors::MathError)?,
+                order.direction,
+            )?
+        };
+
+        // 设置了限价的订单只成交到mark price到达限价为止，保证成交均价不差于限价
+        let mut base_asset_amount = if limit_price > 0 {
+            remaining_base_asset_amount.min(calculate_base_asset_amount_to_trade_to_price(
+                &market.amm,
+                limit_price,
+                order.direction,
+            )?)
+        } else {
+            remaining_base_asset_amount
+        };
+        // reduce only订单的成交数量不能超过当前头寸，避免反向开仓
+        if order.reduce_only != 0 {
+            base_asset_amount = base_asset_amount.min(position_base_asset_amount.unsigned_abs());
+        }
+        if base_asset_amount == 0 {
+            // 本次触发的触发限价单暂时无法成交时，保留转为Open的状态，之后按普通限价单成交
+            if triggered {
+                user_orders.orders[order_index] = order;
+                return Ok(0);
+            }
+            return err!(Errors::CouldNotFillOrder);
+        }
+
+        let market_position = &mut user_positions.positions[position_index];
+        let update_type =
+            get_position_update_type(market_position, base_asset_amount, order.direction);
+        market.validate_status(!update_type.is_risk_increasing())?;
+
+        let quote_asset_amount =
+            swap_base_asset(&mut market.amm, base_asset_amount, order.direction)?;
+        let mark_price_after = calculate_price(
+            market.amm.quote_asset_reserve,
+            market.amm.base_asset_reserve,
+            market.amm.peg_multiplier,
+        )?;
+        market.amm.last_oracle_price = oracle_price;
+
+        update_position_with_base_asset_amount(
+            base_asset_amount,
+            quote_asset_amount,
+            order.direction,
+            market,
+            user,
+            market_position,
+        )?;
+
+        (
+            base_asset_amount,
+            quote_asset_amount,
+            update_type,
+            base_asset_amount == remaining_base_asset_amount,
+            mark_price_after,
+        )
+    };
+
+    let fees = calculate_fee_for_order(
+        quote_asset_amount,
+        &state.fee_structure,
+        &order_state.order_filler_reward_structure,
+        &order.discount_tier,
+        has_referrer,
+        filler.is_none(),
+    )?;
+
+    user.collateral = user
+        .collateral
+        .checked_sub(fees.user_fee)
+        .ok_or(Errors::InsufficientCollateral)?;
+    user.total_fee_paid = user
+        .total_fee_paid
+        .checked_add(fees.user_fee)
+        .ok_or(Errors::MathError)?;
+    user.total_token_discount = user
+        .total_token_discount
+        .checked_add(fees.token_discount)
+        .ok_or(Errors::MathError)?;
+    user.total_referee_discount = user
+        .total_referee_discount
+        .checked_add(fees.referee_discount)
+        .ok_or(Errors::MathError)?;
+
+    {
+        let market = markets.get_market_mut(order.market_index)?;
+        market.amm.total_fee = market
+            .amm
+            .total_fee
+            .checked_add(fees.fee_to_market)
+            .ok_or(Errors::MathError)?;
+        market.amm.total_fee_minus_distributions = market
+            .amm
+            .total_fee_minus_distributions
+            .checked_add(fees.fee_to_market)
+            .ok_or(Errors::MathError)?;
+    }
+
+    if has_referrer {
+        if let Some(referrer) = referrer {
+            referrer.collateral = referrer
+                .collateral
+                .checked_add(fees.referrer_reward)
+                .ok_or(Errors::MathError)?;
+            referrer.total_referral_reward = referrer
+                .total_referral_reward
+                .checked_add(fees.referrer_reward)
+                .ok_or(Errors::MathError)?;
+        }
+    }
+
+    if let Some(filler) = filler {
+        filler.collateral = filler
+            .collateral
+            .checked_add(fees.filler_reward)
+            .ok_or(Errors::MathError)?;
+    }
+
+    // 增加风险敞口的成交必须满足初始保证金要求
+    if update_type.is_risk_increasing()
+        && !meets_initial_margin_requirement(user, user_positions, markets)?
+    {
+        return err!(Errors::InsufficientCollateral);
+    }
+
+    order.base_asset_amount_filled = order
+        .base_asset_amount_filled
+        .checked_add(base_asset_amount)
+        .ok_or(Errors::MathError)?;
+    order.quote_asset_amount_filled = order
+        .quote_asset_amount_filled
+        .checked_add(quote_asset_amount)
+        .ok_or(Errors::MathError)?;
+    order.fee = order
+        .fee
+        .checked_add(i128::try_from(fees.user_fee).map_err(|_| Errors::MathError)?)
+        .ok_or(Errors::MathError)?;
+
+    // 完全成交的订单从订单槽位中移除
+    if fully_filled {
+        user_orders.orders[order_index] = Order::zeroed();
+        let market_position = &mut user_positions.positions[position_index];
+        market_position.open_orders = market_position
+            .open_orders
+            .checked_sub(1)
+            .ok_or(Errors::MathError)?;
+    } else {
+        user_orders.orders[order_index] = order;
+    }
+
+    let fee = i128::try_from(fees.user_fee).map_err(|_| Errors::MathError)?;
+    let trade_record_id = trade_history.next_record_id();
+    trade_history.append(
+        TradeRecord {
+            ts: now,
+            market_index: order.market_index,
+            record_id: trade_record_id,
+            user_authority: user.authority,
+            user: user.key(),
+            base_asset_amount,
+            quote_asset_amount,
+            mark_price_before,
+            mark_price_after,
+            fee,
+            quote_asset_amount_surplus: 0,
+            referee_discount: fees.referee_discount,
+            token_discount: fees.token_discount,
+            oracle_price,
+            liquidation: 0,
+            direction: order.direction,
+            settlement: 0,
+            padding: [0; 13],
+        },
+        state.is_history_ring_buffer_enabled(),
+    );
+
+    // OrderRecord中记录本次成交实际使用的限价（oracle偏移订单为成交时解析出的价格）
+    let mut record_order = order;
+    record_order.price = limit_price;
+    let record_id = order_history.next_record_id();
+    order_history.append(
+        OrderRecord {
+            ts: now,
+            action: OrderAction::Fill,
+            padding: [0; 7],
+            record_id,
+            user: user.key(),
+            authority: filler_authority,
+            order: record_order,
+            filler: filler_key,
+            trade_record_id,
+            base_asset_amount_filled: base_asset_amount,
+            quote_asset_amount_filled: quote_asset_amount,
+            fee,
+            filler_reward: fees.filler_reward,
+            quote_asset_amount_surplus: 0,
+        },
+        state.is_history_ring_buffer_enabled(),
+    );
+
+    // 未完全成交时，IOC订单的剩余部分、以及头寸已被平掉的reduce only订单直接取消
+    let position_closed = user_positions.positions[position_index].base_asset_amount == 0;
+    if !fully_filled
+        && (order.immediate_or_cancel != 0 || (order.reduce_only != 0 && position_closed))
+    {
+        cancel_order(
+            state,
+            order_index,
+            user.key(),
+            filler_authority,
+            user_positions,
+            user_orders,
+            order_history,
+            clock,
+        )?;
+    }
+
+    Ok(base_asset_amount)
+}
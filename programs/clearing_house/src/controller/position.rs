@@ -1,7 +1,14 @@
 use anchor_lang::prelude::*;
 use bytemuck::{Pod, Zeroable};
+use std::cmp::Ordering;
 
-#[derive(Clone, Copy, AnchorDeserialize, AnchorSerialize)]
+use crate::errors::Errors;
+use crate::math::bn::U192;
+use crate::math::position::{calculate_pnl, calculate_updated_collateral};
+use crate::state::market::Market;
+use crate::state::user::{MarketPosition, User};
+
+#[derive(Clone, Copy, AnchorDeserialize, AnchorSerialize, PartialEq, Eq)]
 #[repr(u8)]
 pub enum PositionDirection {
     Long,
@@ -10,3 +17,240 @@ pub enum PositionDirection {
 
 unsafe impl Zeroable for PositionDirection {}
 unsafe impl Pod for PositionDirection {}
+
+// 一次成交后头寸的变化类型
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PositionUpdateType {
+    Open,     // 开新仓
+    Increase, // 加仓
+    Reduce,   // 减仓
+    Close,    // 平仓
+    Flip,     // 反向开仓（先平掉原头寸再反向开仓）
+}
+
+impl PositionUpdateType {
+    // 是否会增加用户的风险敞口
+    pub fn is_risk_increasing(&self) -> bool {
+        matches!(
+            self,
+            PositionUpdateType::Open | PositionUpdateType::Increase | PositionUpdateType::Flip
+        )
+    }
+}
+
+// 计算按direction成交base_asset_amount后头寸的变化类型
+pub fn get_position_update_type(
+    market_position: &MarketPosition,
+    base_asset_amount: u128,
+    direction: PositionDirection,
+) -> PositionUpdateType {
+    let position_base_asset_amount = market_position.base_asset_amount;
+    if position_base_asset_amount == 0 {
+        return PositionUpdateType::Open;
+    }
+
+    let same_direction = (position_base_asset_amount > 0) == (direction == PositionDirection::Long);
+    if same_direction {
+        return PositionUpdateType::Increase;
+    }
+
+    match base_asset_amount.cmp(&position_base_asset_amount.unsigned_abs()) {
+        Ordering::Less => PositionUpdateType::Reduce,
+        Ordering::Equal => PositionUpdateType::Close,
+        Ordering::Greater => PositionUpdateType::Flip,
+    }
+}
+
+// 用一次成交（按direction成交base_asset_amount，对应quote_asset_amount）更新用户头寸与market的多空统计，
+// 减仓/平仓部分的盈亏会直接计入user.collateral，返回头寸的变化类型
+pub fn update_position_with_base_asset_amount(
+    base_asset_amount: u128,
+    quote_asset_amount: u128,
+    direction: PositionDirection,
+    market: &mut Market,
+    user: &mut User,
+    market_position: &mut MarketPosition,
+) -> Result<PositionUpdateType> {
+    let update_type = get_position_update_type(market_position, base_asset_amount, direction);
+
+    match update_type {
+        PositionUpdateType::Open | PositionUpdateType::Increase => {
+            increase_position(
+                base_asset_amount,
+                quote_asset_amount,
+                direction,
+                market,
+                market_position,
+            )?;
+        }
+        PositionUpdateType::Reduce | PositionUpdateType::Close => {
+            reduce_position(
+                base_asset_amount,
+                quote_asset_amount,
+                market,
+                user,
+                market_position,
+            )?;
+        }
+        PositionUpdateType::Flip => {
+            // 按base数量比例拆分quote：一部分用于平掉原头寸，剩余部分反向开仓
+            let close_base_asset_amount = market_position.base_asset_amount.unsigned_abs();
+            let close_quote_asset_amount = U192::from(quote_asset_amount)
+                .checked_mul(U192::from(close_base_asset_amount))
+                .ok_or(Errors::MathError)?
+                .checked_div(U192::from(base_asset_amount))
+                .ok_or(Errors::MathError)?
+                .try_to_u128()?;
+
+            reduce_position(
+                close_base_asset_amount,
+                close_quote_asset_amount,
+                market,
+                user,
+                market_position,
+            )?;
+            increase_position(
+                base_asset_amount
+                    .checked_sub(close_base_asset_amount)
+                    .ok_or(Errors::MathError)?,
+                quote_asset_amount
+                    .checked_sub(close_quote_asset_amount)
+                    .ok_or(Errors::MathError)?,
+                direction,
+                market,
+                market_position,
+            )?;
+        }
+    }
+
+    Ok(update_type)
+}
+
+fn increase_position(
+    base_asset_amount: u128,
+    quote_asset_amount: u128,
+    direction: PositionDirection,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+) -> Result<()> {
+    // 开新仓时从当前的累计资金费率开始计算资金费
+    if market_position.base_asset_amount == 0 {
+        market.open_interest = market
+            .open_interest
+            .checked_add(1)
+            .ok_or(Errors::MathError)?;
+        market_position.last_cumulative_funding_rate = match direction {
+            PositionDirection::Long => market.amm.cumulative_funding_rate_long,
+            PositionDirection::Short => market.amm.cumulative_funding_rate_short,
+        } as i128;
+        market_position.last_cumulative_repeg_rebate = match direction {
+            PositionDirection::Long => market.amm.cumulative_repeg_rebate_long,
+            PositionDirection::Short => market.amm.cumulative_repeg_rebate_short,
+        };
+    }
+
+    let base_asset_amount = signed_base_asset_amount(base_asset_amount, direction)?;
+    market_position.quote_asset_amount = market_position
+        .quote_asset_amount
+        .checked_add(quote_asset_amount)
+        .ok_or(Errors::MathError)?;
+    update_base_asset_amount(base_asset_amount, market, market_position)
+}
+
+// 按减仓比例结转开仓成本，并将这部分的已实现盈亏计入collateral
+fn reduce_position(
+    base_asset_amount: u128,
+    quote_asset_amount: u128,
+    market: &mut Market,
+    user: &mut User,
+    market_position: &mut MarketPosition,
+) -> Result<()> {
+    let position_base_asset_amount = market_position.base_asset_amount.unsigned_abs();
+    let is_long = market_position.base_asset_amount > 0;
+
+    let cost_basis = U192::from(market_position.quote_asset_amount)
+        .checked_mul(U192::from(base_asset_amount))
+        .ok_or(Errors::MathError)?
+        .checked_div(U192::from(position_base_asset_amount))
+        .ok_or(Errors::MathError)?
+        .try_to_u128()?;
+    let pnl = calculate_pnl(quote_asset_amount, cost_basis, is_long)?;
+    user.collateral = calculate_updated_collateral(user.collateral, pnl)?;
+
+    market_position.quote_asset_amount = market_position
+        .quote_asset_amount
+        .checked_sub(cost_basis)
+        .ok_or(Errors::MathError)?;
+
+    // 减仓的方向与原头寸相反
+    let direction = if is_long {
+        PositionDirection::Short
+    } else {
+        PositionDirection::Long
+    };
+    let base_asset_amount = signed_base_asset_amount(base_asset_amount, direction)?;
+    update_base_asset_amount(base_asset_amount, market, market_position)?;
+
+    if market_position.base_asset_amount == 0 {
+        market.open_interest = market
+            .open_interest
+            .checked_sub(1)
+            .ok_or(Errors::MathError)?;
+    }
+
+    Ok(())
+}
+
+// 更新头寸的base数量，并同步更新market中的多空base数量（多头计入long，空头计入short）
+fn update_base_asset_amount(
+    base_asset_amount: i128,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+) -> Result<()> {
+    let base_asset_amount_before = market_position.base_asset_amount;
+    let base_asset_amount_after = base_asset_amount_before
+        .checked_add(base_asset_amount)
+        .ok_or(Errors::MathError)?;
+
+    if base_asset_amount_before > 0 {
+        market.base_asset_amount_long = market
+            .base_asset_amount_long
+            .checked_sub(base_asset_amount_before)
+            .ok_or(Errors::MathError)?;
+    } else {
+        market.base_asset_amount_short = market
+            .base_asset_amount_short
+            .checked_sub(base_asset_amount_before)
+            .ok_or(Errors::MathError)?;
+    }
+    if base_asset_amount_after > 0 {
+        market.base_asset_amount_long = market
+            .base_asset_amount_long
+            .checked_add(base_asset_amount_after)
+            .ok_or(Errors::MathError)?;
+    } else {
+        market.base_asset_amount_short = market
+            .base_asset_amount_short
+            .checked_add(base_asset_amount_after)
+            .ok_or(Errors::MathError)?;
+    }
+    market.base_asset_amount = market
+        .base_asset_amount
+        .checked_add(base_asset_amount)
+        .ok_or(Errors::MathError)?;
+    market_position.base_asset_amount = base_asset_amount_after;
+
+    Ok(())
+}
+
+// 将base数量按方向转为带符号的数量（做多为正，做空为负）
+pub fn signed_base_asset_amount(
+    base_asset_amount: u128,
+    direction: PositionDirection,
+) -> Result<i128> {
+    let base_asset_amount = i128::try_from(base_asset_amount).map_err(|_| Errors::MathError)?;
+    Ok(match direction {
+        PositionDirection::Long => base_asset_amount,
+        PositionDirection::Short => -base_asset_amount,
+    })
+}
@@ -0,0 +1,197 @@
+use anchor_lang::prelude::*;
+use bytemuck::Zeroable;
+
+use crate::controller::orders::cancel_order;
+use crate::controller::position::{update_position_with_base_asset_amount, PositionDirection};
+use crate::errors::Errors;
+use crate::math::amm::calculate_price;
+use crate::math::oracle::is_oracle_valid;
+use crate::math::orders::calculate_quote_asset_amount_for_price;
+use crate::state::history::order_history::OrderHistory;
+use crate::state::history::ring_buffer::RingBuffer;
+use crate::state::history::trade_history::{TradeHistory, TradeRecord};
+use crate::state::market::{Market, MarketStatus};
+use crate::state::market_map::MarketMap;
+use crate::state::oracle::{get_oracle_price, get_oracle_twap, OraclePriceData};
+use crate::state::state::State;
+use crate::state::user::{User, UserPositions};
+use crate::state::user_orders::UserOrders;
+
+// 推进market的结算流程，每次调用前进一步，每一步都会写入一条settlement记录：
+// 1. Active/Paused -> ReduceOnly：只允许用户减仓
+// 2. ReduceOnly -> Settled：以oracle TWAP冻结settlement_price，之后头寸只能通过settle_position平仓
+// 3. Settled且open_interest为0 -> 重置market，slot可以重新初始化
+//    用户在重置前下的订单仍留在UserOrders中，重置时记录min_order_id，这些订单不能在新market上成交
+//    MarketAccount中的market无法重新初始化（pda账户已经存在），所以不允许重置，保持Settled状态
+pub fn settle_market(
+    state: &State,
+    market_index: u64,
+    markets: &mut MarketMap,
+    oracle: &AccountInfo,
+    trade_history: &mut TradeHistory,
+    order_history: &OrderHistory,
+    clock: &Clock,
+) -> Result<()> {
+    let is_market_account = markets.is_market_account(market_index);
+    let market = markets.get_market_mut(market_index)?;
+
+    let (oracle_price, settlement) = match market.status {
+        MarketStatus::Active | MarketStatus::Paused => {
+            market.status = MarketStatus::ReduceOnly;
+            (0, 2)
+        }
+        MarketStatus::ReduceOnly => {
+            require_keys_eq!(oracle.key(), market.amm.oracle, Errors::InvalidOracle);
+            let settlement_price = get_oracle_twap(&market.amm.oracle_source, oracle)?;
+
+            // TWAP与即时价格一样要通过oracle防护栏：价格为正、数据没有过期、置信区间不过大且波动不过大
+            let oracle_price_data =
+                get_oracle_price(&market.amm.oracle_source, oracle, clock.slot)?;
+            let twap_price_data = OraclePriceData {
+                price: settlement_price,
+                ..oracle_price_data
+            };
+            if !is_oracle_valid(
+                &market.amm,
+                &twap_price_data,
+                &state.oracle_guard_rails.validity,
+            )? {
+                return err!(Errors::InvalidOracle);
+            }
+
+            market.settlement_price = settlement_price.unsigned_abs();
+            market.status = MarketStatus::Settled;
+            (settlement_price, 1)
+        }
+        MarketStatus::Settled => {
+            if market.open_interest != 0 {
+                return err!(Errors::MarketHasOpenInterest);
+            }
+            if is_market_account {
+                return err!(Errors::MarketAccountNotResettable);
+            }
+            (0, 3)
+        }
+    };
+
+    // 结算流程的记录不属于任何用户，user与user_authority为空
+    let mark_price = calculate_price(
+        market.amm.quote_asset_reserve,
+        market.amm.base_asset_reserve,
+        market.amm.peg_multiplier,
+    )?;
+    let record_id = trade_history.next_record_id();
+    trade_history.append(
+        TradeRecord {
+            ts: clock.unix_timestamp,
+            market_index,
+            record_id,
+            mark_price_before: mark_price,
+            mark_price_after: mark_price,
+            oracle_price,
+            settlement,
+            ..TradeRecord::zeroed()
+        },
+        state.is_history_ring_buffer_enabled(),
+    );
+
+    // 记录写入后再重置，保留重置前的mark price
+    if settlement == 3 {
+        *market = Market {
+            min_order_id: order_history
+                .last_order_id
+                .checked_add(1)
+                .ok_or(Errors::MathError)?,
+            ..Market::zeroed()
+        };
+    }
+
+    Ok(())
+}
+
+// 取消用户在已结算market上的所有订单，并按settlement_price平掉头寸，
+// 盈亏直接计入collateral，不经过AMM也不收取手续费
+#[allow(clippy::too_many_arguments)]
+pub fn settle_position(
+    state: &State,
+    market_index: u64,
+    user: &mut Account<User>,
+    user_positions: &mut UserPositions,
+    user_orders: &mut UserOrders,
+    markets: &mut MarketMap,
+    trade_history: &mut TradeHistory,
+    order_history: &mut OrderHistory,
+    clock: &Clock,
+) -> Result<()> {
+    let market = markets.get_market_mut(market_index)?;
+    if market.status != MarketStatus::Settled {
+        return err!(Errors::MarketNotSettled);
+    }
+
+    let position_index = user_positions.get_position_index(market_index)?;
+
+    for order_index in 0..user_orders.orders.len() {
+        let order = &user_orders.orders[order_index];
+        if order.is_available() || order.market_index != market_index {
+            continue;
+        }
+
+        cancel_order(
+            state,
+            order_index,
+            user.key(),
+            user.authority,
+            user_positions,
+            user_orders,
+            order_history,
+            clock,
+        )?;
+    }
+
+    // 只有挂单没有持仓时，取消订单后即完成结算
+    let market_position = &mut user_positions.positions[position_index];
+    if market_position.base_asset_amount == 0 {
+        return Ok(());
+    }
+
+    // 以与头寸相反的方向按settlement_price成交全部base数量
+    let base_asset_amount = market_position.base_asset_amount.unsigned_abs();
+    let direction = if market_position.base_asset_amount > 0 {
+        PositionDirection::Short
+    } else {
+        PositionDirection::Long
+    };
+    let quote_asset_amount =
+        calculate_quote_asset_amount_for_price(base_asset_amount, market.settlement_price)?;
+    update_position_with_base_asset_amount(
+        base_asset_amount,
+        quote_asset_amount,
+        direction,
+        market,
+        user,
+        market_position,
+    )?;
+
+    let settlement_price = market.settlement_price;
+    let record_id = trade_history.next_record_id();
+    trade_history.append(
+        TradeRecord {
+            ts: clock.unix_timestamp,
+            market_index,
+            record_id,
+            user_authority: user.authority,
+            user: user.key(),
+            base_asset_amount,
+            quote_asset_amount,
+            mark_price_before: settlement_price,
+            mark_price_after: settlement_price,
+            oracle_price: i128::try_from(settlement_price).map_err(|_| Errors::MathError)?,
+            direction,
+            settlement: 1,
+            ..TradeRecord::zeroed()
+        },
+        state.is_history_ring_buffer_enabled(),
+    );
+
+    Ok(())
+}
@@ -10,4 +10,118 @@ pub enum Errors {
     HistoriesAllInitialized,
     #[msg("Clearing house order state already initialized")]
     OrderStateAlreadyInitialized,
+    #[msg("Invalid margin ratio")]
+    InvalidMarginRatio,
+    #[msg("Market index out of range")]
+    MarketIndexOutOfRange,
+    #[msg("Market index not initialized")]
+    MarketIndexNotInitialized,
+    #[msg("Tightening margin ratio of market with open interest must be forced")]
+    MarginRatioTighteningNotForced,
+    #[msg("Invalid liquidation parameter")]
+    InvalidLiquidationParameter,
+    #[msg("Invalid fee structure")]
+    InvalidFeeStructure,
+    #[msg("Invalid oracle guard rails")]
+    InvalidOracleGuardRails,
+    #[msg("Invalid oracle")]
+    InvalidOracle,
+    #[msg("Unsupported oracle source")]
+    UnsupportedOracleSource,
+    #[msg("Oracle price diverges too much from oracle twap")]
+    OracleTwapDivergenceTooLarge,
+    #[msg("Math error")]
+    MathError,
+    #[msg("Exchange is paused")]
+    ExchangePaused,
+    #[msg("Funding is paused")]
+    FundingPaused,
+    #[msg("Market is paused")]
+    MarketPaused,
+    #[msg("Market is in reduce only mode")]
+    MarketReduceOnly,
+    #[msg("Invalid pending admin")]
+    InvalidPendingAdmin,
+    #[msg("No pending admin")]
+    NoPendingAdmin,
+    #[msg("Signer is not pending admin")]
+    NotPendingAdmin,
+    #[msg("Whitelist token not found")]
+    WhitelistTokenNotFound,
+    #[msg("Invalid whitelist token")]
+    InvalidWhitelistToken,
+    #[msg("Max number of orders taken")]
+    MaxNumberOfOrders,
+    #[msg("Order does not exist")]
+    OrderDoesNotExist,
+    #[msg("User orders still has open orders")]
+    UserOrdersNotEmpty,
+    #[msg("Conversion to u128/u64 failed with an overflow or underflow")]
+    BnConversionError,
+    #[msg("Market index already initialized")]
+    MarketIndexAlreadyInitialized,
+    #[msg("Invalid initial peg")]
+    InvalidInitialPeg,
+    #[msg("Invalid order")]
+    InvalidOrder,
+    #[msg("Order amount too small")]
+    OrderAmountTooSmall,
+    #[msg("User order id already in use")]
+    DuplicateUserOrderId,
+    #[msg("Max number of positions taken")]
+    MaxNumberOfPositions,
+    #[msg("User has no position in market")]
+    UserHasNoPositionInMarket,
+    #[msg("Invalid discount token")]
+    InvalidDiscountToken,
+    #[msg("Invalid referrer")]
+    InvalidReferrer,
+    #[msg("Insufficient deposit")]
+    InsufficientDeposit,
+    #[msg("User max deposit reached")]
+    UserMaxDeposit,
+    #[msg("Order is not open")]
+    OrderNotOpen,
+    #[msg("Could not fill order")]
+    CouldNotFillOrder,
+    #[msg("Insufficient collateral")]
+    InsufficientCollateral,
+    #[msg("Invalid filler")]
+    InvalidFiller,
+    #[msg("Trigger price is on the wrong side of the current price")]
+    InvalidTriggerPrice,
+    #[msg("Order trigger condition not met")]
+    OrderNotTriggerable,
+    #[msg("Invalid oracle price offset")]
+    InvalidOraclePriceOffset,
+    #[msg("Post only order would cross the AMM")]
+    PostOnlyOrderWouldCross,
+    #[msg("Clearing house extended curve history already initialized")]
+    ExtendedCurveHistoryAlreadyInitialized,
+    #[msg("Invalid history account")]
+    InvalidHistoryAccount,
+    #[msg("Clearing house markets already initialized")]
+    MarketsAlreadyInitialized,
+    #[msg("Account version does not match the program, migrate it first")]
+    AccountVersionMismatch,
+    #[msg("Account already migrated to the current version")]
+    AccountAlreadyMigrated,
+    #[msg("Market has been migrated to its own market account")]
+    MarketMigrated,
+    #[msg("Invalid market account")]
+    InvalidMarketAccount,
+    #[msg("Market index is not available for a market account")]
+    InvalidMarketAccountIndex,
+    #[msg("Market is settled, only settle_position is allowed")]
+    MarketSettled,
+    #[msg("Market is not settled")]
+    MarketNotSettled,
+    #[msg("Market still has open interest")]
+    MarketHasOpenInterest,
+    #[msg("Market status can only be changed to settled through settle_market")]
+    InvalidMarketStatusUpdate,
+    #[msg("Settled market in a market account can not be reset")]
+    MarketAccountNotResettable,
+    #[msg("Order was placed before the market was reset")]
+    OrderPlacedBeforeMarketReset,
 }
@@ -1,15 +1,42 @@
 #![allow(unexpected_cfgs)]
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use anchor_lang::Discriminator;
 use context::*;
 use errors::Errors;
+use math::amm::calculate_price;
 use math::constant::*;
+use math::oracle::{is_oracle_valid, is_price_divergence_within_bounds};
+use state::history::curve_history::CurveHistory;
+use state::history::deposit_history::{DepositDirection, DepositHistory, DepositRecord};
+use state::history::funding_payment_history::FundingPaymentHistory;
+use state::history::funding_rate_history::FundingRateHistory;
+use state::history::liquidation_history::LiquidationHistory;
+use state::history::order_history::OrderHistory;
+use state::history::ring_buffer::RingBuffer;
+use state::history::trade_history::TradeHistory;
+use state::market::{
+    Market, MarketAccount, MarketStatus, Markets, OracleSource, AMM, MARKETS_VERSION,
+    MARKET_ACCOUNT_VERSION,
+};
+use state::market_map::MarketMap;
+use state::oracle::{get_oracle_price, get_oracle_twap};
 use state::state::*;
+use state::user_orders::OrderType;
+use validation::fee_structure::validate_fee_structure;
+use validation::liquidation::{
+    validate_liquidation_percentage, validate_liquidator_share_denominator,
+};
+use validation::margin::validate_margin;
+use validation::oracle_guard_rails::validate_oracle_guard_rails;
+use validation::whitelist::validate_whitelist_token;
 
 pub mod context;
 pub mod controller;
 pub mod errors;
 pub mod math;
 pub mod state;
+pub mod validation;
 
 use controller::position::PositionDirection;
 
@@ -17,7 +44,7 @@ declare_id!("HPx7dWgMDvEKRf5S8uLVG2VxEqdKRhQ5Q8meCqEsecZz");
 
 #[program]
 pub mod clearing_house {
-    use crate::state::order_state::{OrderFillerRewardStructure, OrderState};
+    use crate::state::order_state::{OrderFillerRewardStructure, OrderState, ORDER_STATE_VERSION};
 
     use super::*;
 
@@ -46,8 +73,6 @@ pub mod clearing_house {
             Errors::InvalidInsuranceVaultAuthority
         );
 
-        ctx.accounts.markets.load_init()?;
-
         let state = &mut ctx.accounts.state.load_init()?;
         let default_pubkey = Pubkey::default();
         **state = State {
@@ -56,7 +81,11 @@ pub mod clearing_house {
             admin_controls_prices: if admin_controls_prices { 1 } else { 0 },
             collateral_vault_authority_nonce: collateral_vault_authority_bump,
             insurance_vault_authority_nonce: insurance_vault_authority_bump,
-            padding0: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            history_ring_buffers_disabled: 0,
+            bump: ctx.bumps.state,
+            markets_bump: ctx.bumps.markets,
+            version: STATE_VERSION,
+            padding0: [0, 0, 0, 0, 0, 0, 0],
 
             admin: *ctx.accounts.admin.key,
             collateral_mint: ctx.accounts.collateral_mint.key(),
@@ -144,23 +173,77 @@ pub mod clearing_house {
             max_deposit: 0,
             extended_curve_history: default_pubkey,
             order_state: default_pubkey,
-            padding1: [0, 0, 0, 0],
+            pending_admin: default_pubkey,
+            padding1: [0, 0],
         };
 
         Ok(())
     }
 
+    // 把markets账户扩容MAX_PERMITTED_DATA_INCREASE字节（不超过Markets的完整大小），
+    // 扩容到完整大小时写入discriminator完成初始化，此后markets才能被其他指令使用
+    pub fn resize_markets(ctx: Context<ResizeMarkets>) -> Result<()> {
+        let markets = &ctx.accounts.markets;
+        let full_len = 8 + std::mem::size_of::<Markets>();
+        let current_len = markets.data_len();
+        // 已写入discriminator的markets（包括旧版本的markets）不能再通过resize_markets修改，旧版本需要使用migrate_markets
+        if current_len >= full_len || markets.try_borrow_data()?[..8] == Markets::DISCRIMINATOR {
+            return err!(Errors::MarketsAlreadyInitialized);
+        }
+
+        let new_len = full_len.min(current_len + MAX_PERMITTED_DATA_INCREASE);
+        // 新增的空间会被置零，与load_init之后Markets的初始状态一致
+        controller::migration::realloc_account(
+            markets,
+            new_len,
+            &ctx.accounts.admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        if new_len == full_len {
+            let mut data = markets.try_borrow_mut_data()?;
+            data[..8].copy_from_slice(&Markets::DISCRIMINATOR);
+            data[8 + std::mem::offset_of!(Markets, version)] = MARKETS_VERSION;
+        }
+
+        Ok(())
+    }
+
+    // program升级修改了State布局之后，由admin把State账户迁移到当前版本
+    pub fn migrate_state(ctx: Context<MigrateState>) -> Result<()> {
+        controller::migration::migrate_state(
+            &ctx.accounts.state,
+            &ctx.accounts.admin,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    pub fn migrate_markets(ctx: Context<MigrateMarkets>) -> Result<()> {
+        controller::migration::migrate_markets(
+            &ctx.accounts.markets,
+            &ctx.accounts.admin,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    pub fn migrate_order_state(ctx: Context<MigrateOrderState>) -> Result<()> {
+        controller::migration::migrate_order_state(
+            &ctx.accounts.order_state,
+            &ctx.accounts.admin,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    // 已废弃：拼写错误的旧入口，仅为兼容旧客户端保留，请使用initialize_history
     pub fn intialize_history(ctx: Context<InitializeHistory>) -> Result<()> {
+        initialize_history(ctx)
+    }
+
+    pub fn initialize_history(ctx: Context<InitializeHistory>) -> Result<()> {
         let state = &mut ctx.accounts.state.load_mut()?;
-        let default_pubkey = Pubkey::default();
-        // 如果state中这6个Pubkey都不是Pubkey默认值时，就会报错（表明history已经初始化过）
-        if !state.trade_history.eq(&default_pubkey)
-            && !state.deposit_history.eq(&default_pubkey)
-            && !state.liquidation_history.eq(&default_pubkey)
-            && !state.funding_rate_history.eq(&default_pubkey)
-            && !state.funding_payment_history.eq(&default_pubkey)
-            && !state.curve_history.eq(&default_pubkey)
-        {
+        // 只要state中这6个Pubkey有任意一个不是Pubkey默认值就报错（表明history已经初始化过），
+        // 避免重复初始化覆盖state中已有的history账户
+        if state.is_any_history_initialized() {
             return err!(Errors::HistoriesAllInitialized);
         }
 
@@ -182,6 +265,90 @@ pub mod clearing_house {
         Ok(())
     }
 
+    pub fn initialize_extended_curve_history(
+        ctx: Context<InitializeExtendedCurveHistory>,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state.load_mut()?;
+        if !state.extended_curve_history.eq(&Pubkey::default()) {
+            return err!(Errors::ExtendedCurveHistoryAlreadyInitialized);
+        }
+
+        ctx.accounts.extended_curve_history.load_init()?;
+        state.extended_curve_history = ctx.accounts.extended_curve_history.key();
+
+        Ok(())
+    }
+
+    // 用新的空账户替换某个history账户，旧账户作为存档保留，新账户的record_id接着旧账户继续递增
+    pub fn rotate_history(ctx: Context<RotateHistory>, history_type: HistoryType) -> Result<()> {
+        let state = &mut ctx.accounts.state.load_mut()?;
+        let old_history = &ctx.accounts.old_history;
+        let new_history = &ctx.accounts.new_history;
+
+        let history_key = match history_type {
+            HistoryType::Trade => &mut state.trade_history,
+            HistoryType::Deposit => &mut state.deposit_history,
+            HistoryType::FundingPayment => &mut state.funding_payment_history,
+            HistoryType::FundingRate => &mut state.funding_rate_history,
+            HistoryType::Liquidation => &mut state.liquidation_history,
+            HistoryType::Curve => &mut state.curve_history,
+            HistoryType::ExtendedCurve => &mut state.extended_curve_history,
+            HistoryType::Order => match ctx.accounts.order_state.as_deref_mut() {
+                Some(order_state) => &mut order_state.order_history,
+                None => return err!(Errors::InvalidHistoryAccount),
+            },
+        };
+        require_keys_eq!(
+            old_history.key(),
+            *history_key,
+            Errors::InvalidHistoryAccount
+        );
+
+        match history_type {
+            HistoryType::Trade => controller::history::rotate_history::<TradeHistory>(
+                old_history,
+                new_history,
+                |_, _| {},
+            )?,
+            HistoryType::Deposit => controller::history::rotate_history::<DepositHistory>(
+                old_history,
+                new_history,
+                |_, _| {},
+            )?,
+            HistoryType::FundingPayment => controller::history::rotate_history::<
+                FundingPaymentHistory,
+            >(old_history, new_history, |_, _| {})?,
+            HistoryType::FundingRate => controller::history::rotate_history::<FundingRateHistory>(
+                old_history,
+                new_history,
+                |_, _| {},
+            )?,
+            HistoryType::Liquidation => controller::history::rotate_history::<LiquidationHistory>(
+                old_history,
+                new_history,
+                |_, _| {},
+            )?,
+            HistoryType::Curve | HistoryType::ExtendedCurve => {
+                controller::history::rotate_history::<CurveHistory>(
+                    old_history,
+                    new_history,
+                    |_, _| {},
+                )?
+            }
+            // 订单ID同样需要延续，避免新旧order history中出现重复的order_id
+            HistoryType::Order => controller::history::rotate_history::<OrderHistory>(
+                old_history,
+                new_history,
+                |old, new| {
+                    new.last_order_id = old.last_order_id;
+                },
+            )?,
+        }
+        *history_key = new_history.key();
+
+        Ok(())
+    }
+
     pub fn initialize_order_state(ctx: Context<InitializeOrderState>) -> Result<()> {
         let state = &mut ctx.accounts.state.load_mut()?;
         // 判断state中是否已经初始化过order state的
@@ -202,8 +369,925 @@ pub mod clearing_house {
                 time_based_reward_lower_bound: 10_000, // 1 cent
             },
             min_order_quote_asset_amount: 500_000, // 50 cents
+            default_max_order_age: 0,
+            version: ORDER_STATE_VERSION,
+        };
+
+        Ok(())
+    }
+
+    pub fn initialize_market(
+        ctx: Context<InitializeMarket>,
+        market_index: u64,
+        amm_base_asset_reserve: u128,
+        amm_quote_asset_reserve: u128,
+        amm_periodicity: i64,
+        amm_peg_multiplier: u128,
+        oracle_source: OracleSource,
+    ) -> Result<()> {
+        let state = ctx.accounts.state.load()?;
+        let markets = &mut ctx.accounts.markets.load_mut()?;
+        let market = markets.get_market(market_index)?;
+        if market.initialized != 0 {
+            return err!(Errors::MarketIndexAlreadyInitialized);
+        }
+
+        // 重置过的slot保留settle_market记录的min_order_id，重置前的订单不能在新market上成交
+        let min_order_id = market.min_order_id;
+        *markets.get_market_mut(market_index)? = Market {
+            min_order_id,
+            ..new_market(
+                &state,
+                &ctx.accounts.oracle,
+                amm_base_asset_reserve,
+                amm_quote_asset_reserve,
+                amm_periodicity,
+                amm_peg_multiplier,
+                oracle_source,
+            )?
+        };
+
+        Ok(())
+    }
+
+    // 在独立的MarketAccount中创建新market，market_index不能落在Markets的64个slot内
+    // （这些slot中的market只能通过migrate_market_to_account迁移出来）
+    pub fn initialize_market_account(
+        ctx: Context<InitializeMarketAccount>,
+        market_index: u64,
+        amm_base_asset_reserve: u128,
+        amm_quote_asset_reserve: u128,
+        amm_periodicity: i64,
+        amm_peg_multiplier: u128,
+        oracle_source: OracleSource,
+    ) -> Result<()> {
+        if Markets::index_from_u64(market_index).is_ok() {
+            return err!(Errors::InvalidMarketAccountIndex);
+        }
+
+        let state = ctx.accounts.state.load()?;
+        let market_account = &mut ctx.accounts.market_account.load_init()?;
+        **market_account = MarketAccount {
+            market: new_market(
+                &state,
+                &ctx.accounts.oracle,
+                amm_base_asset_reserve,
+                amm_quote_asset_reserve,
+                amm_periodicity,
+                amm_peg_multiplier,
+                oracle_source,
+            )?,
+            market_index,
+            bump: ctx.bumps.market_account,
+            version: MARKET_ACCOUNT_VERSION,
+            padding: [0; 6],
+        };
+
+        Ok(())
+    }
+
+    // 把Markets中已初始化的slot复制到独立的MarketAccount，之后该slot被标记为已迁移，
+    // 所有指令都必须通过remaining_accounts传入这个MarketAccount
+    pub fn migrate_market_to_account(
+        ctx: Context<MigrateMarketToAccount>,
+        market_index: u64,
+    ) -> Result<()> {
+        let markets = &mut ctx.accounts.markets.load_mut()?;
+        let market = markets.get_market_mut(market_index)?;
+        if market.initialized == 0 {
+            return err!(Errors::MarketIndexNotInitialized);
+        }
+
+        let market_account = &mut ctx.accounts.market_account.load_init()?;
+        **market_account = MarketAccount {
+            market: *market,
+            market_index,
+            bump: ctx.bumps.market_account,
+            version: MARKET_ACCOUNT_VERSION,
+            padding: [0; 6],
         };
+        market.migrated = 1;
+
+        Ok(())
+    }
+
+    pub fn update_margin_ratio(
+        ctx: Context<AdminUpdateState>,
+        margin_ratio_initial: u128,
+        margin_ratio_partial: u128,
+        margin_ratio_maintenance: u128,
+    ) -> Result<()> {
+        validate_margin(
+            margin_ratio_initial,
+            margin_ratio_partial,
+            margin_ratio_maintenance,
+        )?;
+
+        // state中的保证金比例只作为全局默认值，修改它不会影响已有market上的头寸
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.margin_ratio_initial = margin_ratio_initial;
+        state.margin_ratio_partial = margin_ratio_partial;
+        state.margin_ratio_maintenance = margin_ratio_maintenance;
+
+        Ok(())
+    }
+
+    #[access_control(
+        market_initialized(&ctx.accounts.markets, ctx.remaining_accounts, market_index)
+    )]
+    pub fn update_market_margin_ratio(
+        ctx: Context<AdminUpdateMarket>,
+        market_index: u64,
+        margin_ratio_initial: u32,
+        margin_ratio_partial: u32,
+        margin_ratio_maintenance: u32,
+        force: bool,
+    ) -> Result<()> {
+        validate_margin(
+            margin_ratio_initial as u128,
+            margin_ratio_partial as u128,
+            margin_ratio_maintenance as u128,
+        )?;
+
+        let markets = &mut MarketMap::load(ctx.accounts.markets.as_ref(), ctx.remaining_accounts)?;
+        let market = markets.get_market_mut(market_index)?;
+        // 调高部分清算或维持保证金比例会让已有头寸立即更接近（甚至低于）清算线，
+        // 链上无法逐个检查用户头寸，所以只要该market还有未平仓头寸，就必须显式传入force才允许调高
+        let tightening = margin_ratio_partial > market.margin_ratio_partial
+            || margin_ratio_maintenance > market.margin_ratio_maintenance;
+        if tightening && market.open_interest > 0 && !force {
+            return err!(Errors::MarginRatioTighteningNotForced);
+        }
+
+        market.margin_ratio_initial = margin_ratio_initial;
+        market.margin_ratio_partial = margin_ratio_partial;
+        market.margin_ratio_maintenance = margin_ratio_maintenance;
+
+        Ok(())
+    }
+
+    pub fn update_partial_liquidation_close_percentage(
+        ctx: Context<AdminUpdateState>,
+        numerator: u128,
+        denominator: u128,
+    ) -> Result<()> {
+        validate_liquidation_percentage(numerator, denominator)?;
+
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.partial_liquidation_close_percentage_numerator = numerator;
+        state.partial_liquidation_close_percentage_denominator = denominator;
+
+        Ok(())
+    }
+
+    pub fn update_partial_liquidation_penalty_percentage(
+        ctx: Context<AdminUpdateState>,
+        numerator: u128,
+        denominator: u128,
+    ) -> Result<()> {
+        validate_liquidation_percentage(numerator, denominator)?;
+
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.partial_liquidation_penalty_percentage_numberator = numerator;
+        state.partial_liquidation_penalty_percentage_denominator = denominator;
+
+        Ok(())
+    }
+
+    pub fn update_full_liquidation_penalty_percentage(
+        ctx: Context<AdminUpdateState>,
+        numerator: u128,
+        denominator: u128,
+    ) -> Result<()> {
+        validate_liquidation_percentage(numerator, denominator)?;
+
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.full_liquidation_penalty_percentage_numerator = numerator;
+        state.full_liquidation_penalty_percentage_denominator = denominator;
+
+        Ok(())
+    }
+
+    pub fn update_partial_liquidation_liquidator_share_denominator(
+        ctx: Context<AdminUpdateState>,
+        denominator: u128,
+    ) -> Result<()> {
+        validate_liquidator_share_denominator(denominator)?;
+
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.partial_liquidation_liquidator_share_denominator = denominator;
+
+        Ok(())
+    }
+
+    pub fn update_full_liquidation_liquidator_share_denominator(
+        ctx: Context<AdminUpdateState>,
+        denominator: u128,
+    ) -> Result<()> {
+        validate_liquidator_share_denominator(denominator)?;
+
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.full_liquidation_liquidator_share_denominator = denominator;
+
+        Ok(())
+    }
+
+    pub fn update_fee(ctx: Context<AdminUpdateState>, fee_structure: FeeStructure) -> Result<()> {
+        validate_fee_structure(&fee_structure)?;
+
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.fee_structure = fee_structure;
+
+        Ok(())
+    }
+
+    pub fn update_discount_mint(
+        ctx: Context<AdminUpdateState>,
+        discount_mint: Pubkey,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.discount_mint = discount_mint;
+
+        Ok(())
+    }
+
+    pub fn update_oracle_guard_rails(
+        ctx: Context<AdminUpdateState>,
+        oracle_guard_rails: OracleGuardRails,
+    ) -> Result<()> {
+        validate_oracle_guard_rails(&oracle_guard_rails)?;
+
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.oracle_guard_rails = oracle_guard_rails;
+
+        Ok(())
+    }
+
+    #[access_control(
+        market_initialized(&ctx.accounts.markets, ctx.remaining_accounts, market_index)
+    )]
+    pub fn update_market_oracle(
+        ctx: Context<AdminUpdateMarketOracle>,
+        market_index: u64,
+        oracle: Pubkey,
+        oracle_source: OracleSource,
+    ) -> Result<()> {
+        require_keys_eq!(ctx.accounts.oracle.key(), oracle, Errors::InvalidOracle);
+
+        let state = ctx.accounts.state.load()?;
+        let markets = &mut MarketMap::load(ctx.accounts.markets.as_ref(), ctx.remaining_accounts)?;
+        let market = markets.get_market_mut(market_index)?;
+        require_keys_eq!(
+            ctx.accounts.current_oracle.key(),
+            market.amm.oracle,
+            Errors::InvalidOracle
+        );
+
+        // amm中的last_oracle_price_twap只在market创建时写入，需要从当前oracle读取实时的TWAP作为比较基准
+        let current_oracle_twap =
+            get_oracle_twap(&market.amm.oracle_source, &ctx.accounts.current_oracle)?;
+        let amm = AMM {
+            last_oracle_price_twap: current_oracle_twap,
+            ..market.amm
+        };
+
+        // 新oracle必须能按oracle_source解析出有效价格
+        let now = Clock::get()?.unix_timestamp;
+        let clock_slot = Clock::get()?.slot;
+        let oracle_price_data = get_oracle_price(&oracle_source, &ctx.accounts.oracle, clock_slot)?;
+        if !is_oracle_valid(&amm, &oracle_price_data, &state.oracle_guard_rails.validity)? {
+            return err!(Errors::InvalidOracle);
+        }
+
+        // 新oracle的价格与当前oracle TWAP的偏离不能超过防护栏的限制（没有TWAP可比较时跳过）
+        if current_oracle_twap > 0
+            && !is_price_divergence_within_bounds(
+                oracle_price_data.price,
+                current_oracle_twap,
+                &state.oracle_guard_rails,
+            )?
+        {
+            return err!(Errors::OracleTwapDivergenceTooLarge);
+        }
+
+        // 切换oracle后TWAP改为从新oracle读取，不再沿用旧oracle的TWAP
+        let oracle_price_twap = get_oracle_twap(&oracle_source, &ctx.accounts.oracle)?;
+        market.amm.oracle = oracle;
+        market.amm.oracle_source = oracle_source;
+        market.amm.last_oracle_price = oracle_price_data.price;
+        market.amm.last_oracle_price_twap = oracle_price_twap;
+        market.amm.last_oracle_price_twap_ts = now;
 
         Ok(())
     }
+
+    pub fn update_exchange_paused(
+        ctx: Context<AdminUpdateState>,
+        exchange_paused: bool,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.exchange_paused = exchange_paused as u8;
+
+        Ok(())
+    }
+
+    // 停止或恢复把历史记录写入各history账户，停止后链下只能通过event获取历史记录
+    pub fn update_history_ring_buffers_disabled(
+        ctx: Context<AdminUpdateState>,
+        history_ring_buffers_disabled: bool,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.history_ring_buffers_disabled = history_ring_buffers_disabled as u8;
+
+        Ok(())
+    }
+
+    pub fn update_funding_paused(
+        ctx: Context<AdminUpdateState>,
+        funding_paused: bool,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.funding_paused = funding_paused as u8;
+
+        Ok(())
+    }
+
+    #[access_control(
+        market_initialized(&ctx.accounts.markets, ctx.remaining_accounts, market_index)
+    )]
+    pub fn update_market_status(
+        ctx: Context<AdminUpdateMarket>,
+        market_index: u64,
+        status: MarketStatus,
+    ) -> Result<()> {
+        // Settled状态只能通过settle_market进入，进入后也不能再修改
+        let markets = &mut MarketMap::load(ctx.accounts.markets.as_ref(), ctx.remaining_accounts)?;
+        let market = markets.get_market_mut(market_index)?;
+        if status == MarketStatus::Settled || market.status == MarketStatus::Settled {
+            return err!(Errors::InvalidMarketStatusUpdate);
+        }
+        market.status = status;
+
+        Ok(())
+    }
+
+    // 下架market：每次调用推进一步，依次进入ReduceOnly、按oracle TWAP冻结为Settled，
+    // 最后在所有头寸都已settle_position（open_interest为0）后重置market使slot可以复用
+    #[access_control(
+        market_initialized(&ctx.accounts.markets, ctx.remaining_accounts, market_index)
+    )]
+    pub fn settle_market(ctx: Context<SettleMarket>, market_index: u64) -> Result<()> {
+        let state = ctx.accounts.state.load()?;
+        let markets = &mut MarketMap::load(ctx.accounts.markets.as_ref(), ctx.remaining_accounts)?;
+        let trade_history = &mut ctx.accounts.trade_history.load_mut()?;
+        let order_history = &ctx.accounts.order_history.load()?;
+
+        controller::settlement::settle_market(
+            &state,
+            market_index,
+            markets,
+            &ctx.accounts.oracle,
+            trade_history,
+            order_history,
+            &Clock::get()?,
+        )
+    }
+
+    // 转移admin的第一步：由当前admin提名新admin，新admin调用accept_admin后才正式生效
+    pub fn propose_admin(ctx: Context<AdminUpdateState>, new_admin: Pubkey) -> Result<()> {
+        let state = &mut ctx.accounts.state.load_mut()?;
+        if new_admin.eq(&Pubkey::default()) || new_admin.eq(&state.admin) {
+            return err!(Errors::InvalidPendingAdmin);
+        }
+        state.pending_admin = new_admin;
+
+        Ok(())
+    }
+
+    // 转移admin的第二步：由被提名的新admin签名接受
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.admin = state.pending_admin;
+        state.pending_admin = Pubkey::default();
+
+        Ok(())
+    }
+
+    // 当前admin撤销尚未被接受的提名
+    pub fn cancel_admin_proposal(ctx: Context<AdminUpdateState>) -> Result<()> {
+        let state = &mut ctx.accounts.state.load_mut()?;
+        if state.pending_admin.eq(&Pubkey::default()) {
+            return err!(Errors::NoPendingAdmin);
+        }
+        state.pending_admin = Pubkey::default();
+
+        Ok(())
+    }
+
+    // 设置白名单代币的mint，设置为Pubkey默认值时表示关闭白名单
+    pub fn update_whitelist_mint(
+        ctx: Context<AdminUpdateState>,
+        whitelist_mint: Pubkey,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state.load_mut()?;
+        state.whitelist_mint = whitelist_mint;
+
+        Ok(())
+    }
+
+    // 设置订单默认的最长存活时间（秒），设置为0时表示未指定max_age的订单永不过期
+    pub fn update_default_max_order_age(
+        ctx: Context<AdminUpdateOrderState>,
+        default_max_order_age: u32,
+    ) -> Result<()> {
+        ctx.accounts.order_state.default_max_order_age = default_max_order_age;
+
+        Ok(())
+    }
+
+    #[access_control(
+        exchange_not_paused(&ctx.accounts.state)
+    )]
+    pub fn initialize_user(ctx: Context<InitializeUser>) -> Result<()> {
+        let state = ctx.accounts.state.load()?;
+        // 开启白名单时，用户必须持有至少1个whitelist_mint的代币
+        if !state.whitelist_mint.eq(&Pubkey::default()) {
+            let whitelist_token = ctx
+                .accounts
+                .whitelist_token
+                .as_ref()
+                .ok_or(Errors::WhitelistTokenNotFound)?;
+            validate_whitelist_token(
+                whitelist_token,
+                &state.whitelist_mint,
+                ctx.accounts.authority.key,
+            )?;
+        }
+
+        let user = &mut ctx.accounts.user;
+        user.authority = ctx.accounts.authority.key();
+        user.positions = ctx.accounts.user_positions.key();
+
+        let user_positions = &mut ctx.accounts.user_positions.load_init()?;
+        user_positions.user = user.key();
+
+        Ok(())
+    }
+
+    #[access_control(
+        exchange_not_paused(&ctx.accounts.state)
+    )]
+    pub fn initialize_user_orders(ctx: Context<InitializeUserOrders>) -> Result<()> {
+        let user_orders = &mut ctx.accounts.user_orders.load_init()?;
+        user_orders.user = ctx.accounts.user.key();
+
+        Ok(())
+    }
+
+    #[access_control(
+        exchange_not_paused(&ctx.accounts.state)
+    )]
+    pub fn deposit_collateral(ctx: Context<DepositCollateral>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return err!(Errors::InsufficientDeposit);
+        }
+
+        let state = ctx.accounts.state.load()?;
+        let user = &mut ctx.accounts.user;
+        let collateral_before = user.collateral;
+        let cumulative_deposits_before = user.cumulative_deposits;
+
+        user.collateral = user
+            .collateral
+            .checked_add(amount as u128)
+            .ok_or(Errors::MathError)?;
+        user.cumulative_deposits = user
+            .cumulative_deposits
+            .checked_add(amount as i128)
+            .ok_or(Errors::MathError)?;
+        // max_deposit为0表示不限制用户的累计净存款
+        if state.max_deposit > 0 && user.cumulative_deposits > state.max_deposit as i128 {
+            return err!(Errors::UserMaxDeposit);
+        }
+
+        controller::token::receive(
+            &ctx.accounts.token_program,
+            &ctx.accounts.user_collateral_account,
+            &ctx.accounts.collateral_vault,
+            &ctx.accounts.authority,
+            amount,
+        )?;
+
+        let deposit_history = &mut ctx.accounts.deposit_history.load_mut()?;
+        let record_id = deposit_history.next_record_id();
+        deposit_history.append(
+            DepositRecord {
+                ts: Clock::get()?.unix_timestamp,
+                amount,
+                record_id,
+                user_authority: user.authority,
+                user: user.key(),
+                collateral_before,
+                cumulative_deposits_before,
+                direction: DepositDirection::Deposit,
+                padding: [0; 15],
+            },
+            state.is_history_ring_buffer_enabled(),
+        );
+
+        Ok(())
+    }
+
+    #[access_control(
+        exchange_not_paused_or_reduce_only(&ctx.accounts.state, params.reduce_only)
+        market_initialized(&ctx.accounts.markets, ctx.remaining_accounts, params.market_index)
+    )]
+    pub fn place_order(ctx: Context<PlaceOrder>, params: OrderParams) -> Result<()> {
+        // IOC订单不能挂单，需要通过place_and_fill_order下单并立即成交
+        if params.immediate_or_cancel {
+            return err!(Errors::InvalidOrder);
+        }
+
+        let referrer = controller::orders::lock_referrer(
+            &mut ctx.accounts.user,
+            ctx.accounts.referrer.as_deref(),
+        )?;
+        let state = ctx.accounts.state.load()?;
+        let markets = MarketMap::load(ctx.accounts.markets.as_ref(), ctx.remaining_accounts)?;
+        let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+        let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+        let order_history = &mut ctx.accounts.order_history.load_mut()?;
+
+        controller::orders::place_order(
+            &state,
+            &ctx.accounts.order_state,
+            ctx.accounts.user.key(),
+            ctx.accounts.authority.key(),
+            user_positions,
+            &markets,
+            user_orders,
+            order_history,
+            &ctx.accounts.oracle,
+            ctx.accounts.discount_token.as_deref().map(|token| &**token),
+            referrer,
+            &Clock::get()?,
+            params,
+        )
+    }
+
+    // 下单并立即与AMM成交，用户作为自己订单的filler（没有filler奖励），
+    // 订单的price作为成交价格的滑点限制，未能成交的剩余部分直接取消
+    #[access_control(
+        exchange_not_paused_or_reduce_only(&ctx.accounts.state, params.reduce_only)
+        market_initialized(&ctx.accounts.markets, ctx.remaining_accounts, params.market_index)
+    )]
+    pub fn place_and_fill_order(
+        ctx: Context<PlaceAndFillOrder>,
+        params: OrderParams,
+    ) -> Result<()> {
+        // 只支持市价单与限价单（包括IOC限价单）：触发单应通过place_order挂单
+        if params.order_type != OrderType::Market && params.order_type != OrderType::Limit {
+            return err!(Errors::InvalidOrder);
+        }
+
+        let referrer = controller::orders::lock_referrer(
+            &mut ctx.accounts.user,
+            ctx.accounts.referrer.as_deref(),
+        )?;
+        let state = ctx.accounts.state.load()?;
+        let markets = &mut MarketMap::load(ctx.accounts.markets.as_ref(), ctx.remaining_accounts)?;
+        let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+        let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+        let trade_history = &mut ctx.accounts.trade_history.load_mut()?;
+        let order_history = &mut ctx.accounts.order_history.load_mut()?;
+        let clock = Clock::get()?;
+
+        controller::orders::place_order(
+            &state,
+            &ctx.accounts.order_state,
+            ctx.accounts.user.key(),
+            ctx.accounts.authority.key(),
+            user_positions,
+            markets,
+            user_orders,
+            order_history,
+            &ctx.accounts.oracle,
+            ctx.accounts.discount_token.as_deref().map(|token| &**token),
+            referrer,
+            &clock,
+            params,
+        )?;
+
+        let order_id = order_history.last_order_id;
+        // 滑点限制内没有可成交的数量时不回滚整笔交易，直接走下面的取消流程
+        match controller::orders::fill_order(
+            order_id,
+            &state,
+            &ctx.accounts.order_state,
+            &mut ctx.accounts.user,
+            user_positions,
+            user_orders,
+            markets,
+            &ctx.accounts.oracle,
+            None,
+            ctx.accounts.referrer.as_deref_mut(),
+            trade_history,
+            order_history,
+            &clock,
+        ) {
+            Err(error) if error == error!(Errors::CouldNotFillOrder) => {}
+            result => {
+                result?;
+            }
+        }
+
+        // 订单未完全成交且没有被fill_order取消时，取消剩余部分
+        if let Ok(order_index) = user_orders.get_order_index(order_id) {
+            controller::orders::cancel_order(
+                &state,
+                order_index,
+                ctx.accounts.user.key(),
+                ctx.accounts.authority.key(),
+                user_positions,
+                user_orders,
+                order_history,
+                &clock,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // 任何人都可以作为filler执行其他用户的订单并获得filler奖励
+    pub fn fill_order(ctx: Context<FillOrder>, order_id: u128) -> Result<()> {
+        let state = ctx.accounts.state.load()?;
+        let markets = &mut MarketMap::load(ctx.accounts.markets.as_ref(), ctx.remaining_accounts)?;
+        let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+        let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+        let trade_history = &mut ctx.accounts.trade_history.load_mut()?;
+        let order_history = &mut ctx.accounts.order_history.load_mut()?;
+
+        controller::orders::fill_order(
+            order_id,
+            &state,
+            &ctx.accounts.order_state,
+            &mut ctx.accounts.user,
+            user_positions,
+            user_orders,
+            markets,
+            &ctx.accounts.oracle,
+            Some(&mut ctx.accounts.filler),
+            ctx.accounts.referrer.as_deref_mut(),
+            trade_history,
+            order_history,
+            &Clock::get()?,
+        )?;
+
+        Ok(())
+    }
+
+    // 任何人都可以清除用户已过期的订单并获得奖励
+    pub fn expire_orders(ctx: Context<ExpireOrders>) -> Result<()> {
+        let state = ctx.accounts.state.load()?;
+        let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+        let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+        let order_history = &mut ctx.accounts.order_history.load_mut()?;
+
+        controller::orders::expire_orders(
+            &state,
+            &ctx.accounts.order_state,
+            &mut ctx.accounts.user,
+            user_positions,
+            user_orders,
+            &mut ctx.accounts.filler,
+            order_history,
+            &Clock::get()?,
+        )
+    }
+
+    pub fn cancel_order(ctx: Context<CancelOrder>, order_id: u128) -> Result<()> {
+        let state = ctx.accounts.state.load()?;
+        let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+        let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+        let order_history = &mut ctx.accounts.order_history.load_mut()?;
+        let order_index = user_orders.get_order_index(order_id)?;
+
+        controller::orders::cancel_order(
+            &state,
+            order_index,
+            ctx.accounts.user.key(),
+            ctx.accounts.authority.key(),
+            user_positions,
+            user_orders,
+            order_history,
+            &Clock::get()?,
+        )
+    }
+
+    pub fn cancel_order_by_user_order_id(
+        ctx: Context<CancelOrder>,
+        user_order_id: u8,
+    ) -> Result<()> {
+        let state = ctx.accounts.state.load()?;
+        let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+        let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+        let order_history = &mut ctx.accounts.order_history.load_mut()?;
+        let order_index = user_orders.get_order_index_by_user_order_id(user_order_id)?;
+
+        controller::orders::cancel_order(
+            &state,
+            order_index,
+            ctx.accounts.user.key(),
+            ctx.accounts.authority.key(),
+            user_positions,
+            user_orders,
+            order_history,
+            &Clock::get()?,
+        )
+    }
+
+    // 用户在已结算的market上按settlement_price平仓（同时取消该market上的所有订单）
+    #[access_control(
+        market_initialized(&ctx.accounts.markets, ctx.remaining_accounts, market_index)
+    )]
+    pub fn settle_position(ctx: Context<SettlePosition>, market_index: u64) -> Result<()> {
+        let state = ctx.accounts.state.load()?;
+        let markets = &mut MarketMap::load(ctx.accounts.markets.as_ref(), ctx.remaining_accounts)?;
+        let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+        let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+        let trade_history = &mut ctx.accounts.trade_history.load_mut()?;
+        let order_history = &mut ctx.accounts.order_history.load_mut()?;
+
+        controller::settlement::settle_position(
+            &state,
+            market_index,
+            &mut ctx.accounts.user,
+            user_positions,
+            user_orders,
+            markets,
+            trade_history,
+            order_history,
+            &Clock::get()?,
+        )
+    }
+
+    // 一次性取消用户的所有订单，可以按market和方向过滤（为None时不过滤）
+    pub fn cancel_all_orders(
+        ctx: Context<CancelOrder>,
+        market_index: Option<u64>,
+        direction: Option<PositionDirection>,
+    ) -> Result<()> {
+        let state = ctx.accounts.state.load()?;
+        let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+        let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+        let order_history = &mut ctx.accounts.order_history.load_mut()?;
+        let clock = Clock::get()?;
+
+        for order_index in 0..user_orders.orders.len() {
+            let order = &user_orders.orders[order_index];
+            if order.is_available()
+                || market_index.is_some_and(|market_index| order.market_index != market_index)
+                || direction.is_some_and(|direction| order.direction != direction)
+            {
+                continue;
+            }
+
+            controller::orders::cancel_order(
+                &state,
+                order_index,
+                ctx.accounts.user.key(),
+                ctx.accounts.authority.key(),
+                user_positions,
+                user_orders,
+                order_history,
+                &clock,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn delete_user_orders(ctx: Context<DeleteUserOrders>) -> Result<()> {
+        // 还有未完成的订单时不允许删除，否则这些订单在UserPositions中计入的open_orders将无法被清除
+        if !ctx.accounts.user_orders.load()?.is_empty() {
+            return err!(Errors::UserOrdersNotEmpty);
+        }
+
+        Ok(())
+    }
+}
+
+// 检查exchange是否处于暂停状态
+fn exchange_not_paused(state: &AccountLoader<State>) -> Result<()> {
+    if state.load()?.is_exchange_paused() {
+        return err!(Errors::ExchangePaused);
+    }
+    Ok(())
+}
+
+// exchange暂停时进入只减仓模式：只允许reduce only订单（取消订单、settle_position等不增加风险的指令不受暂停限制）
+fn exchange_not_paused_or_reduce_only(
+    state: &AccountLoader<State>,
+    reduce_only: bool,
+) -> Result<()> {
+    if state.load()?.is_exchange_paused() && !reduce_only {
+        return err!(Errors::ExchangePaused);
+    }
+    Ok(())
+}
+
+// 按初始参数创建一个新market（Markets的slot与MarketAccount共用）
+fn new_market(
+    state: &State,
+    oracle: &AccountInfo,
+    amm_base_asset_reserve: u128,
+    amm_quote_asset_reserve: u128,
+    amm_periodicity: i64,
+    amm_peg_multiplier: u128,
+    oracle_source: OracleSource,
+) -> Result<Market> {
+    // 初始时base与quote储备量必须相等，此时mark price完全由peg_multiplier决定
+    if amm_base_asset_reserve != amm_quote_asset_reserve || amm_peg_multiplier == 0 {
+        return err!(Errors::InvalidInitialPeg);
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+    let clock_slot = Clock::get()?.slot;
+    let oracle_price_data = get_oracle_price(&oracle_source, oracle, clock_slot)?;
+    let init_mark_price = calculate_price(
+        amm_quote_asset_reserve,
+        amm_base_asset_reserve,
+        amm_peg_multiplier,
+    )?;
+
+    // 新market的保证金比例使用state中的全局默认值（已由validate_margin保证不超过MARGIN_PRECISION）
+    let market = Market {
+        base_asset_amount_long: 0,
+        base_asset_amount_short: 0,
+        base_asset_amount: 0,
+        open_interest: 0,
+        amm: AMM {
+            base_asset_reserve: amm_base_asset_reserve,
+            quote_asset_reserve: amm_quote_asset_reserve,
+            sqrt_k: amm_base_asset_reserve,
+            cumulative_repeg_rebate_long: 0,
+            cumulative_repeg_rebate_short: 0,
+            cumulative_funding_rate_long: 0,
+            cumulative_funding_rate_short: 0,
+            last_funding_rate: 0,
+            last_funding_rate_ts: now,
+            funding_period: amm_periodicity,
+            peg_multiplier: amm_peg_multiplier,
+            total_fee: 0,
+            total_fee_minus_distributions: 0,
+            total_fee_withdrawn: 0,
+            minimum_base_asset_trade_size: DEFAULT_MINIMUM_BASE_ASSET_TRADE_SIZE,
+            mininum_quote_asset_trade_size: DEFAULT_MINIMUM_QUOTE_ASSET_TRADE_SIZE,
+            last_mark_price_twap: init_mark_price,
+            last_mark_price_twap_ts: now,
+            last_oracle_price_twap_ts: now,
+            last_oracle_price_twap: oracle_price_data.price,
+            oracle: oracle.key(),
+            last_oracle_price: oracle_price_data.price,
+            base_spread: 0,
+            oracle_source,
+            padding: [0; 13],
+        },
+        margin_ratio_initial: state.margin_ratio_initial as u32,
+        margin_ratio_partial: state.margin_ratio_partial as u32,
+        margin_ratio_maintenance: state.margin_ratio_maintenance as u32,
+        initialized: 1,
+        status: MarketStatus::Active,
+        migrated: 0,
+        padding0: [0],
+        settlement_price: 0,
+        min_order_id: 0,
+        padding3: 0,
+        padding4: 0,
+    };
+
+    // oracle必须能按oracle_source解析出有效价格（价格为正、置信区间与延迟在防护栏范围内）
+    if !is_oracle_valid(
+        &market.amm,
+        &oracle_price_data,
+        &state.oracle_guard_rails.validity,
+    )? {
+        return err!(Errors::InvalidOracle);
+    }
+
+    Ok(market)
+}
+
+// 检查market_index对应的market是否已初始化
+// market可能在Markets的slot中，也可能在remaining_accounts传入的MarketAccount中
+fn market_initialized(
+    markets: &Option<AccountLoader<Markets>>,
+    market_accounts: &[AccountInfo],
+    market_index: u64,
+) -> Result<()> {
+    let market_map = MarketMap::load(markets.as_ref(), market_accounts)?;
+    if market_map.get_market(market_index)?.initialized == 0 {
+        return err!(Errors::MarketIndexNotInitialized);
+    }
+    Ok(())
 }
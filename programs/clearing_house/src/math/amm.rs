@@ -0,0 +1,137 @@
+use anchor_lang::prelude::*;
+
+use crate::controller::position::PositionDirection;
+use crate::errors::Errors;
+use crate::math::bn::{U192, U256};
+use crate::math::constant::{AMM_TIMES_PEG_TO_QUOTE_PRECISION_RATIO, PRICE_TO_PEG_PRECISION_RATIO};
+use crate::state::market::AMM;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SwapDirection {
+    Add,    // 向AMM中放入资产
+    Remove, // 从AMM中取出资产
+}
+
+// 计算AMM的价格（MARK_PRICE_PRECISION精度）：
+// price = quote_asset_reserve * peg_multiplier / base_asset_reserve
+pub fn calculate_price(
+    quote_asset_reserve: u128,
+    base_asset_reserve: u128,
+    peg_multiplier: u128,
+) -> Result<u128> {
+    let peg_quote_asset_amount = quote_asset_reserve
+        .checked_mul(peg_multiplier)
+        .ok_or(Errors::MathError)?;
+
+    let price = U192::from(peg_quote_asset_amount)
+        .checked_mul(U192::from(PRICE_TO_PEG_PRECISION_RATIO))
+        .ok_or(Errors::MathError)?
+        .checked_div(U192::from(base_asset_reserve))
+        .ok_or(Errors::MathError)?
+        .try_to_u128()?;
+
+    Ok(price)
+}
+
+// 按x*y=k计算swap后的储备量：input储备按swap_direction增减swap_amount，output储备由k反推
+// 返回(new_output_asset_reserve, new_input_asset_reserve)
+pub fn calculate_swap_output(
+    swap_amount: u128,
+    input_asset_reserve: u128,
+    swap_direction: SwapDirection,
+    invariant_sqrt: u128,
+) -> Result<(u128, u128)> {
+    let invariant_sqrt = U192::from(invariant_sqrt);
+    let invariant = invariant_sqrt
+        .checked_mul(invariant_sqrt)
+        .ok_or(Errors::MathError)?;
+
+    let new_input_asset_reserve = match swap_direction {
+        SwapDirection::Add => input_asset_reserve.checked_add(swap_amount),
+        SwapDirection::Remove => input_asset_reserve.checked_sub(swap_amount),
+    }
+    .ok_or(Errors::MathError)?;
+
+    let new_output_asset_reserve = invariant
+        .checked_div(U192::from(new_input_asset_reserve))
+        .ok_or(Errors::MathError)?
+        .try_to_u128()?;
+
+    Ok((new_output_asset_reserve, new_input_asset_reserve))
+}
+
+// 将quote储备量（AMM_RESERVE_PRECISION精度）换算为quote资产数量（QUOTE_PRECISION精度）
+pub fn reserve_to_asset_amount(quote_asset_reserve: u128, peg_multiplier: u128) -> Result<u128> {
+    Ok(quote_asset_reserve
+        .checked_mul(peg_multiplier)
+        .ok_or(Errors::MathError)?
+        .checked_div(AMM_TIMES_PEG_TO_QUOTE_PRECISION_RATIO)
+        .ok_or(Errors::MathError)?)
+}
+
+// 将quote资产数量（QUOTE_PRECISION精度）换算为quote储备量（AMM_RESERVE_PRECISION精度）
+pub fn asset_to_reserve_amount(quote_asset_amount: u128, peg_multiplier: u128) -> Result<u128> {
+    Ok(quote_asset_amount
+        .checked_mul(AMM_TIMES_PEG_TO_QUOTE_PRECISION_RATIO)
+        .ok_or(Errors::MathError)?
+        .checked_div(peg_multiplier)
+        .ok_or(Errors::MathError)?)
+}
+
+// 计算用quote_asset_amount按direction与AMM交易能换到的base数量（不修改AMM）
+pub fn calculate_base_asset_amount_for_quote(
+    amm: &AMM,
+    quote_asset_amount: u128,
+    direction: PositionDirection,
+) -> Result<u128> {
+    let quote_asset_reserve_amount =
+        asset_to_reserve_amount(quote_asset_amount, amm.peg_multiplier)?;
+    // 做多时向AMM中放入quote，做空时从AMM中取出quote
+    let swap_direction = match direction {
+        PositionDirection::Long => SwapDirection::Add,
+        PositionDirection::Short => SwapDirection::Remove,
+    };
+    let (new_base_asset_reserve, _) = calculate_swap_output(
+        quote_asset_reserve_amount,
+        amm.quote_asset_reserve,
+        swap_direction,
+        amm.sqrt_k,
+    )?;
+
+    Ok(new_base_asset_reserve.abs_diff(amm.base_asset_reserve))
+}
+
+// 计算按direction交易、使mark price恰好移动到limit_price所需的base数量，mark price已越过limit_price时返回0
+// 由price = k * peg / base_asset_reserve^2可得：base_asset_reserve = sqrt(k * peg / limit_price)
+pub fn calculate_base_asset_amount_to_trade_to_price(
+    amm: &AMM,
+    limit_price: u128,
+    direction: PositionDirection,
+) -> Result<u128> {
+    if limit_price == 0 {
+        return err!(Errors::MathError);
+    }
+
+    let invariant_sqrt = U256::from(amm.sqrt_k);
+    let new_base_asset_reserve = invariant_sqrt
+        .checked_mul(invariant_sqrt)
+        .ok_or(Errors::MathError)?
+        .checked_mul(U256::from(amm.peg_multiplier))
+        .ok_or(Errors::MathError)?
+        .checked_mul(U256::from(PRICE_TO_PEG_PRECISION_RATIO))
+        .ok_or(Errors::MathError)?
+        .checked_div(U256::from(limit_price))
+        .ok_or(Errors::MathError)?
+        .integer_sqrt()
+        .try_to_u128()?;
+
+    // 做多会减少base储备（推高价格），做空会增加base储备（压低价格）
+    let base_asset_amount = match direction {
+        PositionDirection::Long => amm
+            .base_asset_reserve
+            .saturating_sub(new_base_asset_reserve),
+        PositionDirection::Short => new_base_asset_reserve.saturating_sub(amm.base_asset_reserve),
+    };
+
+    Ok(base_asset_amount)
+}
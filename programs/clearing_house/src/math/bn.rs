@@ -31,7 +31,7 @@ macro_rules! impl_anchor_deserialize_for_bn {
         impl AnchorDeserialize for $type {
             #[inline]
             fn deserialize_reader<R: Read>(reader: &mut R) -> std::io::Result<Self> {
-                let mut buf = Vec::with_capacity(size_of::<Self>());
+                let mut buf = vec![0u8; size_of::<Self>()];
                 reader.read_exact(&mut buf)?;
                 Ok(Self::from_little_endian(&buf))
             }
@@ -54,17 +54,17 @@ impl U192 {
 
     // U192安全转换为u64，返回Option
     pub fn to_u64(self) -> Option<u64> {
-        self.try_to_u64().map_or(None, Some)
+        self.try_to_u64().ok()
     }
 
     // U192安全转换为u128，返回Result
-    pub fn try_to_u128(self) -> ClearingHouseResult<u64> {
+    pub fn try_to_u128(self) -> ClearingHouseResult<u128> {
         self.try_into().map_err(|_| BnConversionError)
     }
 
     // U192安全转换为u128，返回Option
-    pub fn to_u128(self) -> Option<u64> {
-        self.try_to_u128().map_or(None, Some)
+    pub fn to_u128(self) -> Option<u128> {
+        self.try_to_u128().ok()
     }
 }
 
@@ -85,17 +85,17 @@ impl U256 {
 
     // U256安全转换为u64，返回Option
     pub fn to_u64(self) -> Option<u64> {
-        self.try_to_u64().map_or(None, Some)
+        self.try_to_u64().ok()
     }
 
     // U256安全转换为u128，返回Result
-    pub fn try_to_u128(self) -> ClearingHouseResult<u64> {
+    pub fn try_to_u128(self) -> ClearingHouseResult<u128> {
         self.try_into().map_err(|_| BnConversionError)
     }
 
     // U256安全转换为u128，返回Option
-    pub fn to_u128(self) -> Option<u64> {
-        self.try_to_u128().map_or(None, Some)
+    pub fn to_u128(self) -> Option<u128> {
+        self.try_to_u128().ok()
     }
 }
 
@@ -16,3 +16,27 @@ pub const DEFAULT_REFERRER_REWARD_NUMERATOR: u128 = 5;
 pub const DEFAULT_REFERRER_REWARD_DENOMINATOR: u128 = 100;
 pub const DEFAULT_REFEREE_DISCOUNT_NUMERATOR: u128 = 5;
 pub const DEFAULT_REFEREE_DISCOUNT_DENOMINATOR: u128 = 100;
+
+// 保证金比例精度（10000即100%）
+pub const MARGIN_PRECISION: u128 = 10_000;
+// 保证金比例上限：100%（即1倍杠杆）
+pub const MAXIMUM_MARGIN_RATIO: u128 = MARGIN_PRECISION;
+// 保证金比例下限：2%（即50倍杠杆）
+pub const MINIMUM_MARGIN_RATIO: u128 = MARGIN_PRECISION / 50;
+
+// 价格精度（mark price与oracle price都统一到该精度）
+pub const MARK_PRICE_PRECISION: u128 = 10_000_000_000;
+// AMM储备量（以及base资产数量）精度
+pub const AMM_RESERVE_PRECISION: u128 = 10_000_000_000_000;
+// quote资产（抵押品）精度
+pub const QUOTE_PRECISION: u128 = 1_000_000;
+// 锚定乘数精度
+pub const PEG_PRECISION: u128 = 1_000;
+pub const PRICE_TO_PEG_PRECISION_RATIO: u128 = MARK_PRICE_PRECISION / PEG_PRECISION; // 10^7
+pub const AMM_TO_QUOTE_PRECISION_RATIO: u128 = AMM_RESERVE_PRECISION / QUOTE_PRECISION; // 10^7
+pub const AMM_TIMES_PEG_TO_QUOTE_PRECISION_RATIO: u128 =
+    AMM_TO_QUOTE_PRECISION_RATIO * PEG_PRECISION; // 10^10
+
+// 新market默认的最小交易量
+pub const DEFAULT_MINIMUM_BASE_ASSET_TRADE_SIZE: u128 = 10_000_000;
+pub const DEFAULT_MINIMUM_QUOTE_ASSET_TRADE_SIZE: u128 = 10_000_000;
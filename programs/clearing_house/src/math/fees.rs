@@ -0,0 +1,143 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+use crate::errors::Errors;
+use crate::state::order_state::OrderFillerRewardStructure;
+use crate::state::state::FeeStructure;
+use crate::state::user_orders::OrderDiscountTier;
+
+// 一次成交中手续费的拆分结果
+pub struct FillFees {
+    pub user_fee: u128,         // 用户实际支付的手续费（已扣除各类折扣）
+    pub fee_to_market: u128,    // 留给market的手续费
+    pub token_discount: u128,   // 持币折扣
+    pub filler_reward: u128,    // 支付给filler的奖励
+    pub referrer_reward: u128,  // 支付给推荐人的奖励
+    pub referee_discount: u128, // 被推荐人（即用户）获得的折扣
+}
+
+// 按用户持有的折扣代币余额确定订单的手续费折扣等级（第1档要求的余额最高）
+pub fn calculate_order_fee_tier(
+    fee_structure: &FeeStructure,
+    discount_token: Option<&TokenAccount>,
+) -> OrderDiscountTier {
+    let Some(discount_token) = discount_token else {
+        return OrderDiscountTier::None;
+    };
+
+    let tiers = &fee_structure.discount_token_tiers;
+    let balance = discount_token.amount;
+    if balance >= tiers.first_tier.minimun_balance {
+        OrderDiscountTier::First
+    } else if balance >= tiers.second_tier.minimun_balance {
+        OrderDiscountTier::Second
+    } else if balance >= tiers.third_tier.minimun_balance {
+        OrderDiscountTier::Third
+    } else if balance >= tiers.fourth_tier.minimun_balance {
+        OrderDiscountTier::Fourth
+    } else {
+        OrderDiscountTier::None
+    }
+}
+
+// 计算订单一次成交的手续费：
+// 1. 基础手续费按fee_numerator/fee_denominator收取，再扣除下单时锁定的持币折扣与被推荐人折扣
+// 2. filler按用户手续费的reward_numerator/reward_denominator获得奖励，且不低于time_based_reward_lower_bound，
+//    但奖励（加上推荐人奖励）不会超过用户实际支付的手续费
+// 3. filler就是用户自己时没有filler奖励
+pub fn calculate_fee_for_order(
+    quote_asset_amount: u128,
+    fee_structure: &FeeStructure,
+    filler_reward_structure: &OrderFillerRewardStructure,
+    discount_tier: &OrderDiscountTier,
+    has_referrer: bool,
+    filler_is_user: bool,
+) -> Result<FillFees> {
+    let fee = mul_div(
+        quote_asset_amount,
+        fee_structure.fee_numerator,
+        fee_structure.fee_denominator,
+    )?;
+
+    let tiers = &fee_structure.discount_token_tiers;
+    let token_discount = match discount_tier {
+        OrderDiscountTier::None => 0,
+        OrderDiscountTier::First => mul_div(
+            fee,
+            tiers.first_tier.discount_numerator,
+            tiers.first_tier.discount_denominator,
+        )?,
+        OrderDiscountTier::Second => mul_div(
+            fee,
+            tiers.second_tier.discount_numerator,
+            tiers.second_tier.discount_denominator,
+        )?,
+        OrderDiscountTier::Third => mul_div(
+            fee,
+            tiers.third_tier.discount_numerator,
+            tiers.third_tier.discount_denominator,
+        )?,
+        OrderDiscountTier::Fourth => mul_div(
+            fee,
+            tiers.fourth_tier.discount_numerator,
+            tiers.fourth_tier.discount_denominator,
+        )?,
+    };
+
+    let referral_discount = &fee_structure.referral_discount;
+    let (referrer_reward, referee_discount) = if has_referrer {
+        (
+            mul_div(
+                fee,
+                referral_discount.referral_reward_numerator,
+                referral_discount.referral_reward_denominator,
+            )?,
+            mul_div(
+                fee,
+                referral_discount.referee_discount_numerator,
+                referral_discount.referee_discount_denominator,
+            )?,
+        )
+    } else {
+        (0, 0)
+    };
+
+    let user_fee = fee
+        .checked_sub(token_discount)
+        .ok_or(Errors::MathError)?
+        .checked_sub(referee_discount)
+        .ok_or(Errors::MathError)?;
+    // 推荐人奖励不能超过用户实际支付的手续费
+    let referrer_reward = referrer_reward.min(user_fee);
+
+    let filler_reward = if filler_is_user {
+        0
+    } else {
+        mul_div(
+            user_fee,
+            filler_reward_structure.reward_numerator,
+            filler_reward_structure.reward_denominator,
+        )?
+        .max(filler_reward_structure.time_based_reward_lower_bound)
+        .min(user_fee - referrer_reward)
+    };
+
+    let fee_to_market = user_fee - referrer_reward - filler_reward;
+
+    Ok(FillFees {
+        user_fee,
+        fee_to_market,
+        token_discount,
+        filler_reward,
+        referrer_reward,
+        referee_discount,
+    })
+}
+
+fn mul_div(amount: u128, numerator: u128, denominator: u128) -> Result<u128> {
+    Ok(amount
+        .checked_mul(numerator)
+        .ok_or(Errors::MathError)?
+        .checked_div(denominator)
+        .ok_or(Errors::MathError)?)
+}
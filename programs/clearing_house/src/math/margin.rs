@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::Errors;
+use crate::math::constant::MARGIN_PRECISION;
+use crate::math::position::{calculate_base_asset_value_and_pnl, calculate_updated_collateral};
+use crate::state::market_map::MarketMap;
+use crate::state::user::{User, UserPositions};
+
+// 计算用户的总抵押品（collateral加上所有头寸的未实现盈亏）以及所有头寸按初始保证金比例计算的保证金要求
+pub fn calculate_total_collateral_and_initial_margin_requirement(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &MarketMap,
+) -> Result<(u128, u128)> {
+    let mut unrealized_pnl: i128 = 0;
+    let mut initial_margin_requirement: u128 = 0;
+
+    for market_position in user_positions.positions.iter() {
+        if market_position.base_asset_amount == 0 {
+            continue;
+        }
+
+        let market = markets.get_market(market_position.market_index)?;
+        let (base_asset_value, pnl) =
+            calculate_base_asset_value_and_pnl(market_position, &market.amm)?;
+
+        unrealized_pnl = unrealized_pnl.checked_add(pnl).ok_or(Errors::MathError)?;
+        initial_margin_requirement = base_asset_value
+            .checked_mul(market.margin_ratio_initial as u128)
+            .ok_or(Errors::MathError)?
+            .checked_div(MARGIN_PRECISION)
+            .ok_or(Errors::MathError)?
+            .checked_add(initial_margin_requirement)
+            .ok_or(Errors::MathError)?;
+    }
+
+    let total_collateral = calculate_updated_collateral(user.collateral, unrealized_pnl)?;
+
+    Ok((total_collateral, initial_margin_requirement))
+}
+
+// 用户的总抵押品是否满足初始保证金要求
+pub fn meets_initial_margin_requirement(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &MarketMap,
+) -> Result<bool> {
+    let (total_collateral, initial_margin_requirement) =
+        calculate_total_collateral_and_initial_margin_requirement(user, user_positions, markets)?;
+
+    Ok(total_collateral >= initial_margin_requirement)
+}
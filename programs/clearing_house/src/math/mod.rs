@@ -1 +1,10 @@
+pub mod amm;
+// construct_uint!宏生成的代码会触发该lint
+#[allow(clippy::manual_div_ceil)]
+pub mod bn;
 pub mod constant;
+pub mod fees;
+pub mod margin;
+pub mod oracle;
+pub mod orders;
+pub mod position;
@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::Errors;
+use crate::state::market::AMM;
+use crate::state::oracle::OraclePriceData;
+use crate::state::state::{OracleGuardRails, ValidityGuardRails};
+
+// 按ValidityGuardRails判断oracle价格是否可用：
+// 1. 价格必须为正
+// 2. 最新价格与oracle TWAP的比值不能超过too_volatile_ratio
+// 3. 价格/置信区间不能小于confidence_interval_max_size（即置信区间不能过宽）
+// 4. oracle价格不能超过slots_before_stable个slot没有更新
+pub fn is_oracle_valid(
+    amm: &AMM,
+    oracle_price_data: &OraclePriceData,
+    valid_oracle_guard_rails: &ValidityGuardRails,
+) -> Result<bool> {
+    let OraclePriceData {
+        price: oracle_price,
+        confidence: oracle_conf,
+        delay: oracle_delay,
+    } = *oracle_price_data;
+
+    let is_oracle_price_nonpositive = oracle_price <= 0;
+
+    let is_oracle_price_too_volatile = amm.last_oracle_price_twap > 0
+        && oracle_price > 0
+        && oracle_price.max(amm.last_oracle_price_twap)
+            / oracle_price.min(amm.last_oracle_price_twap)
+            > valid_oracle_guard_rails.too_volatile_ratio;
+
+    let conf_denom_of_price = oracle_price.unsigned_abs() / oracle_conf.max(1);
+    let is_conf_too_large =
+        conf_denom_of_price < valid_oracle_guard_rails.confidence_interval_max_size;
+
+    let is_stale = oracle_delay > valid_oracle_guard_rails.slots_before_stable;
+
+    Ok(!(is_oracle_price_nonpositive
+        || is_oracle_price_too_volatile
+        || is_conf_too_large
+        || is_stale))
+}
+
+// 判断price与参考价格reference_price的偏离是否在price_divergence允许的范围内：
+// |price - reference_price| / reference_price <= numerator / denominator
+pub fn is_price_divergence_within_bounds(
+    price: i128,
+    reference_price: i128,
+    guard_rails: &OracleGuardRails,
+) -> Result<bool> {
+    let divergence = price
+        .checked_sub(reference_price)
+        .ok_or(Errors::MathError)?
+        .unsigned_abs();
+    let price_divergence = &guard_rails.price_divergence;
+    let lhs = divergence
+        .checked_mul(price_divergence.mark_oracle_divergence_denominator)
+        .ok_or(Errors::MathError)?;
+    let rhs = reference_price
+        .unsigned_abs()
+        .checked_mul(price_divergence.mark_oracle_divergence_numerator)
+        .ok_or(Errors::MathError)?;
+    Ok(lhs <= rhs)
+}
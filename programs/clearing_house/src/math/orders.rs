@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::Errors;
+use crate::math::bn::U192;
+use crate::math::constant::{AMM_TO_QUOTE_PRECISION_RATIO, MARK_PRICE_PRECISION};
+use crate::state::user_orders::{Order, OrderTriggerPriceSource};
+
+// 按价格估算base资产数量对应的quote资产价值（QUOTE_PRECISION精度）：
+// quote_asset_amount = base_asset_amount * price / (AMM_TO_QUOTE_PRECISION_RATIO * MARK_PRICE_PRECISION)
+pub fn calculate_quote_asset_amount_for_price(
+    base_asset_amount: u128,
+    price: u128,
+) -> Result<u128> {
+    let quote_asset_amount = U192::from(base_asset_amount)
+        .checked_mul(U192::from(price))
+        .ok_or(Errors::MathError)?
+        .checked_div(U192::from(
+            AMM_TO_QUOTE_PRECISION_RATIO * MARK_PRICE_PRECISION,
+        ))
+        .ok_or(Errors::MathError)?
+        .try_to_u128()?;
+
+    Ok(quote_asset_amount)
+}
+
+// 按订单的trigger_price_source取判断触发条件所用的当前价格
+pub fn get_trigger_reference_price(
+    order: &Order,
+    mark_price: u128,
+    oracle_price: i128,
+) -> Result<u128> {
+    match order.trigger_price_source {
+        OrderTriggerPriceSource::Mark => Ok(mark_price),
+        OrderTriggerPriceSource::Oracle => {
+            Ok(u128::try_from(oracle_price).map_err(|_| Errors::InvalidOracle)?)
+        }
+    }
+}
+
+// 计算订单成交时的限价：oracle偏移订单的限价为oracle价格加上偏移量，其余订单为Order.price
+pub fn calculate_limit_price(order: &Order, oracle_price: i128) -> Result<u128> {
+    if order.oracle_price_offset == 0 {
+        return Ok(order.price);
+    }
+
+    let limit_price = oracle_price
+        .checked_add(order.oracle_price_offset)
+        .ok_or(Errors::MathError)?;
+    if limit_price <= 0 {
+        return err!(Errors::InvalidOraclePriceOffset);
+    }
+
+    Ok(limit_price.unsigned_abs())
+}
@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::Errors;
+use crate::math::amm::calculate_price;
+use crate::math::orders::calculate_quote_asset_amount_for_price;
+use crate::state::market::AMM;
+use crate::state::user::MarketPosition;
+
+// 按当前mark price计算头寸的价值（QUOTE_PRECISION精度）与未实现盈亏
+pub fn calculate_base_asset_value_and_pnl(
+    market_position: &MarketPosition,
+    amm: &AMM,
+) -> Result<(u128, i128)> {
+    if market_position.base_asset_amount == 0 {
+        return Ok((0, 0));
+    }
+
+    let mark_price = calculate_price(
+        amm.quote_asset_reserve,
+        amm.base_asset_reserve,
+        amm.peg_multiplier,
+    )?;
+    let base_asset_value = calculate_quote_asset_amount_for_price(
+        market_position.base_asset_amount.unsigned_abs(),
+        mark_price,
+    )?;
+
+    let pnl = calculate_pnl(
+        base_asset_value,
+        market_position.quote_asset_amount,
+        market_position.base_asset_amount > 0,
+    )?;
+
+    Ok((base_asset_value, pnl))
+}
+
+// 多头的盈亏为当前价值减去开仓成本，空头相反
+pub fn calculate_pnl(
+    base_asset_value: u128,
+    quote_asset_amount: u128,
+    is_long: bool,
+) -> Result<i128> {
+    let base_asset_value = i128::try_from(base_asset_value).map_err(|_| Errors::MathError)?;
+    let quote_asset_amount = i128::try_from(quote_asset_amount).map_err(|_| Errors::MathError)?;
+
+    let pnl = if is_long {
+        base_asset_value.checked_sub(quote_asset_amount)
+    } else {
+        quote_asset_amount.checked_sub(base_asset_value)
+    };
+
+    Ok(pnl.ok_or(Errors::MathError)?)
+}
+
+// 将已实现盈亏计入collateral，亏损超过collateral时collateral归零
+pub fn calculate_updated_collateral(collateral: u128, pnl: i128) -> Result<u128> {
+    if pnl >= 0 {
+        Ok(collateral
+            .checked_add(pnl.unsigned_abs())
+            .ok_or(Errors::MathError)?)
+    } else {
+        Ok(collateral.saturating_sub(pnl.unsigned_abs()))
+    }
+}
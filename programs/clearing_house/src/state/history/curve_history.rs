@@ -1,16 +1,21 @@
+use anchor_lang::prelude::borsh;
 use anchor_lang::prelude::*;
 use static_assertions::const_assert_eq;
 
+use crate::state::history::ring_buffer::impl_ring_buffer;
+
 #[account(zero_copy)]
 pub struct CurveHistory {
     head: u64,
-    padding: [u8; 8],
+    // 最近一条记录的record_id（ring buffer被禁用时也会更新）
+    last_record_id: u64,
     curve_records: [CurveRecord; 1024],
 }
 
 const_assert_eq!(std::mem::size_of::<CurveHistory>(), 311312);
 
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct CurveRecord {
     pub ts: i64,                             // 时间戳
     pub market_index: u64,                   // 市场索引标识
@@ -34,23 +39,10 @@ pub struct CurveRecord {
     pub trade_record: u128,                  // 关联的交易记录ID
 }
 
-impl CurveHistory {
-    // 增添curve_record
-    pub fn append(&mut self, curve_record: CurveRecord) {
-        self.curve_records[Self::index(self.head)] = curve_record;
-        self.head = (self.head + 1) % 1024;
-    }
-
-    // 将u64安全转为usize
-    pub fn index(counter: u64) -> usize {
-        std::convert::TryInto::try_into(counter).unwrap()
-    }
+impl_ring_buffer!(CurveHistory, CurveRecord, curve_records, CurveRecordEvent);
 
-    // 下一个record的record_id
-    // 注： self.head会在0~1023之间来回递增，而每个record.record_id一直单向递增
-    pub fn next_record_id(&self) -> u128 {
-        let pre_record_id = if self.head == 0 { 1023 } else { self.head - 1 };
-        let pre_record = &self.curve_records[Self::index(pre_record_id)];
-        pre_record.record_id + 1
-    }
+// 每次写入CurveRecord时发出的event，链下索引服务可以订阅日志获取曲线调整记录
+#[event]
+pub struct CurveRecordEvent {
+    pub record: CurveRecord,
 }
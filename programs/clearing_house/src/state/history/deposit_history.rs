@@ -1,11 +1,15 @@
+use anchor_lang::prelude::borsh;
 use anchor_lang::prelude::*;
 use bytemuck::{Pod, Zeroable};
 use static_assertions::const_assert_eq;
 
+use crate::state::history::ring_buffer::impl_ring_buffer;
+
 #[account(zero_copy)]
 pub struct DepositHistory {
     head: u64,
-    padding: [u8; 8],
+    // 最近一条记录的record_id（ring buffer被禁用时也会更新）
+    last_record_id: u64,
     deposit_records: [DepositRecord; 1024],
 }
 
@@ -22,6 +26,7 @@ unsafe impl Zeroable for DepositDirection {}
 unsafe impl Pod for DepositDirection {}
 
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct DepositRecord {
     pub ts: i64,                          // 时间戳
     pub amount: u64,                      // 本次操作的金额
@@ -34,23 +39,15 @@ pub struct DepositRecord {
     pub padding: [u8; 15],
 }
 
-impl DepositHistory {
-    // 增添deposit_record
-    pub fn append(&mut self, deposit_record: DepositRecord) {
-        self.deposit_records[Self::index(self.head)] = deposit_record;
-        self.head = (self.head + 1) % 1024;
-    }
-
-    // 将u64安全转为usize
-    pub fn index(counter: u64) -> usize {
-        std::convert::TryInto::try_into(counter).unwrap()
-    }
-
-    // 下一个record的record_id
-    // 注： self.head会在0~1023之间来回递增，而每个record.record_id一直单向递增
-    pub fn next_record_id(&self) -> u128 {
-        let pre_record_id = if self.head == 0 { 1023 } else { self.head - 1 };
-        let pre_record = &self.deposit_records[Self::index(pre_record_id)];
-        pre_record.record_id + 1
-    }
+impl_ring_buffer!(
+    DepositHistory,
+    DepositRecord,
+    deposit_records,
+    DepositRecordEvent
+);
+
+// 每次写入DepositRecord时发出的event，链下索引服务可以订阅日志获取存取款记录
+#[event]
+pub struct DepositRecordEvent {
+    pub record: DepositRecord,
 }
@@ -1,11 +1,15 @@
+use anchor_lang::prelude::borsh;
 use anchor_lang::prelude::*;
 use static_assertions::const_assert_eq;
 
+use crate::state::history::ring_buffer::impl_ring_buffer;
+
 #[account(zero_copy)]
 pub struct FundingPaymentHistory {
     // 作为循环缓冲区的指针，指示下一个记录应该写入的位置
     head: u64,
-    padding: [u8; 8],
+    // 最近一条记录的record_id（ring buffer被禁用时也会更新）
+    last_record_id: u64,
     // 存储资金费率支付记录的实际数据
     funding_payment_records: [FundingPaymentRecord; 1024],
 }
@@ -13,6 +17,7 @@ pub struct FundingPaymentHistory {
 const_assert_eq!(std::mem::size_of::<FundingPaymentHistory>(), 196624);
 
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct FundingPaymentRecord {
     pub ts: i64,                            // 时间戳（记录创建时间）
     pub market_index: u64,                  // 市场索引
@@ -28,23 +33,15 @@ pub struct FundingPaymentRecord {
     pub padding: [u8; 8],
 }
 
-impl FundingPaymentHistory {
-    // 增添funding_payment_record
-    pub fn append(&mut self, funding_payment_record: FundingPaymentRecord) {
-        self.funding_payment_records[Self::index(self.head)] = funding_payment_record;
-        self.head = (self.head + 1) % 1024;
-    }
-
-    // 将u64安全转为usize
-    pub fn index(counter: u64) -> usize {
-        std::convert::TryInto::try_into(counter).unwrap()
-    }
+impl_ring_buffer!(
+    FundingPaymentHistory,
+    FundingPaymentRecord,
+    funding_payment_records,
+    FundingPaymentRecordEvent
+);
 
-    // 下一个record的record_id
-    // 注： self.head会在0~1023之间来回递增，而每个record.record_id一直单向递增
-    pub fn next_record_id(&self) -> u128 {
-        let pre_record_id = if self.head == 0 { 1023 } else { self.head - 1 };
-        let pre_record = &self.funding_payment_records[Self::index(pre_record_id)];
-        pre_record.record_id + 1
-    }
+// 每次写入FundingPaymentRecord时发出的event，链下索引服务可以订阅日志获取资金费支付记录
+#[event]
+pub struct FundingPaymentRecordEvent {
+    pub record: FundingPaymentRecord,
 }
@@ -1,16 +1,21 @@
+use anchor_lang::prelude::borsh;
 use anchor_lang::prelude::*;
 use static_assertions::const_assert_eq;
 
+use crate::state::history::ring_buffer::impl_ring_buffer;
+
 #[account(zero_copy)]
 pub struct FundingRateHistory {
     head: u64,
-    padding: [u8; 8],
+    // 最近一条记录的record_id（ring buffer被禁用时也会更新）
+    last_record_id: u64,
     funding_rate_record: [FundingRateRecord; 1024],
 }
 
 const_assert_eq!(std::mem::size_of::<FundingRateHistory>(), 114704);
 
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct FundingRateRecord {
     pub ts: i64,                             // 时间戳
     pub market_index: u64,                   // 市场索引，
@@ -22,23 +27,15 @@ pub struct FundingRateRecord {
     pub mark_price_twap: u128,               // 标记价格的TWAP（时间加权平均价）
 }
 
-impl FundingRateHistory {
-    // 增添funding_rate_record
-    pub fn append(&mut self, funding_rate_record: FundingRateRecord) {
-        self.funding_rate_record[Self::index(self.head)] = funding_rate_record;
-        self.head = (self.head + 1) % 1024;
-    }
-
-    // 将u64安全转为usize
-    pub fn index(counter: u64) -> usize {
-        std::convert::TryInto::try_into(counter).unwrap()
-    }
+impl_ring_buffer!(
+    FundingRateHistory,
+    FundingRateRecord,
+    funding_rate_record,
+    FundingRateRecordEvent
+);
 
-    // 下一个record的record_id
-    // 注： self.head会在0~1023之间来回递增，而每个record.record_id一直单向递增
-    pub fn next_record_id(&self) -> u128 {
-        let pre_record_id = if self.head == 0 { 1023 } else { self.head - 1 };
-        let pre_record = &self.funding_rate_record[Self::index(pre_record_id)];
-        pre_record.record_id + 1
-    }
+// 每次写入FundingRateRecord时发出的event，链下索引服务可以订阅日志获取资金费率记录
+#[event]
+pub struct FundingRateRecordEvent {
+    pub record: FundingRateRecord,
 }
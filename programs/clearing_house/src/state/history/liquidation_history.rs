@@ -1,17 +1,22 @@
+use anchor_lang::prelude::borsh;
 use anchor_lang::prelude::*;
 use static_assertions::const_assert_eq;
 
+use crate::state::history::ring_buffer::impl_ring_buffer;
+
 #[account(zero_copy)]
 pub struct LiquidationHistory {
     // 作为循环缓冲区的指针，指示下一个记录应该写入的位置
     head: u64,
-    padding: [u8; 8],
+    // 最近一条记录的record_id（ring buffer被禁用时也会更新）
+    last_record_id: u64,
     liquidation_records: [LiquidationRecord; 1024],
 }
 
 const_assert_eq!(std::mem::size_of::<LiquidationHistory>(), 262160);
 
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct LiquidationRecord {
     pub record_id: u128,               // 记录的唯一标识符
     pub user_authority: Pubkey,        // 用户授权公钥（执行交易的地址）
@@ -31,23 +36,15 @@ pub struct LiquidationRecord {
     pub padding: [u8; 7],
 }
 
-impl LiquidationHistory {
-    // 增添liquidation_record
-    pub fn append(&mut self, liquidation_record: LiquidationRecord) {
-        self.liquidation_records[Self::index(self.head)] = liquidation_record;
-        self.head = (self.head + 1) % 1024;
-    }
-
-    // 将u64安全转为usize
-    pub fn index(counter: u64) -> usize {
-        std::convert::TryInto::try_into(counter).unwrap()
-    }
+impl_ring_buffer!(
+    LiquidationHistory,
+    LiquidationRecord,
+    liquidation_records,
+    LiquidationRecordEvent
+);
 
-    // 下一个record的record_id
-    // 注： self.head会在0~1023之间来回递增，而每个record.record_id一直单向递增
-    pub fn next_record_id(&self) -> u128 {
-        let pre_record_id = if self.head == 0 { 1023 } else { self.head - 1 };
-        let pre_record = &self.liquidation_records[Self::index(pre_record_id)];
-        pre_record.record_id + 1
-    }
+// 每次写入LiquidationRecord时发出的event，链下索引服务可以订阅日志获取清算记录
+#[event]
+pub struct LiquidationRecordEvent {
+    pub record: LiquidationRecord,
 }
@@ -4,4 +4,5 @@ pub mod funding_payment_history;
 pub mod funding_rate_history;
 pub mod liquidation_history;
 pub mod order_history;
+pub mod ring_buffer;
 pub mod trade_history;
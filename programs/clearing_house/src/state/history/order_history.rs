@@ -1,13 +1,15 @@
+use anchor_lang::prelude::borsh;
 use anchor_lang::prelude::*;
 use bytemuck::{Pod, Zeroable};
 use static_assertions::const_assert_eq;
 
+use crate::state::history::ring_buffer::impl_ring_buffer;
 use crate::state::user_orders::Order;
 
 #[account(zero_copy)]
 pub struct OrderHistory {
-    head: u64, // 作为循环缓冲区的指针，指示下一个记录应该写入的位置
-    _padding: [u8; 8],
+    head: u64,               // 作为循环缓冲区的指针，指示下一个记录应该写入的位置
+    last_record_id: u64,     // 最近一条记录的record_id（ring buffer被禁用时也会更新）
     pub last_order_id: u128, // 最近一个订单的全局唯一ID
     order_records: [OrderRecord; 1024],
 }
@@ -15,6 +17,7 @@ pub struct OrderHistory {
 const_assert_eq!(std::mem::size_of::<OrderHistory>(), 458784);
 
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct OrderRecord {
     pub ts: i64,             // 交易时间戳
     pub action: OrderAction, // 订单操作类型（枚举）
@@ -32,26 +35,15 @@ pub struct OrderRecord {
     pub quote_asset_amount_surplus: u128, // quote资产的剩余金额（可能用于部分成交或滑点计算）
 }
 
-impl OrderHistory {
-    // 增添order_record
-    pub fn append(&mut self, order_record: OrderRecord) {
-        self.order_records[Self::index(self.head)] = order_record;
-        self.head = (self.head + 1) % 1024;
-    }
-
-    // 将u64安全转为usize
-    pub fn index(counter: u64) -> usize {
-        std::convert::TryInto::try_into(counter).unwrap()
-    }
+impl_ring_buffer!(OrderHistory, OrderRecord, order_records, OrderRecordEvent);
 
-    // 下一个record的record_id
-    // 注： self.head会在0~1023之间来回递增，而每个record.record_id一直单向递增
-    pub fn next_record_id(&self) -> u128 {
-        let pre_record_id = if self.head == 0 { 1023 } else { self.head - 1 };
-        let pre_record = &self.order_records[Self::index(pre_record_id)];
-        pre_record.record_id + 1
-    }
+// 每次写入OrderRecord时发出的event，链下索引服务可以订阅日志获取订单记录
+#[event]
+pub struct OrderRecordEvent {
+    pub record: OrderRecord,
+}
 
+impl OrderHistory {
     pub fn next_order_id(&mut self) -> u128 {
         let next_order_id = self.last_order_id + 1;
         self.last_order_id = next_order_id;
@@ -0,0 +1,315 @@
+use std::iter::Chain;
+use std::slice::Iter;
+
+// 历史记录：record_id从1开始单调递增，record_id为0的记录表示该槽位从未被写入
+pub trait HistoryRecord: Copy {
+    fn record_id(&self) -> u128;
+
+    // 发出与该记录对应的event，链下索引服务可以通过订阅日志获取记录
+    fn emit(&self);
+}
+
+// 各history账户共用的循环缓冲区实现
+// 实现者只需提供head指针、最近一条记录的record_id与记录数组，head指向下一个记录应该写入的位置
+pub trait RingBuffer {
+    type Record: HistoryRecord;
+
+    fn head(&self) -> u64;
+
+    fn set_head(&mut self, head: u64);
+
+    fn last_record_id(&self) -> u64;
+
+    fn set_last_record_id(&mut self, last_record_id: u64);
+
+    fn records(&self) -> &[Self::Record];
+
+    fn records_mut(&mut self) -> &mut [Self::Record];
+
+    // 将u64安全转为usize
+    fn index(counter: u64) -> usize {
+        std::convert::TryInto::try_into(counter).unwrap()
+    }
+
+    // 增添record：总是发出对应的event；ring buffer启用时才把记录写入缓冲区，缓冲区写满后覆盖最旧的记录
+    fn append(&mut self, record: Self::Record, ring_buffer_enabled: bool) {
+        record.emit();
+        self.set_last_record_id(record.record_id() as u64);
+        if !ring_buffer_enabled {
+            return;
+        }
+
+        let head = Self::index(self.head());
+        let records = self.records_mut();
+        let capacity = records.len();
+        records[head] = record;
+        self.set_head(((head + 1) % capacity) as u64);
+    }
+
+    // 最近写入的记录，缓冲区为空时返回None
+    fn latest(&self) -> Option<&Self::Record> {
+        let records = self.records();
+        let head = Self::index(self.head());
+        let latest_index = if head == 0 {
+            records.len() - 1
+        } else {
+            head - 1
+        };
+        let latest = &records[latest_index];
+        if latest.record_id() == 0 {
+            None
+        } else {
+            Some(latest)
+        }
+    }
+
+    // 下一个record的record_id
+    // 注：head会在0~N-1之间来回递增，而每个record的record_id一直单向递增
+    // ring buffer被禁用时记录不会写入缓冲区，所以同时参考last_record_id
+    fn next_record_id(&self) -> u128 {
+        let latest_record_id = self.latest().map_or(0, |latest| latest.record_id());
+        latest_record_id.max(self.last_record_id() as u128) + 1
+    }
+
+    // 按写入的先后顺序遍历所有已写入的记录
+    // head所在的槽位未被写入时说明缓冲区还没写满过，最旧的记录在0号槽位；否则最旧的记录就在head处
+    fn iter(&self) -> Chain<Iter<'_, Self::Record>, Iter<'_, Self::Record>> {
+        let records = self.records();
+        let head = Self::index(self.head());
+        if records[head].record_id() == 0 {
+            records[..head].iter().chain(records[..0].iter())
+        } else {
+            records[head..].iter().chain(records[..head].iter())
+        }
+    }
+
+    // 根据record_id查找记录，已被覆盖、还未写入或者写入时ring buffer被禁用的记录返回None
+    fn get_by_record_id(&self, record_id: u128) -> Option<&Self::Record> {
+        let latest_record_id = self.latest()?.record_id();
+        if record_id == 0 || record_id > latest_record_id {
+            return None;
+        }
+
+        // ring buffer没有被禁用过时，缓冲区中的record_id连续递增，可以直接根据与最新记录的距离计算出槽位
+        let records = self.records();
+        let distance = latest_record_id - record_id;
+        if distance < records.len() as u128 {
+            let latest_index = Self::index(self.head()) + records.len() - 1;
+            let record = &records[(latest_index - distance as usize) % records.len()];
+            if record.record_id() == record_id {
+                return Some(record);
+            }
+        }
+
+        // 禁用期间的record_id没有写入缓冲区，record_id不连续时逐个查找
+        records
+            .iter()
+            .find(|record| record.record_id() == record_id)
+    }
+}
+
+// 为history账户实现RingBuffer，并为其记录实现HistoryRecord
+// 参数依次为：history账户类型、记录类型、history中的记录数组字段、写入记录时发出的event类型
+// history账户需要有head与last_record_id字段，记录需要有record_id字段
+macro_rules! impl_ring_buffer {
+    ($history:ty, $record:ty, $records:ident, $event:ident) => {
+        impl $crate::state::history::ring_buffer::RingBuffer for $history {
+            type Record = $record;
+
+            fn head(&self) -> u64 {
+                self.head
+            }
+
+            fn set_head(&mut self, head: u64) {
+                self.head = head;
+            }
+
+            fn last_record_id(&self) -> u64 {
+                self.last_record_id
+            }
+
+            fn set_last_record_id(&mut self, last_record_id: u64) {
+                self.last_record_id = last_record_id;
+            }
+
+            fn records(&self) -> &[$record] {
+                &self.$records
+            }
+
+            fn records_mut(&mut self) -> &mut [$record] {
+                &mut self.$records
+            }
+        }
+
+        impl $crate::state::history::ring_buffer::HistoryRecord for $record {
+            fn record_id(&self) -> u128 {
+                self.record_id
+            }
+
+            fn emit(&self) {
+                emit!($event { record: *self });
+            }
+        }
+    };
+}
+
+pub(crate) use impl_ring_buffer;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Copy, Clone, Default)]
+    struct TestRecord {
+        record_id: u128,
+    }
+
+    impl HistoryRecord for TestRecord {
+        fn record_id(&self) -> u128 {
+            self.record_id
+        }
+
+        fn emit(&self) {}
+    }
+
+    struct TestHistory {
+        head: u64,
+        last_record_id: u64,
+        records: [TestRecord; 4],
+    }
+
+    impl RingBuffer for TestHistory {
+        type Record = TestRecord;
+
+        fn head(&self) -> u64 {
+            self.head
+        }
+
+        fn set_head(&mut self, head: u64) {
+            self.head = head;
+        }
+
+        fn last_record_id(&self) -> u64 {
+            self.last_record_id
+        }
+
+        fn set_last_record_id(&mut self, last_record_id: u64) {
+            self.last_record_id = last_record_id;
+        }
+
+        fn records(&self) -> &[TestRecord] {
+            &self.records
+        }
+
+        fn records_mut(&mut self) -> &mut [TestRecord] {
+            &mut self.records
+        }
+    }
+
+    fn history_with_records(count: u128) -> TestHistory {
+        let mut history = TestHistory {
+            head: 0,
+            last_record_id: 0,
+            records: [TestRecord::default(); 4],
+        };
+        for _ in 0..count {
+            let record_id = history.next_record_id();
+            history.append(TestRecord { record_id }, true);
+        }
+        history
+    }
+
+    fn record_ids(history: &TestHistory) -> Vec<u128> {
+        history.iter().map(|record| record.record_id).collect()
+    }
+
+    #[test]
+    fn empty_buffer() {
+        let history = history_with_records(0);
+        assert!(history.latest().is_none());
+        assert_eq!(history.next_record_id(), 1);
+        assert!(record_ids(&history).is_empty());
+        assert!(history.get_by_record_id(1).is_none());
+    }
+
+    #[test]
+    fn first_append() {
+        let history = history_with_records(1);
+        assert_eq!(history.head, 1);
+        assert_eq!(history.latest().unwrap().record_id, 1);
+        assert_eq!(history.next_record_id(), 2);
+        assert_eq!(record_ids(&history), vec![1]);
+    }
+
+    #[test]
+    fn full_buffer() {
+        let history = history_with_records(4);
+        assert_eq!(history.head, 0);
+        assert_eq!(history.latest().unwrap().record_id, 4);
+        assert_eq!(history.next_record_id(), 5);
+        assert_eq!(record_ids(&history), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn wrapped_buffer() {
+        let history = history_with_records(6);
+        assert_eq!(history.head, 2);
+        assert_eq!(history.latest().unwrap().record_id, 6);
+        assert_eq!(history.next_record_id(), 7);
+        assert_eq!(record_ids(&history), vec![3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn get_by_record_id() {
+        let history = history_with_records(6);
+        assert!(history.get_by_record_id(0).is_none());
+        // 已被覆盖的记录
+        assert!(history.get_by_record_id(2).is_none());
+        for record_id in 3..=6 {
+            assert_eq!(
+                history.get_by_record_id(record_id).unwrap().record_id,
+                record_id
+            );
+        }
+        assert!(history.get_by_record_id(7).is_none());
+    }
+
+    #[test]
+    fn get_by_record_id_after_ring_buffer_reenabled() {
+        // 1、2写入缓冲区，3在禁用期间只发出event，重新启用后写入4、5
+        let mut history = history_with_records(2);
+        let record_id = history.next_record_id();
+        history.append(TestRecord { record_id }, false);
+        for _ in 0..2 {
+            let record_id = history.next_record_id();
+            history.append(TestRecord { record_id }, true);
+        }
+        assert_eq!(record_ids(&history), vec![1, 2, 4, 5]);
+
+        assert!(history.get_by_record_id(3).is_none());
+        for record_id in [1, 2, 4, 5] {
+            assert_eq!(
+                history.get_by_record_id(record_id).unwrap().record_id,
+                record_id
+            );
+        }
+
+        // 继续写入6后，最旧的记录1被覆盖
+        let record_id = history.next_record_id();
+        history.append(TestRecord { record_id }, true);
+        assert!(history.get_by_record_id(1).is_none());
+        assert_eq!(history.get_by_record_id(2).unwrap().record_id, 2);
+        assert_eq!(history.get_by_record_id(6).unwrap().record_id, 6);
+    }
+
+    #[test]
+    fn ring_buffer_disabled() {
+        let mut history = history_with_records(2);
+        let record_id = history.next_record_id();
+        history.append(TestRecord { record_id }, false);
+        assert_eq!(history.head, 2);
+        assert_eq!(history.latest().unwrap().record_id, 2);
+        assert_eq!(history.next_record_id(), 4);
+        assert_eq!(record_ids(&history), vec![1, 2]);
+    }
+}
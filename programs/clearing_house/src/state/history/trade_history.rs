@@ -1,19 +1,23 @@
+use anchor_lang::prelude::borsh;
 use anchor_lang::prelude::*;
 use static_assertions::const_assert_eq;
 
+use crate::state::history::ring_buffer::impl_ring_buffer;
 use crate::PositionDirection;
 
 #[account(zero_copy)]
 pub struct TradeHistory {
     // 作为循环缓冲区的指针，指示下一个交易记录应该写入的位置
     head: u64,
-    padding: [u8; 8],
+    // 最近一条记录的record_id（ring buffer被禁用时也会更新）
+    last_record_id: u64,
     trade_record: [TradeRecord; 1024],
 }
 
 const_assert_eq!(std::mem::size_of::<TradeHistory>(), 262160);
 
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct TradeRecord {
     pub ts: i64,                          // 交易时间戳
     pub market_index: u64,                // 市场索引
@@ -31,26 +35,14 @@ pub struct TradeRecord {
     pub oracle_price: i128,               // 交易时的预言机价格(用于比较标记价格)
     pub liquidation: u8,                  // 是否是清算交易(1表示这是强制平仓)
     pub direction: PositionDirection,     // 交易方向
-    pub padding: [u8; 14],
+    pub settlement: u8, // market结算产生的记录(1表示冻结或按settlement_price结算，2表示进入ReduceOnly，3表示重置market)
+    pub padding: [u8; 13],
 }
 
-impl TradeHistory {
-    // 增添trade_record
-    pub fn append(&mut self, trade_record: TradeRecord) {
-        self.trade_record[Self::index(self.head)] = trade_record;
-        self.head = (self.head + 1) % 1024;
-    }
+impl_ring_buffer!(TradeHistory, TradeRecord, trade_record, TradeRecordEvent);
 
-    // 将u64安全转为usize
-    pub fn index(counter: u64) -> usize {
-        std::convert::TryInto::try_into(counter).unwrap()
-    }
-
-    // 下一个record的record_id
-    // 注： self.head会在0~1023之间来回递增，而每个record.record_id一直单向递增
-    pub fn next_record_id(&self) -> u128 {
-        let pre_record_id = if self.head == 0 { 1023 } else { self.head - 1 };
-        let pre_record = &self.trade_record[Self::index(pre_record_id)];
-        pre_record.record_id + 1
-    }
+// 每次写入TradeRecord时发出的event，链下索引服务可以订阅日志获取交易记录
+#[event]
+pub struct TradeRecordEvent {
+    pub record: TradeRecord,
 }
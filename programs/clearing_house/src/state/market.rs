@@ -3,13 +3,80 @@ use bytemuck::{Pod, Zeroable};
 use static_assertions::const_assert_eq;
 use std::mem::size_of;
 
+use crate::errors::Errors;
+use crate::state::oracle::{get_oracle_price, OraclePriceData};
+
 #[account(zero_copy)]
 // markets账户里面存有最多64个Market的信息
 pub struct Markets {
     pub markets: [Market; 64],
+    // version放在末尾，版本0的markets账户（没有这部分）是当前布局的前缀，迁移时只需扩容
+    pub version: u8, // Markets账户的版本，见MARKETS_VERSION
+    pub padding: [u8; 15],
+}
+
+const_assert_eq!(size_of::<Markets>(), 31760);
+
+// 当前program使用的Markets版本。修改Markets布局时递增该值，并在migrate_markets中补充迁移逻辑
+// 版本0：引入version字段之前的Markets（31744字节）
+pub const MARKETS_VERSION: u8 = 1;
+
+impl Markets {
+    // markets账户是否已经迁移到当前program使用的版本
+    // 旧版本的账户可能小于当前的Markets，不能直接load，所以先检查账户大小
+    pub fn is_current_version(markets: &AccountInfo) -> Result<bool> {
+        let data = markets.try_borrow_data()?;
+        Ok(data.len() == 8 + size_of::<Markets>()
+            && data[8 + std::mem::offset_of!(Markets, version)] == MARKETS_VERSION)
+    }
+
+    // 将u64的market_index安全转为usize，超出64个市场的范围时报错
+    pub fn index_from_u64(index: u64) -> Result<usize> {
+        usize::try_from(index)
+            .ok()
+            .filter(|index| *index < 64)
+            .ok_or_else(|| error!(Errors::MarketIndexOutOfRange))
+    }
+
+    // 已迁移到MarketAccount的slot不能再使用，必须传入对应的MarketAccount
+    pub fn get_market(&self, index: u64) -> Result<&Market> {
+        let market = &self.markets[Self::index_from_u64(index)?];
+        if market.migrated != 0 {
+            return err!(Errors::MarketMigrated);
+        }
+        Ok(market)
+    }
+
+    pub fn get_market_mut(&mut self, index: u64) -> Result<&mut Market> {
+        let market = &mut self.markets[Self::index_from_u64(index)?];
+        if market.migrated != 0 {
+            return err!(Errors::MarketMigrated);
+        }
+        Ok(market)
+    }
+}
+
+// 单个market的独立账户（pda，seeds为[b"market", market_index]）
+// 交易只需要锁住涉及的MarketAccount而不是整个Markets；market_index也不再受Markets的64个slot限制
+#[account(zero_copy)]
+pub struct MarketAccount {
+    pub market: Market,
+    pub market_index: u64,
+    pub bump: u8,
+    pub version: u8, // MarketAccount账户的版本，见MARKET_ACCOUNT_VERSION
+    pub padding: [u8; 6],
 }
 
-const_assert_eq!(size_of::<Markets>(), 31744);
+const_assert_eq!(size_of::<MarketAccount>(), 512);
+
+// 当前program使用的MarketAccount版本
+pub const MARKET_ACCOUNT_VERSION: u8 = 1;
+
+impl MarketAccount {
+    pub fn is_current_version(&self) -> bool {
+        self.version == MARKET_ACCOUNT_VERSION
+    }
+}
 
 #[zero_copy]
 pub struct Market {
@@ -23,14 +90,43 @@ pub struct Market {
     pub margin_ratio_maintenance: u32, // 维持保证金比例（当保证金低于此比例时可能触发强制清算）
     // 该Market是否完成初始化标志
     pub initialized: u8,
+    pub status: MarketStatus, // 市场状态（正常/只减仓/暂停）
+    pub migrated: u8, // 该slot是否已迁移到独立的MarketAccount（迁移后Markets中的这份数据不再使用）
     // upgrade-ability
-    pub padding0: [u8; 3],
-    pub padding1: u128,
-    pub padding2: u128,
+    pub padding0: [u8; 1],
+    pub settlement_price: u128, // market进入Settled状态时冻结的结算价格（settle_market时的oracle TWAP）
+    pub min_order_id: u128, // slot重置后重新初始化的market只成交order_id不小于该值的订单（更早的订单属于重置前的market）
     pub padding3: u128,
     pub padding4: u128,
 }
 
+impl Market {
+    // 校验market的状态是否允许本次操作：
+    // Paused时禁止一切操作；ReduceOnly时只允许减仓操作（如平仓、清算）；
+    // Settled时头寸只能通过settle_position按结算价格平仓
+    pub fn validate_status(&self, reduce_only: bool) -> Result<()> {
+        match self.status {
+            MarketStatus::Active => Ok(()),
+            MarketStatus::ReduceOnly if reduce_only => Ok(()),
+            MarketStatus::ReduceOnly => err!(Errors::MarketReduceOnly),
+            MarketStatus::Paused => err!(Errors::MarketPaused),
+            MarketStatus::Settled => err!(Errors::MarketSettled),
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MarketStatus {
+    Active,     // 正常交易
+    ReduceOnly, // 只允许减仓（包括清算）
+    Paused,     // 暂停一切操作
+    Settled,    // 已按settlement_price冻结，只允许settle_position
+}
+
+unsafe impl Zeroable for MarketStatus {}
+unsafe impl Pod for MarketStatus {}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
 #[repr(u8)]
 pub enum OracleSource {
@@ -76,6 +172,17 @@ pub struct AMM {
     pub padding: [u8; 13],
 }
 
+impl AMM {
+    // 按本market配置的oracle_source读取oracle价格
+    pub fn get_oracle_price(
+        &self,
+        price_oracle: &AccountInfo,
+        clock_slot: u64,
+    ) -> Result<OraclePriceData> {
+        get_oracle_price(&self.oracle_source, price_oracle, clock_slot)
+    }
+}
+
 // #[test]
 // fn test_a() {
 //     use std::mem;
@@ -0,0 +1,137 @@
+use std::cell::{Ref, RefMut};
+use std::collections::BTreeMap;
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+use crate::errors::Errors;
+use crate::state::market::{Market, MarketAccount, Markets};
+
+// 以只读或可写方式借用的账户数据，取决于账户在交易中是否可写
+enum Loaded<'a, T> {
+    Readonly(Ref<'a, T>),
+    Writable(RefMut<'a, T>),
+}
+
+impl<T> Loaded<'_, T> {
+    fn get(&self) -> &T {
+        match self {
+            Loaded::Readonly(data) => data,
+            Loaded::Writable(data) => data,
+        }
+    }
+
+    fn get_mut(&mut self) -> Result<&mut T> {
+        match self {
+            Loaded::Readonly(_) => err!(ErrorCode::AccountNotMutable),
+            Loaded::Writable(data) => Ok(data),
+        }
+    }
+}
+
+// 同时支持两种market存储方式：Markets中的slot，以及独立的MarketAccount
+// 指令通过remaining_accounts传入涉及的MarketAccount，没有传入的market从Markets中读取
+// 只交易已迁移market的用户可以不传Markets，这样不同market上的交易不会因为Markets互相锁住
+pub struct MarketMap<'a> {
+    markets: Option<Loaded<'a, Markets>>,
+    market_accounts: BTreeMap<u64, Loaded<'a, MarketAccount>>,
+}
+
+impl<'a> MarketMap<'a> {
+    pub fn load(
+        markets: Option<&'a AccountLoader<'_, Markets>>,
+        market_accounts: &'a [AccountInfo<'_>],
+    ) -> Result<MarketMap<'a>> {
+        let markets = match markets {
+            Some(markets) if markets.as_ref().is_writable => {
+                Some(Loaded::Writable(markets.load_mut()?))
+            }
+            Some(markets) => Some(Loaded::Readonly(markets.load()?)),
+            None => None,
+        };
+
+        let mut map = BTreeMap::new();
+        for account_info in market_accounts {
+            let market_account = load_market_account(account_info)?;
+            let market_index = market_account.get().market_index;
+            if map.insert(market_index, market_account).is_some() {
+                return err!(Errors::InvalidMarketAccount);
+            }
+        }
+
+        Ok(MarketMap {
+            markets,
+            market_accounts: map,
+        })
+    }
+
+    // market是否存放在独立的MarketAccount中
+    pub fn is_market_account(&self, index: u64) -> bool {
+        self.market_accounts.contains_key(&index)
+    }
+
+    pub fn get_market(&self, index: u64) -> Result<&Market> {
+        if let Some(market_account) = self.market_accounts.get(&index) {
+            return Ok(&market_account.get().market);
+        }
+        match &self.markets {
+            Some(markets) => markets.get().get_market(index),
+            None => err!(Errors::InvalidMarketAccount),
+        }
+    }
+
+    pub fn get_market_mut(&mut self, index: u64) -> Result<&mut Market> {
+        if let Some(market_account) = self.market_accounts.get_mut(&index) {
+            return Ok(&mut market_account.get_mut()?.market);
+        }
+        match &mut self.markets {
+            Some(markets) => markets.get_mut()?.get_market_mut(index),
+            None => err!(Errors::InvalidMarketAccount),
+        }
+    }
+}
+
+// 检查owner和discriminator，并用记录的market_index与bump重新推导pda，确认账户就是该market的MarketAccount
+fn load_market_account<'a>(account_info: &'a AccountInfo) -> Result<Loaded<'a, MarketAccount>> {
+    if account_info.owner != &crate::ID {
+        return err!(ErrorCode::AccountOwnedByWrongProgram);
+    }
+    {
+        let data = account_info.try_borrow_data()?;
+        if data.len() != 8 + size_of::<MarketAccount>() || data[..8] != MarketAccount::DISCRIMINATOR
+        {
+            return err!(Errors::InvalidMarketAccount);
+        }
+    }
+
+    let market_account: Loaded<MarketAccount> = if account_info.is_writable {
+        Loaded::Writable(RefMut::map(account_info.try_borrow_mut_data()?, |data| {
+            bytemuck::from_bytes_mut(&mut data[8..])
+        }))
+    } else {
+        Loaded::Readonly(Ref::map(account_info.try_borrow_data()?, |data| {
+            bytemuck::from_bytes(&data[8..])
+        }))
+    };
+    if !market_account.get().is_current_version() {
+        return err!(Errors::AccountVersionMismatch);
+    }
+
+    let market_index = market_account.get().market_index;
+    let bump = market_account.get().bump;
+    let expected_key = Pubkey::create_program_address(
+        &[
+            b"market".as_ref(),
+            market_index.to_le_bytes().as_ref(),
+            &[bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(|_| Errors::InvalidMarketAccount)?;
+    if account_info.key != &expected_key {
+        return err!(Errors::InvalidMarketAccount);
+    }
+
+    Ok(market_account)
+}
@@ -1,5 +1,9 @@
 pub mod history;
 pub mod market;
+pub mod market_map;
+pub mod oracle;
 pub mod order_state;
+#[allow(clippy::module_inception)]
 pub mod state;
+pub mod user;
 pub mod user_orders;
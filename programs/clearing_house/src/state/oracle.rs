@@ -0,0 +1,149 @@
+use anchor_lang::prelude::*;
+use bytemuck::{Pod, Zeroable};
+use static_assertions::const_assert_eq;
+
+use crate::errors::Errors;
+use crate::math::constant::MARK_PRICE_PRECISION;
+use crate::state::market::OracleSource;
+
+// pyth v2 price账户的magic number、版本号以及账户类型（3表示Price账户）
+const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+const PYTH_VERSION_2: u32 = 2;
+const PYTH_ACCOUNT_TYPE_PRICE: u32 = 3;
+// pyth聚合价格的状态，1表示正常交易中
+const PYTH_STATUS_TRADING: u32 = 1;
+
+// 从oracle读取并统一到MARK_PRICE_PRECISION精度后的价格数据
+#[derive(Clone, Copy)]
+pub struct OraclePriceData {
+    pub price: i128,      // oracle价格
+    pub confidence: u128, // 价格置信区间
+    pub delay: i64,       // 当前slot距离oracle价格有效slot的间隔
+}
+
+// 按oracle_source从oracle账户中读取价格
+pub fn get_oracle_price(
+    oracle_source: &OracleSource,
+    price_oracle: &AccountInfo,
+    clock_slot: u64,
+) -> Result<OraclePriceData> {
+    match oracle_source {
+        OracleSource::Pyth => get_pyth_price(price_oracle, clock_slot),
+        // 暂未支持switchboard账户的解析
+        OracleSource::SwitchBoard => err!(Errors::UnsupportedOracleSource),
+    }
+}
+
+fn get_pyth_price(price_oracle: &AccountInfo, clock_slot: u64) -> Result<OraclePriceData> {
+    let data = price_oracle.try_borrow_data()?;
+    let pyth_price = load_pyth_price(&data)?;
+
+    // 将pyth价格（price * 10^expo）转为MARK_PRICE_PRECISION精度
+    let price = scale_pyth_value(pyth_price.agg.price as i128, pyth_price.expo)?;
+    let confidence = scale_pyth_value(pyth_price.agg.conf as i128, pyth_price.expo)?;
+    let delay = (clock_slot as i64)
+        .checked_sub(pyth_price.valid_slot as i64)
+        .ok_or(Errors::MathError)?;
+
+    Ok(OraclePriceData {
+        price,
+        confidence: confidence.unsigned_abs(),
+        delay,
+    })
+}
+
+// 按oracle_source从oracle账户中读取价格的时间加权平均（MARK_PRICE_PRECISION精度）
+// oracle不处于正常交易状态时TWAP不可信，直接报错
+pub fn get_oracle_twap(oracle_source: &OracleSource, price_oracle: &AccountInfo) -> Result<i128> {
+    match oracle_source {
+        OracleSource::Pyth => {
+            let data = price_oracle.try_borrow_data()?;
+            let pyth_price = load_pyth_price(&data)?;
+            if pyth_price.agg.status != PYTH_STATUS_TRADING {
+                return err!(Errors::InvalidOracle);
+            }
+            scale_pyth_value(pyth_price.twap.val as i128, pyth_price.expo)
+        }
+        OracleSource::SwitchBoard => err!(Errors::UnsupportedOracleSource),
+    }
+}
+
+fn load_pyth_price(data: &[u8]) -> Result<&PythPrice> {
+    if data.len() < std::mem::size_of::<PythPrice>() {
+        return err!(Errors::InvalidOracle);
+    }
+    let pyth_price: &PythPrice = bytemuck::from_bytes(&data[..std::mem::size_of::<PythPrice>()]);
+    if pyth_price.magic != PYTH_MAGIC
+        || pyth_price.ver != PYTH_VERSION_2
+        || pyth_price.atype != PYTH_ACCOUNT_TYPE_PRICE
+    {
+        return err!(Errors::InvalidOracle);
+    }
+    Ok(pyth_price)
+}
+
+fn scale_pyth_value(value: i128, expo: i32) -> Result<i128> {
+    let pyth_precision = 10_i128
+        .checked_pow(expo.unsigned_abs())
+        .ok_or(Errors::MathError)?;
+    let scaled = if expo < 0 {
+        value
+            .checked_mul(MARK_PRICE_PRECISION as i128)
+            .map(|value| value / pyth_precision)
+    } else {
+        value
+            .checked_mul(MARK_PRICE_PRECISION as i128)
+            .and_then(|value| value.checked_mul(pyth_precision))
+    };
+    scaled.ok_or_else(|| error!(Errors::MathError))
+}
+
+// pyth v2 price账户的头部布局（与pyth-client中的Price结构体前240字节一致）
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct PythPrice {
+    pub magic: u32,      // pyth magic number
+    pub ver: u32,        // 版本号
+    pub atype: u32,      // 账户类型
+    pub size: u32,       // 账户大小
+    pub ptype: u32,      // 价格类型
+    pub expo: i32,       // 价格指数（price * 10^expo为真实价格）
+    pub num: u32,        // 报价者数量
+    pub num_qt: u32,     // 参与聚合的报价者数量
+    pub last_slot: u64,  // 最近一次聚合的slot
+    pub valid_slot: u64, // 聚合价格有效的slot
+    pub twap: PythEma,   // 价格的时间加权平均
+    pub twac: PythEma,   // 置信区间的时间加权平均
+    pub drv1: i64,
+    pub drv2: i64,
+    pub prod: [u8; 32],  // product账户地址
+    pub next: [u8; 32],  // 下一个price账户地址
+    pub prev_slot: u64,  // 上一次聚合的slot
+    pub prev_price: i64, // 上一次聚合的价格
+    pub prev_conf: u64,  // 上一次聚合的置信区间
+    pub drv3: i64,
+    pub agg: PythPriceInfo, // 聚合价格信息
+}
+
+const_assert_eq!(std::mem::size_of::<PythPrice>(), 240);
+
+unsafe impl Zeroable for PythPrice {}
+unsafe impl Pod for PythPrice {}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct PythEma {
+    pub val: i64,
+    pub numer: i64,
+    pub denom: i64,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct PythPriceInfo {
+    pub price: i64,    // 价格
+    pub conf: u64,     // 置信区间
+    pub status: u32,   // 价格状态
+    pub corp_act: u32, // 公司行为
+    pub pub_slot: u64, // 发布slot
+}
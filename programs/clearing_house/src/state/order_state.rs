@@ -7,9 +7,22 @@ pub struct OrderState {
     pub order_history: Pubkey, // 存储order历史记录的账户
     pub order_filler_reward_structure: OrderFillerRewardStructure, // order填充者的奖励结构
     pub min_order_quote_asset_amount: u128, // 订单成功放置所需的最小quote资产金额估计值
+    pub default_max_order_age: u32, // 下单时未指定max_age的订单默认的最长存活时间（秒），0表示永不过期
+    pub version: u8,                // OrderState账户的版本，见ORDER_STATE_VERSION
 }
 
-const_assert_eq!(std::mem::size_of::<OrderState>(), 96);
+const_assert_eq!(std::mem::size_of::<OrderState>(), 112);
+
+// 当前program使用的OrderState版本。修改OrderState布局时递增该值，并在migrate_order_state中补充迁移逻辑
+// 版本0：引入version字段之前的OrderState（序列化后末尾的空闲字节为0，反序列化出的version即为0）
+pub const ORDER_STATE_VERSION: u8 = 1;
+
+impl OrderState {
+    // OrderState是否已经迁移到当前program使用的版本
+    pub fn is_current_version(&self) -> bool {
+        self.version == ORDER_STATE_VERSION
+    }
+}
 
 #[derive(Clone, AnchorSerialize, AnchorDeserialize)]
 pub struct OrderFillerRewardStructure {
@@ -1,3 +1,4 @@
+use anchor_lang::prelude::borsh;
 use anchor_lang::prelude::*;
 use static_assertions::const_assert_eq;
 use std::mem::size_of;
@@ -9,7 +10,11 @@ pub struct State {
     pub admin_controls_prices: u8,            // 管理员是否控制价格(紧急情况下)
     pub collateral_vault_authority_nonce: u8, // 生成collateral_vault_authority的bump值
     pub insurance_vault_authority_nonce: u8,  // 生成insurance_vault_authority的bump值
-    pub padding0: [u8; 11],
+    pub history_ring_buffers_disabled: u8, // 是否停止把历史记录写入各history账户（event照常发出）
+    pub bump: u8,                          // State pda（seeds为[b"clearing_house"]）的bump值
+    pub markets_bump: u8,                  // Markets pda（seeds为[b"markets"]）的bump值
+    pub version: u8,                       // State账户的版本，见STATE_VERSION
+    pub padding0: [u8; 7],
 
     pub admin: Pubkey,
     pub collateral_mint: Pubkey,            // 抵押品token的mint地址
@@ -42,14 +47,56 @@ pub struct State {
     pub max_deposit: u128,                                      // 最大存款限额
     pub extended_curve_history: Pubkey,                         // 扩展的曲线历史记录账户地址
     pub order_state: Pubkey,                                    // 订单状态账户地址
+    pub pending_admin: Pubkey,                                  // 待接受的新admin（两步转移）
     // Upgrade ability
-    pub padding1: [u128; 4],
+    pub padding1: [u128; 2],
 }
 
 const_assert_eq!(size_of::<State>(), 1200);
 
+// 当前program使用的State版本。修改State布局时递增该值，并在migrate_state中补充旧版本到新版本的迁移
+// 版本0：引入version字段之前的State（布局与版本1相同）
+pub const STATE_VERSION: u8 = 1;
+
+impl State {
+    // State是否已经迁移到当前program使用的版本
+    pub fn is_current_version(&self) -> bool {
+        self.version == STATE_VERSION
+    }
+
+    // 整个exchange是否暂停（暂停时只允许reduce only订单、取消订单与settle_position）
+    pub fn is_exchange_paused(&self) -> bool {
+        self.exchange_paused != 0
+    }
+
+    // 资金费率的更新与结算是否暂停
+    pub fn is_funding_paused(&self) -> bool {
+        self.funding_paused != 0
+    }
+
+    // 历史记录是否写入各history账户的ring buffer
+    pub fn is_history_ring_buffer_enabled(&self) -> bool {
+        self.history_ring_buffers_disabled == 0
+    }
+
+    // initialize_history负责的6个history账户中是否有任意一个已经设置
+    pub fn is_any_history_initialized(&self) -> bool {
+        [
+            self.trade_history,
+            self.deposit_history,
+            self.liquidation_history,
+            self.funding_rate_history,
+            self.funding_payment_history,
+            self.curve_history,
+        ]
+        .iter()
+        .any(|history| !history.eq(&Pubkey::default()))
+    }
+}
+
 // Oracle防护栏（防护机制）
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct OracleGuardRails {
     pub price_divergence: PriceDivergenceGuardRails, // 价格偏离保护(标记价格与预言机价格的最大偏离比例)
     pub validity: ValidityGuardRails,                // 数据有效性检查
@@ -59,6 +106,7 @@ pub struct OracleGuardRails {
 
 // 价格背离防护栏
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct PriceDivergenceGuardRails {
     pub mark_oracle_divergence_numerator: u128, // 标记价格与预言机价格的最大偏离比例分子
     pub mark_oracle_divergence_denominator: u128, // 标记价格与预言机价格的最大偏离比例分母
@@ -66,6 +114,7 @@ pub struct PriceDivergenceGuardRails {
 
 // 有效性防护机制
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct ValidityGuardRails {
     pub confidence_interval_max_size: u128, // 置信区间最大宽度
     pub too_volatile_ratio: i128,           // 价格波动率阈值
@@ -75,6 +124,7 @@ pub struct ValidityGuardRails {
 
 // fee结构
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct FeeStructure {
     pub fee_numerator: u128,                      // 基础fee分子
     pub fee_denominator: u128,                    // 基础fee分母
@@ -83,6 +133,7 @@ pub struct FeeStructure {
 }
 
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct DiscountTokenTiers {
     pub first_tier: DiscountTokenTier,  // 第1档
     pub second_tier: DiscountTokenTier, // 第2档
@@ -91,6 +142,7 @@ pub struct DiscountTokenTiers {
 }
 
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct DiscountTokenTier {
     pub discount_numerator: u128,   // 折扣率分子
     pub discount_denominator: u128, // 折扣率分母
@@ -99,9 +151,41 @@ pub struct DiscountTokenTier {
 }
 
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct ReferralDiscount {
     pub referral_reward_numerator: u128,    // 推荐人奖励分子
     pub referral_reward_denominator: u128,  // 推荐人奖励分母
     pub referee_discount_numerator: u128,   // 被推荐人折扣分子
     pub referee_discount_denominator: u128, // 被推荐人折扣分母
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn any_history_initialized() {
+        assert!(!State::zeroed().is_any_history_initialized());
+
+        // 只设置其中一个history也视为已经初始化
+        let setters: [fn(&mut State); 6] = [
+            |state| state.trade_history = Pubkey::new_unique(),
+            |state| state.deposit_history = Pubkey::new_unique(),
+            |state| state.liquidation_history = Pubkey::new_unique(),
+            |state| state.funding_rate_history = Pubkey::new_unique(),
+            |state| state.funding_payment_history = Pubkey::new_unique(),
+            |state| state.curve_history = Pubkey::new_unique(),
+        ];
+        for set_history in setters {
+            let mut state = State::zeroed();
+            set_history(&mut state);
+            assert!(state.is_any_history_initialized());
+        }
+
+        // extended curve history由initialize_extended_curve_history单独初始化，不影响initialize_history
+        let mut state = State::zeroed();
+        state.extended_curve_history = Pubkey::new_unique();
+        assert!(!state.is_any_history_initialized());
+    }
+}
@@ -0,0 +1,91 @@
+use anchor_lang::prelude::*;
+use static_assertions::const_assert_eq;
+use std::mem::size_of;
+
+use crate::errors::Errors;
+
+#[account]
+#[repr(C)]
+pub struct User {
+    pub authority: Pubkey,            // 用户的钱包地址
+    pub collateral: u128,             // 用户当前的抵押品数量
+    pub cumulative_deposits: i128,    // 累计净存款（存款减取款）
+    pub total_fee_paid: u128,         // 累计支付的手续费
+    pub total_token_discount: u128,   // 累计获得的持币折扣
+    pub total_referral_reward: u128,  // 作为推荐人累计获得的奖励
+    pub total_referee_discount: u128, // 作为被推荐人累计获得的折扣
+    pub positions: Pubkey,            // 用户的UserPositions账户地址
+    pub referrer: Pubkey, // 用户第一次带推荐人下单时锁定的推荐人（User账户地址），之后不能更换
+    // upgrade-ability
+    pub padding2: u128,
+    pub padding3: u128,
+}
+
+const_assert_eq!(size_of::<User>(), 224);
+
+#[account(zero_copy)]
+// 每个用户最多同时持有5个market的头寸
+pub struct UserPositions {
+    pub user: Pubkey, // 所属的User账户地址
+    pub positions: [MarketPosition; 5],
+}
+
+const_assert_eq!(size_of::<UserPositions>(), 672);
+
+impl UserPositions {
+    // 找到用户在market_index上的头寸槽位
+    pub fn get_position_index(&self, market_index: u64) -> Result<usize> {
+        self.positions
+            .iter()
+            .position(|position| position.is_for(market_index))
+            .ok_or_else(|| error!(Errors::UserHasNoPositionInMarket))
+    }
+
+    // 在第一个空闲的槽位上为market_index开一个新头寸
+    pub fn add_new_position(&mut self, market_index: u64) -> Result<usize> {
+        let new_position_index = self
+            .positions
+            .iter()
+            .position(|position| position.is_available())
+            .ok_or_else(|| error!(Errors::MaxNumberOfPositions))?;
+
+        self.positions[new_position_index] = MarketPosition {
+            market_index,
+            ..MarketPosition::default()
+        };
+
+        Ok(new_position_index)
+    }
+
+    // 找到用户在market_index上的头寸槽位，没有时开一个新头寸
+    pub fn get_or_add_position_index(&mut self, market_index: u64) -> Result<usize> {
+        self.get_position_index(market_index)
+            .or_else(|_| self.add_new_position(market_index))
+    }
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct MarketPosition {
+    pub market_index: u64,                  // 头寸所在的市场索引
+    pub last_funding_rate_ts: i64,          // 上次结算资金费的时间戳
+    pub base_asset_amount: i128,            // 头寸的base资产数量（正数为多头，负数为空头）
+    pub quote_asset_amount: u128,           // 开仓时的quote资产价值（用于计算盈亏）
+    pub last_cumulative_funding_rate: i128, // 上次结算时的累计资金费率
+    pub last_cumulative_repeg_rebate: u128, // 上次结算时的累计重新锚定返利
+    pub open_orders: u128,                  // 该市场上挂单的数量
+    // upgrade-ability
+    pub padding0: u128,
+    pub padding1: u128,
+}
+
+impl MarketPosition {
+    // 既没有持仓也没有挂单的槽位可以被其他market复用
+    pub fn is_available(&self) -> bool {
+        self.base_asset_amount == 0 && self.open_orders == 0
+    }
+
+    pub fn is_for(&self, market_index: u64) -> bool {
+        self.market_index == market_index && !self.is_available()
+    }
+}
@@ -1,36 +1,115 @@
+use anchor_lang::prelude::borsh;
 use anchor_lang::prelude::*;
 use bytemuck::{Pod, Zeroable};
+use static_assertions::const_assert_eq;
+use std::mem::size_of;
 
 use crate::controller::position::PositionDirection;
+use crate::errors::Errors;
+
+#[account(zero_copy)]
+// 每个用户最多同时存在32个订单
+pub struct UserOrders {
+    pub user: Pubkey, // 所属的User账户地址
+    pub orders: [Order; 32],
+}
+
+const_assert_eq!(size_of::<UserOrders>(), 7200);
+
+impl UserOrders {
+    // 找到第一个空闲的订单槽位
+    pub fn get_unused_order_index(&self) -> Result<usize> {
+        self.orders
+            .iter()
+            .position(|order| order.is_available())
+            .ok_or_else(|| error!(Errors::MaxNumberOfOrders))
+    }
+
+    // 按全局唯一的order_id查找订单槽位
+    pub fn get_order_index(&self, order_id: u128) -> Result<usize> {
+        self.orders
+            .iter()
+            .position(|order| !order.is_available() && order.order_id == order_id)
+            .ok_or_else(|| error!(Errors::OrderDoesNotExist))
+    }
+
+    // 按用户自定义的user_order_id查找订单槽位（user_order_id为0表示用户未设置，不可用于查找）
+    pub fn get_order_index_by_user_order_id(&self, user_order_id: u8) -> Result<usize> {
+        if user_order_id == 0 {
+            return err!(Errors::OrderDoesNotExist);
+        }
+        self.orders
+            .iter()
+            .position(|order| !order.is_available() && order.user_order_id == user_order_id)
+            .ok_or_else(|| error!(Errors::OrderDoesNotExist))
+    }
+
+    // 是否所有订单槽位都为空
+    pub fn is_empty(&self) -> bool {
+        self.orders.iter().all(|order| order.is_available())
+    }
+}
 
 #[zero_copy]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct Order {
-    pub status: OrderStatus,                      // 订单状态
-    pub order_type: OrderType,                    // 订单类型
-    pub direction: PositionDirection,             // 方向（做多做空）
-    pub user_order_id: u8,                        // 用户自定义订单标识（0-255）
-    pub reduce_only: u8,                          // 是否仅减仓（不允许增加风险）
-    pub post_only: u8,                            // 是否只做Maker（不支付Taker费）
-    pub immediate_or_cancel: u8,                  // 是否立即成交否则取消（IOC）
-    pub discount_tier: OrderDiscountTier,         // 手续费折扣等级（从无折扣到4级折扣）
-    pub trigger_condition: OrderTriggerCondition, // 订单触发条件
-    pub padding: [u8; 7],
-    pub ts: i64,                         // 订单创建时间戳
-    pub market_index: u64,               // 交易对的市场索引（如 BTC/USDC=0）
-    pub order_id: u128,                  // 全局唯一订单ID
-    pub price: u128,                     // 订单限价（原始价格）
-    pub user_base_asset_amount: i128,    // 用户期望的base资产数量（可正负）
-    pub quote_asset_amount: u128,        // 实际quote资产数量（绝对值）
-    pub base_asset_amount: u128,         // 实际base资产数量（绝对值）
-    pub base_asset_amount_filled: u128,  // 已成交的base资产数量
+    pub status: OrderStatus,                           // 订单状态
+    pub order_type: OrderType,                         // 订单类型
+    pub direction: PositionDirection,                  // 方向（做多做空）
+    pub user_order_id: u8,                             // 用户自定义订单标识（0-255）
+    pub reduce_only: u8,                               // 是否仅减仓（不允许增加风险）
+    pub post_only: u8,                                 // 是否只做Maker（不支付Taker费）
+    pub immediate_or_cancel: u8,                       // 是否立即成交否则取消（IOC）
+    pub discount_tier: OrderDiscountTier,              // 手续费折扣等级（从无折扣到4级折扣）
+    pub trigger_condition: OrderTriggerCondition,      // 订单触发条件
+    pub trigger_price_source: OrderTriggerPriceSource, // 判断触发条件时使用的价格来源
+    pub padding: [u8; 2],
+    pub max_age: u32,      // 订单从ts起的最长存活时间（秒），0表示永不过期
+    pub ts: i64,           // 订单创建时间戳
+    pub market_index: u64, // 交易对的市场索引（如 BTC/USDC=0）
+    pub order_id: u128,    // 全局唯一订单ID
+    pub price: u128,       // 订单限价（原始价格）
+    pub user_base_asset_amount: i128, // 用户期望的base资产数量（可正负）
+    pub quote_asset_amount: u128, // 实际quote资产数量（绝对值）
+    pub base_asset_amount: u128, // 实际base资产数量（绝对值）
+    pub base_asset_amount_filled: u128, // 已成交的base资产数量
     pub quote_asset_amount_filled: u128, // 已成交的quote资产数量
-    pub fee: i128,                       // 手续费（可正可负，负值表示返佣）
-    pub trigger_price: u128,             // 触发单的触发价格
-    pub referrer: Pubkey,                // 推荐人地址（用于返佣）
-    pub oracle_price_offset: i128,       // 相对于预言机价格的偏移量（动态定价）
+    pub fee: i128,         // 手续费（可正可负，负值表示返佣）
+    pub trigger_price: u128, // 触发单的触发价格
+    pub referrer: Pubkey,  // 推荐人地址（用于返佣）
+    pub oracle_price_offset: i128, // 相对于预言机价格的偏移量（动态定价）
 }
 
-#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize)]
+const_assert_eq!(size_of::<Order>(), 224);
+
+impl Order {
+    // order_id从1开始分配，所以order_id为0的槽位是空闲的
+    pub fn is_available(&self) -> bool {
+        self.order_id == 0
+    }
+
+    pub fn is_trigger_order(&self) -> bool {
+        matches!(
+            self.order_type,
+            OrderType::TriggerMarket | OrderType::TriggerLimit
+        )
+    }
+
+    // 订单是否已超过最长存活时间
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.max_age != 0 && now.saturating_sub(self.ts) >= self.max_age as i64
+    }
+
+    // 当前价格是否满足订单的触发条件
+    pub fn is_trigger_condition_met(&self, price: u128) -> bool {
+        match self.trigger_condition {
+            OrderTriggerCondition::Above => price > self.trigger_price,
+            OrderTriggerCondition::Below => price < self.trigger_price,
+        }
+    }
+}
+
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
 #[repr(u8)]
 pub enum OrderStatus {
     Init, // 订单已创建但未开放（如触发单等待条件）
@@ -40,7 +119,7 @@ pub enum OrderStatus {
 unsafe impl Zeroable for OrderStatus {}
 unsafe impl Pod for OrderStatus {}
 
-#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize)]
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
 #[repr(u8)]
 pub enum OrderType {
     Market,        // 市价单
@@ -74,3 +153,13 @@ pub enum OrderTriggerCondition {
 
 unsafe impl Zeroable for OrderTriggerCondition {}
 unsafe impl Pod for OrderTriggerCondition {}
+
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize)]
+#[repr(u8)]
+pub enum OrderTriggerPriceSource {
+    Oracle, // 使用预言机价格判断触发条件
+    Mark,   // 使用AMM的mark price判断触发条件
+}
+
+unsafe impl Zeroable for OrderTriggerPriceSource {}
+unsafe impl Pod for OrderTriggerPriceSource {}
@@ -0,0 +1,72 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::Errors;
+use crate::state::state::{DiscountTokenTier, FeeStructure, ReferralDiscount};
+
+// 校验fee结构：
+// 1. 基础fee的分母不为0，且fee比例小于100%
+// 2. 4个持币折扣档位按minimun_balance严格递减（第1档要求持币最多），且每档折扣都小于100%
+// 3. 推荐人奖励与被推荐人折扣之和小于基础fee（两者都是按基础fee的比例计算的）
+pub fn validate_fee_structure(fee_structure: &FeeStructure) -> Result<()> {
+    validate_fraction_below_one(fee_structure.fee_numerator, fee_structure.fee_denominator)?;
+
+    let tiers = &fee_structure.discount_token_tiers;
+    validate_discount_token_tier(&tiers.first_tier)?;
+    validate_discount_token_tier(&tiers.second_tier)?;
+    validate_discount_token_tier(&tiers.third_tier)?;
+    validate_discount_token_tier(&tiers.fourth_tier)?;
+    require!(
+        tiers.first_tier.minimun_balance > tiers.second_tier.minimun_balance
+            && tiers.second_tier.minimun_balance > tiers.third_tier.minimun_balance
+            && tiers.third_tier.minimun_balance > tiers.fourth_tier.minimun_balance,
+        Errors::InvalidFeeStructure
+    );
+
+    validate_referral_discount(&fee_structure.referral_discount)
+}
+
+fn validate_discount_token_tier(tier: &DiscountTokenTier) -> Result<()> {
+    validate_fraction_below_one(tier.discount_numerator, tier.discount_denominator)
+}
+
+// 检查 reward_numerator/reward_denominator + discount_numerator/discount_denominator < 1
+// 即 reward_numerator * discount_denominator + discount_numerator * reward_denominator < reward_denominator * discount_denominator
+fn validate_referral_discount(referral_discount: &ReferralDiscount) -> Result<()> {
+    let ReferralDiscount {
+        referral_reward_numerator,
+        referral_reward_denominator,
+        referee_discount_numerator,
+        referee_discount_denominator,
+    } = *referral_discount;
+    require!(
+        referral_reward_denominator != 0 && referee_discount_denominator != 0,
+        Errors::InvalidFeeStructure
+    );
+
+    let total_share_numerator = referral_reward_numerator
+        .checked_mul(referee_discount_denominator)
+        .and_then(|reward| {
+            referee_discount_numerator
+                .checked_mul(referral_reward_denominator)
+                .and_then(|discount| reward.checked_add(discount))
+        })
+        .ok_or(Errors::InvalidFeeStructure)?;
+    let total_share_denominator = referral_reward_denominator
+        .checked_mul(referee_discount_denominator)
+        .ok_or(Errors::InvalidFeeStructure)?;
+    require!(
+        total_share_numerator < total_share_denominator,
+        Errors::InvalidFeeStructure
+    );
+
+    Ok(())
+}
+
+// 分母不为0且分子严格小于分母
+fn validate_fraction_below_one(numerator: u128, denominator: u128) -> Result<()> {
+    require!(
+        denominator != 0 && numerator < denominator,
+        Errors::InvalidFeeStructure
+    );
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::Errors;
+
+// 校验清算相关的比例：分母不能为0，且分子不能大于分母（即比例不超过100%）
+pub fn validate_liquidation_percentage(numerator: u128, denominator: u128) -> Result<()> {
+    require!(
+        denominator != 0 && numerator <= denominator,
+        Errors::InvalidLiquidationParameter
+    );
+    Ok(())
+}
+
+// 校验清算人份额的分母：清算人获得的份额为 1/denominator，所以分母不能为0
+pub fn validate_liquidator_share_denominator(denominator: u128) -> Result<()> {
+    require!(denominator != 0, Errors::InvalidLiquidationParameter);
+    Ok(())
+}
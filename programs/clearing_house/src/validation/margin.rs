@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::Errors;
+use crate::math::constant::{MAXIMUM_MARGIN_RATIO, MINIMUM_MARGIN_RATIO};
+
+// 校验一组保证金比例是否合法：
+// 1. 每个比例都必须在[MINIMUM_MARGIN_RATIO, MAXIMUM_MARGIN_RATIO]之间（即1倍~50倍杠杆）
+// 2. 必须满足 initial >= maintenance >= partial
+pub fn validate_margin(
+    margin_ratio_initial: u128,
+    margin_ratio_partial: u128,
+    margin_ratio_maintenance: u128,
+) -> Result<()> {
+    let valid_range = MINIMUM_MARGIN_RATIO..=MAXIMUM_MARGIN_RATIO;
+    require!(
+        valid_range.contains(&margin_ratio_initial)
+            && valid_range.contains(&margin_ratio_partial)
+            && valid_range.contains(&margin_ratio_maintenance),
+        Errors::InvalidMarginRatio
+    );
+
+    require!(
+        margin_ratio_initial >= margin_ratio_maintenance
+            && margin_ratio_maintenance >= margin_ratio_partial,
+        Errors::InvalidMarginRatio
+    );
+
+    Ok(())
+}
@@ -0,0 +1,6 @@
+pub mod fee_structure;
+pub mod liquidation;
+pub mod margin;
+pub mod oracle_guard_rails;
+pub mod order;
+pub mod whitelist;
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::Errors;
+use crate::state::state::OracleGuardRails;
+
+// 校验oracle防护栏参数：
+// 1. mark/oracle价格最大偏离比例在(0, 100%]之间
+// 2. 置信区间、波动率阈值与过期slot数都必须是有意义的正数
+// 3. use_for_liquidations只能为0或1
+pub fn validate_oracle_guard_rails(oracle_guard_rails: &OracleGuardRails) -> Result<()> {
+    let price_divergence = &oracle_guard_rails.price_divergence;
+    require!(
+        price_divergence.mark_oracle_divergence_numerator > 0
+            && price_divergence.mark_oracle_divergence_numerator
+                <= price_divergence.mark_oracle_divergence_denominator,
+        Errors::InvalidOracleGuardRails
+    );
+
+    let validity = &oracle_guard_rails.validity;
+    require!(
+        validity.confidence_interval_max_size > 0
+            && validity.too_volatile_ratio > 1
+            && validity.slots_before_stable > 0,
+        Errors::InvalidOracleGuardRails
+    );
+
+    require!(
+        oracle_guard_rails.use_for_liquidations <= 1,
+        Errors::InvalidOracleGuardRails
+    );
+
+    Ok(())
+}
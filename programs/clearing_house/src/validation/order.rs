@@ -0,0 +1,199 @@
+use anchor_lang::prelude::*;
+
+use crate::controller::position::PositionDirection;
+use crate::errors::Errors;
+use crate::math::orders::{
+    calculate_limit_price, calculate_quote_asset_amount_for_price, get_trigger_reference_price,
+};
+use crate::state::market::Market;
+use crate::state::order_state::OrderState;
+use crate::state::user_orders::{Order, OrderType};
+
+// 按订单类型校验新订单的参数
+pub fn validate_order(
+    order: &Order,
+    market: &Market,
+    order_state: &OrderState,
+    mark_price: u128,
+    oracle_price: i128,
+) -> Result<()> {
+    match order.order_type {
+        OrderType::Market => validate_market_order(order, market, order_state, mark_price),
+        OrderType::Limit => validate_limit_order(order, market, order_state, oracle_price),
+        OrderType::TriggerMarket => validate_trigger_market_order(order, market, order_state),
+        OrderType::TriggerLimit => validate_trigger_limit_order(order, market, order_state),
+    }?;
+
+    if order.is_trigger_order() {
+        validate_trigger_price(order, mark_price, oracle_price)?;
+    }
+
+    if order.post_only != 0 && order.order_type == OrderType::Limit {
+        validate_post_only(order, mark_price, oracle_price)?;
+    }
+
+    Ok(())
+}
+
+// post only订单下单时不能与AMM立即成交：买单的限价必须低于mark price，卖单的限价必须高于mark price
+fn validate_post_only(order: &Order, mark_price: u128, oracle_price: i128) -> Result<()> {
+    let limit_price = calculate_limit_price(order, oracle_price)?;
+    let crosses_amm = match order.direction {
+        PositionDirection::Long => limit_price >= mark_price,
+        PositionDirection::Short => limit_price <= mark_price,
+    };
+    if crosses_amm {
+        return err!(Errors::PostOnlyOrderWouldCross);
+    }
+
+    Ok(())
+}
+
+// 市价单：base与quote数量二选一；price为可选的滑点保护价格，不能设置触发价格，也不能是post only
+fn validate_market_order(
+    order: &Order,
+    market: &Market,
+    order_state: &OrderState,
+    mark_price: u128,
+) -> Result<()> {
+    if (order.base_asset_amount > 0) == (order.quote_asset_amount > 0) {
+        return err!(Errors::InvalidOrder);
+    }
+    if order.trigger_price > 0 || order.post_only != 0 || order.oracle_price_offset != 0 {
+        return err!(Errors::InvalidOrder);
+    }
+
+    if order.base_asset_amount > 0 {
+        validate_base_asset_amount(order, market, order_state, mark_price)
+    } else {
+        validate_quote_asset_amount(order.quote_asset_amount, market, order_state)
+    }
+}
+
+// 限价单：必须以base数量下单，不能设置触发价格；
+// 限价由price指定，或者由oracle_price_offset指定（成交时的限价为oracle价格加上偏移量），二者只能选其一
+fn validate_limit_order(
+    order: &Order,
+    market: &Market,
+    order_state: &OrderState,
+    oracle_price: i128,
+) -> Result<()> {
+    if order.quote_asset_amount > 0 || order.trigger_price > 0 {
+        return err!(Errors::InvalidOrder);
+    }
+    if (order.price > 0) == (order.oracle_price_offset != 0) {
+        return err!(Errors::InvalidOrder);
+    }
+    if order.oracle_price_offset != 0 {
+        validate_oracle_price_offset(order, oracle_price)?;
+    }
+
+    let limit_price = calculate_limit_price(order, oracle_price)?;
+    validate_base_asset_amount(order, market, order_state, limit_price)
+}
+
+// 所有oracle偏移订单下单时按当前oracle价格解析出的限价都必须为正；
+// post only订单还必须挂在oracle价格的maker一侧：买单低于oracle价格，卖单高于oracle价格。
+// 非post only订单允许偏移到taker一侧，此时订单以oracle价格加偏移量作为滑点保护的限价立即与AMM成交
+fn validate_oracle_price_offset(order: &Order, oracle_price: i128) -> Result<()> {
+    let limit_price = oracle_price
+        .checked_add(order.oracle_price_offset)
+        .ok_or(Errors::MathError)?;
+    if limit_price <= 0 {
+        return err!(Errors::InvalidOraclePriceOffset);
+    }
+
+    if order.post_only != 0 {
+        let on_maker_side = match order.direction {
+            PositionDirection::Long => order.oracle_price_offset < 0,
+            PositionDirection::Short => order.oracle_price_offset > 0,
+        };
+        if !on_maker_side {
+            return err!(Errors::InvalidOraclePriceOffset);
+        }
+    }
+
+    Ok(())
+}
+
+// 触发市价单：必须以base数量下单并设置触发价格，不能是post only
+fn validate_trigger_market_order(
+    order: &Order,
+    market: &Market,
+    order_state: &OrderState,
+) -> Result<()> {
+    if order.quote_asset_amount > 0
+        || order.trigger_price == 0
+        || order.post_only != 0
+        || order.oracle_price_offset != 0
+    {
+        return err!(Errors::InvalidOrder);
+    }
+
+    validate_base_asset_amount(order, market, order_state, order.trigger_price)
+}
+
+// 触发限价单：必须以base数量下单，并同时设置限价与触发价格
+fn validate_trigger_limit_order(
+    order: &Order,
+    market: &Market,
+    order_state: &OrderState,
+) -> Result<()> {
+    if order.quote_asset_amount > 0
+        || order.price == 0
+        || order.trigger_price == 0
+        || order.oracle_price_offset != 0
+    {
+        return err!(Errors::InvalidOrder);
+    }
+
+    validate_base_asset_amount(order, market, order_state, order.price)
+}
+
+// 触发价格必须位于当前价格的正确一侧（Above时高于当前价格，Below时低于当前价格），
+// 即下单时触发条件还没有被满足
+fn validate_trigger_price(order: &Order, mark_price: u128, oracle_price: i128) -> Result<()> {
+    let price = get_trigger_reference_price(order, mark_price, oracle_price)?;
+    if order.is_trigger_condition_met(price) || order.trigger_price == price {
+        return err!(Errors::InvalidTriggerPrice);
+    }
+
+    Ok(())
+}
+
+// base数量不能小于market的最小交易量，且按price估算的价值不能小于min_order_quote_asset_amount
+fn validate_base_asset_amount(
+    order: &Order,
+    market: &Market,
+    order_state: &OrderState,
+    price: u128,
+) -> Result<()> {
+    if order.base_asset_amount == 0 {
+        return err!(Errors::InvalidOrder);
+    }
+    if order.base_asset_amount < market.amm.minimum_base_asset_trade_size {
+        return err!(Errors::OrderAmountTooSmall);
+    }
+
+    let approximate_quote_asset_amount =
+        calculate_quote_asset_amount_for_price(order.base_asset_amount, price)?;
+    if approximate_quote_asset_amount < order_state.min_order_quote_asset_amount {
+        return err!(Errors::OrderAmountTooSmall);
+    }
+
+    Ok(())
+}
+
+fn validate_quote_asset_amount(
+    quote_asset_amount: u128,
+    market: &Market,
+    order_state: &OrderState,
+) -> Result<()> {
+    if quote_asset_amount < market.amm.mininum_quote_asset_trade_size
+        || quote_asset_amount < order_state.min_order_quote_asset_amount
+    {
+        return err!(Errors::OrderAmountTooSmall);
+    }
+
+    Ok(())
+}
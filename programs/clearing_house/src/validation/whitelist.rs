@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+use crate::errors::Errors;
+
+// 校验白名单token account：mint必须是whitelist_mint，owner必须是authority，且余额至少为1
+pub fn validate_whitelist_token(
+    whitelist_token: &TokenAccount,
+    whitelist_mint: &Pubkey,
+    authority: &Pubkey,
+) -> Result<()> {
+    require!(
+        whitelist_token.mint.eq(whitelist_mint)
+            && whitelist_token.owner.eq(authority)
+            && whitelist_token.amount > 0,
+        Errors::InvalidWhitelistToken
+    );
+    Ok(())
+}
@@ -22,9 +22,9 @@ pub mod mock_usdc_faucet {
         }
 
         **ctx.accounts.state = State {
-            mint_authority_pda: mint_authority_pda,
+            mint_authority_pda,
             mint: mock_usdc_mint,
-            mint_authority_pda_bump: mint_authority_pda_bump,
+            mint_authority_pda_bump,
         };
 
         Ok(())
@@ -0,0 +1,125 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use bytemuck::{Pod, Zeroable};
+
+declare_id!("CivgHA8UqayXHJqGbTyXJdMw8NYt5HQPPMoNFQk2oggf");
+
+// pyth v2 price账户的magic number、版本号以及账户类型（3表示Price账户）
+pub const MAGIC: u32 = 0xa1b2c3d4;
+pub const VERSION_2: u32 = 2;
+pub const ACCOUNT_TYPE_PRICE: u32 = 3;
+// pyth v2 price账户的完整大小（Price头部 + 32个PriceComp）
+pub const PRICE_ACCOUNT_SIZE: usize = 3312;
+
+// 仅用于本地测试的mock pyth program，只写入clearing house会读取的price账户头部字段
+#[program]
+pub mod pyth {
+    use super::*;
+
+    pub fn initialize(ctx: Context<Initialize>, price: i64, expo: i32, conf: u64) -> Result<()> {
+        let slot = Clock::get()?.slot;
+        let mut data = ctx.accounts.price.try_borrow_mut_data()?;
+        let price_account: &mut Price =
+            bytemuck::from_bytes_mut(&mut data[..std::mem::size_of::<Price>()]);
+
+        price_account.magic = MAGIC;
+        price_account.ver = VERSION_2;
+        price_account.atype = ACCOUNT_TYPE_PRICE;
+        price_account.size = PRICE_ACCOUNT_SIZE as u32;
+        price_account.expo = expo;
+        price_account.valid_slot = slot;
+        price_account.twap.val = price;
+        price_account.agg.price = price;
+        price_account.agg.conf = conf;
+        price_account.agg.status = 1; // Trading
+        price_account.agg.pub_slot = slot;
+
+        Ok(())
+    }
+
+    pub fn set_price(ctx: Context<SetPrice>, price: i64) -> Result<()> {
+        let slot = Clock::get()?.slot;
+        let mut data = ctx.accounts.price.try_borrow_mut_data()?;
+        let price_account: &mut Price =
+            bytemuck::from_bytes_mut(&mut data[..std::mem::size_of::<Price>()]);
+
+        price_account.prev_price = price_account.agg.price;
+        price_account.prev_slot = price_account.valid_slot;
+        price_account.valid_slot = slot;
+        price_account.twap.val = price;
+        price_account.agg.price = price;
+        price_account.agg.pub_slot = slot;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    /// CHECK: 由客户端预先创建（owner为本program，大小为PRICE_ACCOUNT_SIZE），这里只写入数据
+    #[account(
+        mut,
+        owner = crate::ID,
+        constraint = price.data_len() == PRICE_ACCOUNT_SIZE
+    )]
+    pub price: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    /// CHECK: 同Initialize
+    #[account(
+        mut,
+        owner = crate::ID,
+        constraint = price.data_len() == PRICE_ACCOUNT_SIZE
+    )]
+    pub price: UncheckedAccount<'info>,
+}
+
+// pyth v2 price账户的头部布局（与pyth-client中的Price结构体前240字节一致）
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct Price {
+    pub magic: u32,      // pyth magic number
+    pub ver: u32,        // 版本号
+    pub atype: u32,      // 账户类型
+    pub size: u32,       // 账户大小
+    pub ptype: u32,      // 价格类型
+    pub expo: i32,       // 价格指数（price * 10^expo为真实价格）
+    pub num: u32,        // 报价者数量
+    pub num_qt: u32,     // 参与聚合的报价者数量
+    pub last_slot: u64,  // 最近一次聚合的slot
+    pub valid_slot: u64, // 聚合价格有效的slot
+    pub twap: Ema,       // 价格的时间加权平均
+    pub twac: Ema,       // 置信区间的时间加权平均
+    pub drv1: i64,       // 保留字段
+    pub drv2: i64,       // 保留字段
+    pub prod: [u8; 32],  // product账户地址
+    pub next: [u8; 32],  // 下一个price账户地址
+    pub prev_slot: u64,  // 上一次聚合的slot
+    pub prev_price: i64, // 上一次聚合的价格
+    pub prev_conf: u64,  // 上一次聚合的置信区间
+    pub drv3: i64,       // 保留字段
+    pub agg: PriceInfo,  // 聚合价格信息
+}
+
+unsafe impl Zeroable for Price {}
+unsafe impl Pod for Price {}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct Ema {
+    pub val: i64,
+    pub numer: i64,
+    pub denom: i64,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct PriceInfo {
+    pub price: i64,    // 价格
+    pub conf: u64,     // 置信区间
+    pub status: u32,   // 价格状态
+    pub corp_act: u32, // 公司行为
+    pub pub_slot: u64, // 发布slot
+}
@@ -0,0 +1,120 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes } from "@coral-xyz/anchor";
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type OrderParams = IdlTypes<ClearingHouse>['orderParams'];
+
+const MARK_PRICE_PRECISION = new BN(10_000_000_000);
+const AMM_RESERVE_PRECISION = new BN(10_000_000_000_000);
+
+function limitOrderParams(marketIndex: BN, direction: IdlTypes<ClearingHouse>['positionDirection'], userOrderId = 0): OrderParams {
+    return {
+        orderType: { limit: {} },
+        direction,
+        userOrderId,
+        quoteAssetAmount: ZERO_BN,
+        baseAssetAmount: AMM_RESERVE_PRECISION,
+        price: MARK_PRICE_PRECISION.muln(50),
+        marketIndex,
+        reduceOnly: false,
+        postOnly: false,
+        immediateOrCancel: false,
+        triggerPrice: ZERO_BN,
+        triggerCondition: { above: {} },
+        triggerPriceSource: { oracle: {} },
+        oraclePriceOffset: ZERO_BN,
+        maxAge: null,
+    };
+}
+
+describe("clearing house: cancel_order && cancel_order_by_user_order_id && cancel_all_orders", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+    let authority: anchor.web3.PublicKey;
+
+    async function openOrderIds(): Promise<Array<string>> {
+        return (await testCli.getUserOrders(authority)).orders
+            .filter(order => !order.orderId.isZero())
+            .map(order => order.orderId.toString());
+    }
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(9, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeOrderState();
+
+        const oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+        await testCli.initializeMarket(new BN(0), oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+        await testCli.initializeMarket(new BN(1), oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        testCli.changeCurrentSigner(1);
+        authority = testCli.getCurrentSigner().publicKey;
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        // order id 1~5
+        await testCli.placeOrder(limitOrderParams(new BN(0), { long: {} }, 1));
+        await testCli.placeOrder(limitOrderParams(new BN(0), { long: {} }, 2));
+        await testCli.placeOrder(limitOrderParams(new BN(0), { short: {} }));
+        await testCli.placeOrder(limitOrderParams(new BN(1), { long: {} }));
+        await testCli.placeOrder(limitOrderParams(new BN(1), { short: {} }));
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if order does not exist', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(testCli.cancelOrder(new BN(100)), 'OrderDoesNotExist');
+        await requireCustomError(testCli.cancelOrderByUserOrderId(100), 'OrderDoesNotExist');
+        await requireCustomError(testCli.cancelOrderByUserOrderId(0), 'OrderDoesNotExist');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass cancel order', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.cancelOrder(new BN(1));
+
+        expect(await openOrderIds()).deep.eq(['2', '3', '4', '5']);
+        requireBNEq((await testCli.getUserPositions(authority)).positions[0].openOrders, new BN(2));
+
+        const record = (await testCli.getOrderHistory()).orderRecords[5];
+        expect(record.action).deep.eq({ cancel: {} });
+        requireBNEq(record.order.orderId, new BN(1));
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass cancel order by user order id', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.cancelOrderByUserOrderId(2);
+        expect(await openOrderIds()).deep.eq(['3', '4', '5']);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass cancel all orders filtered by market and direction', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.cancelAllOrders(new BN(1), { short: {} });
+        expect(await openOrderIds()).deep.eq(['3', '4']);
+
+        await testCli.cancelAllOrders(null, { long: {} });
+        expect(await openOrderIds()).deep.eq(['3']);
+
+        await testCli.cancelAllOrders();
+        expect(await openOrderIds()).deep.eq([]);
+
+        // 没有挂单和持仓后，头寸槽位被释放
+        for (const position of (await testCli.getUserPositions(authority)).positions) {
+            requireBNEq(position.openOrders, ZERO_BN);
+        }
+        testCli.changeCurrentSigner(0);
+    });
+});
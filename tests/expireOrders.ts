@@ -0,0 +1,127 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes } from "@coral-xyz/anchor";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type OrderParams = IdlTypes<ClearingHouse>['orderParams'];
+
+const MARK_PRICE_PRECISION = new BN(10_000_000_000);
+const AMM_RESERVE_PRECISION = new BN(10_000_000_000_000);
+const QUOTE_PRECISION = new BN(1_000_000);
+
+function limitOrderParams(maxAge: number | null): OrderParams {
+    return {
+        orderType: { limit: {} },
+        direction: { long: {} },
+        userOrderId: 0,
+        quoteAssetAmount: ZERO_BN,
+        baseAssetAmount: AMM_RESERVE_PRECISION,
+        price: MARK_PRICE_PRECISION.muln(49),
+        marketIndex: ZERO_BN,
+        reduceOnly: false,
+        postOnly: false,
+        immediateOrCancel: false,
+        triggerPrice: ZERO_BN,
+        triggerCondition: { above: {} },
+        triggerPriceSource: { oracle: {} },
+        oraclePriceOffset: ZERO_BN,
+        maxAge,
+    };
+}
+
+describe("clearing house: expire orders", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+    let userAuthority: anchor.web3.PublicKey;
+    let fillerAuthority: anchor.web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 3);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+
+        const oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+        await testCli.initializeMarket(ZERO_BN, oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        userAuthority = testCli.signers[1].publicKey;
+        fillerAuthority = testCli.signers[2].publicKey;
+        const amount = QUOTE_PRECISION.muln(1000);
+        const userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority);
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, userCollateralAccount, testCli.signers[0], BigInt(amount.toString()));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        await testCli.depositCollateral(amount, userCollateralAccount);
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass use default max order age if not set', async () => {
+        await testCli.updateDefaultMaxOrderAge(3600);
+        expect((await testCli.getOrderState()).defaultMaxOrderAge).eq(3600);
+
+        testCli.changeCurrentSigner(1);
+        await testCli.placeOrder(limitOrderParams(null));
+        expect((await testCli.getUserOrders(userAuthority)).orders[0].maxAge).eq(3600);
+        await testCli.cancelOrder(new BN(1));
+        testCli.changeCurrentSigner(0);
+        await testCli.updateDefaultMaxOrderAge(0);
+    });
+
+    it('Pass skip orders not expired', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.placeOrder(limitOrderParams(0));
+        await testCli.placeOrder(limitOrderParams(3600));
+
+        testCli.changeCurrentSigner(2);
+        await testCli.expireOrders(userAuthority);
+
+        const orders = (await testCli.getUserOrders(userAuthority)).orders;
+        requireBNEq(orders[0].orderId, new BN(2));
+        requireBNEq(orders[1].orderId, new BN(3));
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass expire stale orders and reward caller', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.placeOrder(limitOrderParams(1));
+        // 等待订单过期
+        await new Promise((resolve) => setTimeout(resolve, 3000));
+        const userCollateralBefore = (await testCli.getUser(userAuthority)).collateral;
+        const fillerCollateralBefore = (await testCli.getUser(fillerAuthority)).collateral;
+
+        testCli.changeCurrentSigner(2);
+        await testCli.expireOrders(userAuthority);
+
+        const orders = (await testCli.getUserOrders(userAuthority)).orders;
+        requireBNEq(orders[2].orderId, ZERO_BN);
+        requireBNEq(orders[0].orderId, new BN(2));
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].openOrders, new BN(2));
+
+        // 奖励为time_based_reward_lower_bound
+        const reward = new BN(10_000);
+        requireBNEq((await testCli.getUser(userAuthority)).collateral, userCollateralBefore.sub(reward));
+        requireBNEq((await testCli.getUser(fillerAuthority)).collateral, fillerCollateralBefore.add(reward));
+
+        const orderRecord = (await testCli.getOrderHistory()).orderRecords[5];
+        expect(orderRecord.action).deep.eq({ expire: {} });
+        requireBNEq(orderRecord.order.orderId, new BN(4));
+        requireBNEq(orderRecord.fillerReward, reward);
+        testCli.changeCurrentSigner(0);
+    });
+});
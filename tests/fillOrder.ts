@@ -0,0 +1,166 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes } from "@coral-xyz/anchor";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError, requirePublickeyEq, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type OrderParams = IdlTypes<ClearingHouse>['orderParams'];
+
+const MARK_PRICE_PRECISION = new BN(10_000_000_000);
+const AMM_RESERVE_PRECISION = new BN(10_000_000_000_000);
+const QUOTE_PRECISION = new BN(1_000_000);
+
+function limitOrderParams(price: BN, baseAssetAmount = AMM_RESERVE_PRECISION): OrderParams {
+    return {
+        orderType: { limit: {} },
+        direction: { long: {} },
+        userOrderId: 0,
+        quoteAssetAmount: ZERO_BN,
+        baseAssetAmount,
+        price,
+        marketIndex: ZERO_BN,
+        reduceOnly: false,
+        postOnly: false,
+        immediateOrCancel: false,
+        triggerPrice: ZERO_BN,
+        triggerCondition: { above: {} },
+        triggerPriceSource: { oracle: {} },
+        oraclePriceOffset: ZERO_BN,
+        maxAge: null,
+    };
+}
+
+describe("clearing house: deposit_collateral && fill_order", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+    let oracle: anchor.web3.PublicKey;
+    let userAuthority: anchor.web3.PublicKey;
+    let fillerAuthority: anchor.web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 3);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+
+        oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+        await testCli.initializeMarket(ZERO_BN, oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        userAuthority = testCli.signers[1].publicKey;
+        fillerAuthority = testCli.signers[2].publicKey;
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail deposit zero collateral', async () => {
+        const userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority);
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(testCli.depositCollateral(ZERO_BN, userCollateralAccount), 'InsufficientDeposit');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass deposit collateral', async () => {
+        const amount = QUOTE_PRECISION.muln(1000);
+        const userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority, anchor.web3.Keypair.generate());
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, userCollateralAccount, testCli.signers[0], BigInt(amount.toString()));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.depositCollateral(amount, userCollateralAccount);
+
+        const user = await testCli.getUser(userAuthority);
+        requireBNEq(user.collateral, amount);
+        requireBNEq(user.cumulativeDeposits, amount);
+        const record = (await testCli.getDepositHistory()).depositRecords[0];
+        requireBNEq(record.amount, amount);
+        expect(record.direction).deep.eq({ deposit: {} });
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if filler is user self', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.placeOrder(limitOrderParams(MARK_PRICE_PRECISION.muln(51)));
+        await requireCustomError(testCli.fillOrder(userAuthority, new BN(1), oracle), 'InvalidFiller');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if referrer is filler', async () => {
+        testCli.changeCurrentSigner(2);
+        const filler = testCli.getUserAddress(fillerAuthority);
+        await requireCustomError(testCli.fillOrder(userAuthority, new BN(1), oracle, filler), 'InvalidReferrer');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if oracle price is invalid', async () => {
+        // oracle价格与market记录的oracle TWAP（50）之比超过too_volatile_ratio（5）
+        await testCli.setPrice(pythProgram, oracle, new BN(300_000_000));
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(testCli.fillOrder(userAuthority, new BN(1), oracle), 'InvalidOracle');
+        testCli.changeCurrentSigner(0);
+        await testCli.setPrice(pythProgram, oracle, new BN(50_000_000));
+    });
+
+    it('Pass fill limit order', async () => {
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(1), oracle);
+
+        // 完全成交后订单被移除
+        const orders = (await testCli.getUserOrders(userAuthority)).orders;
+        requireBNEq(orders[0].orderId, ZERO_BN);
+        const position = (await testCli.getUserPositions(userAuthority)).positions[0];
+        requireBNEq(position.baseAssetAmount, AMM_RESERVE_PRECISION);
+        requireBNEq(position.openOrders, ZERO_BN);
+
+        const tradeRecord = (await testCli.getTradeHistory()).tradeRecord[0];
+        requireBNEq(tradeRecord.baseAssetAmount, AMM_RESERVE_PRECISION);
+        requirePublickeyEq(tradeRecord.user, testCli.getUserAddress(userAuthority));
+
+        const orderRecord = (await testCli.getOrderHistory()).orderRecords[1];
+        expect(orderRecord.action).deep.eq({ fill: {} });
+        requireBNEq(orderRecord.tradeRecordId, tradeRecord.recordId);
+        requirePublickeyEq(orderRecord.filler, testCli.getUserAddress(fillerAuthority));
+        requireBNEq(orderRecord.baseAssetAmountFilled, AMM_RESERVE_PRECISION);
+
+        // 手续费约为0.05，filler奖励取time_based_reward_lower_bound（0.01）
+        requireBNEq(orderRecord.fillerReward, new BN(10_000));
+        requireBNEq((await testCli.getUser(fillerAuthority)).collateral, new BN(10_000));
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if limit price not reachable', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.placeOrder(limitOrderParams(MARK_PRICE_PRECISION.muln(49)));
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(testCli.fillOrder(userAuthority, new BN(2), oracle), 'CouldNotFillOrder');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass partially fill limit order up to limit price', async () => {
+        testCli.changeCurrentSigner(1);
+        // 把价格推到50.00005只需要成交约1.5个base，剩余部分继续挂单
+        const baseAssetAmount = AMM_RESERVE_PRECISION.muln(10);
+        await testCli.placeOrder(limitOrderParams(new BN(500_000_500_000), baseAssetAmount));
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(3), oracle);
+
+        const order = (await testCli.getUserOrders(userAuthority)).orders.find(order => order.orderId.eqn(3));
+        expect(order.baseAssetAmountFilled.gt(ZERO_BN)).eq(true);
+        expect(order.baseAssetAmountFilled.lt(baseAssetAmount)).eq(true);
+        testCli.changeCurrentSigner(0);
+    });
+});
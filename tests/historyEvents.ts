@@ -0,0 +1,105 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlEvents } from "@coral-xyz/anchor";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { ClearingHouse } from "../target/types/clearing_house";
+import { requireBNEq, requireCustomError, requirePublickeyEq, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type DepositRecordEvent = IdlEvents<ClearingHouse>['depositRecordEvent'];
+
+const QUOTE_PRECISION = new BN(1_000_000);
+
+describe("clearing house: history record events", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+
+    let testCli: TestClient;
+    let userAuthority: anchor.web3.PublicKey;
+    let userCollateralAccount: anchor.web3.PublicKey;
+    let listener: number;
+    const events: DepositRecordEvent[] = [];
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+
+        userAuthority = testCli.signers[1].publicKey;
+        userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority);
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, userCollateralAccount, testCli.signers[0], BigInt(QUOTE_PRECISION.muln(1000).toString()));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        testCli.changeCurrentSigner(0);
+
+        listener = program.addEventListener('depositRecordEvent', (event) => events.push(event));
+    });
+
+    after(async () => {
+        await program.removeEventListener(listener);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.updateHistoryRingBuffersDisabled(true),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass emit event and write ring buffer', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.depositCollateral(QUOTE_PRECISION.muln(100), userCollateralAccount);
+        testCli.changeCurrentSigner(0);
+        // 等待event被监听到
+        await new Promise((resolve) => setTimeout(resolve, 2000));
+
+        expect(events.length).eq(1);
+        requireBNEq(events[0].record.recordId, new BN(1));
+        requireBNEq(events[0].record.amount, QUOTE_PRECISION.muln(100));
+        requirePublickeyEq(events[0].record.userAuthority, userAuthority);
+
+        const depositHistory = await testCli.getDepositHistory();
+        requireBNEq(depositHistory.head, new BN(1));
+        requireBNEq(depositHistory.lastRecordId, new BN(1));
+        requireBNEq(depositHistory.depositRecords[0].recordId, new BN(1));
+    });
+
+    it('Pass only emit event if ring buffers disabled', async () => {
+        await testCli.updateHistoryRingBuffersDisabled(true);
+        expect((await testCli.getState()).historyRingBuffersDisabled).eq(1);
+
+        testCli.changeCurrentSigner(1);
+        await testCli.depositCollateral(QUOTE_PRECISION.muln(200), userCollateralAccount);
+        testCli.changeCurrentSigner(0);
+        await new Promise((resolve) => setTimeout(resolve, 2000));
+
+        expect(events.length).eq(2);
+        requireBNEq(events[1].record.recordId, new BN(2));
+        requireBNEq(events[1].record.amount, QUOTE_PRECISION.muln(200));
+
+        // ring buffer中没有写入新的记录，但record_id仍然连续
+        const depositHistory = await testCli.getDepositHistory();
+        requireBNEq(depositHistory.head, new BN(1));
+        requireBNEq(depositHistory.lastRecordId, new BN(2));
+        requireBNEq(depositHistory.depositRecords[1].recordId, ZERO_BN);
+    });
+
+    it('Pass resume ring buffers', async () => {
+        await testCli.updateHistoryRingBuffersDisabled(false);
+
+        testCli.changeCurrentSigner(1);
+        await testCli.depositCollateral(QUOTE_PRECISION.muln(300), userCollateralAccount);
+        testCli.changeCurrentSigner(0);
+
+        const depositHistory = await testCli.getDepositHistory();
+        requireBNEq(depositHistory.head, new BN(2));
+        requireBNEq(depositHistory.depositRecords[1].recordId, new BN(3));
+    });
+});
@@ -1,9 +1,9 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program, web3 } from "@coral-xyz/anchor";
 import { ClearingHouse } from "../target/types/clearing_house";
-import { createAccounts, requireCustomError, requireNativeError } from "./utils";
+import { requireCustomError, requireNativeError } from "./utils";
 import { expect } from "chai";
-import { TestClient } from "./testClient";
+import { MARKETS_ACCOUNT_SIZE, TestClient } from "./testClient";
 
 describe("clearing house: initialize", () => {
     const provider = anchor.AnchorProvider.env();
@@ -14,7 +14,7 @@ describe("clearing house: initialize", () => {
     let testCli: TestClient;
 
     before(async () => {
-        testCli = await TestClient.create(provider, program, 1);
+        testCli = await TestClient.create(provider, program, 2);
         await testCli.initializeRelevantAccounts(9, true);
     });
 
@@ -22,7 +22,7 @@ describe("clearing house: initialize", () => {
         const signer = testCli.getCurrentSigner();
         await requireCustomError(
             program.methods.initialize(true)
-                .accounts({
+                .accountsPartial({
                     admin: signer.publicKey,
                     state: testCli.state,
                     collateralMint: testCli.collateralMint,
@@ -40,7 +40,7 @@ describe("clearing house: initialize", () => {
         const signer = testCli.getCurrentSigner();
         await requireCustomError(
             program.methods.initialize(true)
-                .accounts({
+                .accountsPartial({
                     admin: signer.publicKey,
                     state: testCli.state,
                     collateralMint: testCli.collateralMint,
@@ -59,34 +59,48 @@ describe("clearing house: initialize", () => {
         // check state
         const state = await testCli.getState();
         expect(state.adminControlsPrices).eq(1);
+        expect(state.version).eq(1);
+        // markets在initialize之后已通过resize_markets扩容到完整大小
+        expect((await provider.connection.getAccountInfo(testCli.markets)).data.length).eq(MARKETS_ACCOUNT_SIZE);
         const markets = await testCli.getMarkets();
         expect(markets.markets.length).eq(64);
+        expect(markets.version).eq(1);
     });
 
-    it('Fail if initialize again with another state and markets', async () => {
-        const [otherState, otherMarkets] = await createAccounts(
-            provider,
-            [8 + 1200, 8 + 31744],
-            program.programId
+    it('Fail resize markets if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.resizeMarkets(),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail resize markets if already initialized', async () => {
+        await requireCustomError(
+            testCli.resizeMarkets(),
+            'MarketsAlreadyInitialized'
         );
+    });
 
+    it('Fail if initialize again', async () => {
         const signer = testCli.getCurrentSigner();
         await requireNativeError(
             program.methods.initialize(true)
-                .accounts({
+                .accountsPartial({
                     admin: signer.publicKey,
-                    state: otherState,
+                    state: testCli.state,
                     collateralMint: testCli.collateralMint,
                     collateralVaultAuthority: testCli.collateralVaultAuthority,
                     insuranceVaultAuthority: testCli.insuranceVaultAuthority,
-                    markets: otherMarkets
+                    markets: testCli.markets
                 })
                 .signers([signer])
                 .rpc(),
             'Transaction simulation failed: Error processing Instruction 0: custom program error: 0x0',
             [3, 4],
             [
-                `Allocate: account Address { address: ${testCli.collateralVault}, base: None } already in use`,
+                `Allocate: account Address { address: ${testCli.state}, base: None } already in use`,
                 'Program 11111111111111111111111111111111 failed: custom program error: 0x0'
             ]
         );
@@ -79,15 +79,60 @@ describe("clearing house: initialize_history", () => {
             program.programId
         );
         const signer = testCli.getCurrentSigner();
+        const accounts = {
+            admin: signer.publicKey,
+            state: testCli.state,
+            fundingPaymentHistory: newFundingPaymentHistory,
+            tradeHistory: newTradeHistory,
+            liquidationHistory: newLiquidationHistory,
+            depositHistory: newDepositHistory,
+            fundingRateHistory: newFundingRateHistory,
+            curveHistory: newCurveHistory,
+        };
+        await requireCustomError(
+            program.methods.initializeHistory()
+                .accounts(accounts as any)
+                .signers([signer])
+                .rpc(),
+            'HistoriesAllInitialized'
+        );
+
+        // 已废弃的intialize_history入口同样不能重复初始化
         await requireCustomError(
             program.methods.intializeHistory()
+                .accounts(accounts as any)
+                .signers([signer])
+                .rpc(),
+            'HistoriesAllInitialized'
+        );
+
+        const state = await testCli.getState();
+        requirePublickeyEq(state.tradeHistory, testCli.tradeHistory);
+        requirePublickeyEq(state.curveHistory, testCli.curveHistory);
+    });
+
+    it('Fail if any history already set', async () => {
+        // 轮换deposit history之后，其余history账户不变，仍然不能重新初始化
+        const [newDepositHistory] = await createAccounts(provider, [8 + 147472], program.programId);
+        await testCli.rotateHistory({ deposit: {} }, testCli.depositHistory, newDepositHistory);
+        testCli.depositHistory = newDepositHistory;
+
+        const [newTradeHistory, newLiquidationHistory, newFundingPaymentHistory, newFundingRateHistory, newCurveHistory] = await createAccounts(
+            provider,
+            [8 + 262160, 8 + 262160, 8 + 196624, 8 + 114704, 8 + 311312],
+            program.programId
+        );
+        const [anotherDepositHistory] = await createAccounts(provider, [8 + 147472], program.programId);
+        const signer = testCli.getCurrentSigner();
+        await requireCustomError(
+            program.methods.initializeHistory()
                 .accounts({
                     admin: signer.publicKey,
                     state: testCli.state,
                     fundingPaymentHistory: newFundingPaymentHistory,
                     tradeHistory: newTradeHistory,
                     liquidationHistory: newLiquidationHistory,
-                    depositHistory: newDepositHistory,
+                    depositHistory: anotherDepositHistory,
                     fundingRateHistory: newFundingRateHistory,
                     curveHistory: newCurveHistory,
                 } as any)
@@ -96,4 +141,51 @@ describe("clearing house: initialize_history", () => {
             'HistoriesAllInitialized'
         );
     });
-});
\ No newline at end of file
+});
+
+describe("clearing house: intialize_history (deprecated alias)", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+
+    let testCli: TestClient;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 1);
+        await testCli.initializeRelevantAccounts(9);
+        await testCli.initializeHistoriesAccounts();
+        await testCli.initialize(true);
+    });
+
+    it('Pass initialize history by deprecated alias', async () => {
+        const signer = testCli.getCurrentSigner();
+        await program.methods.intializeHistory()
+            .accounts({
+                admin: signer.publicKey,
+                state: testCli.state,
+                fundingPaymentHistory: testCli.fundingPaymentHistory,
+                tradeHistory: testCli.tradeHistory,
+                liquidationHistory: testCli.liquidationHistory,
+                depositHistory: testCli.depositHistory,
+                fundingRateHistory: testCli.fundingRateHistory,
+                curveHistory: testCli.curveHistory,
+            } as any)
+            .signers([signer])
+            .rpc();
+
+        const state = await testCli.getState();
+        requirePublickeyEq(state.tradeHistory, testCli.tradeHistory);
+        requirePublickeyEq(state.depositHistory, testCli.depositHistory);
+        requirePublickeyEq(state.curveHistory, testCli.curveHistory);
+    });
+
+    it('Fail if reinitialize by initialize_history', async () => {
+        // 使用新的空账户，确保是指令本身而不是#[account(zero)]拒绝了重复初始化
+        await testCli.initializeHistoriesAccounts();
+        await requireCustomError(
+            testCli.initializeHistory(),
+            'HistoriesAllInitialized'
+        );
+    });
+});
@@ -0,0 +1,88 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN } from "@coral-xyz/anchor";
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError, requirePublickeyEq } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+describe("clearing house: initialize_market", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    // 储备量相等，mark price = peg_multiplier / PEG_PRECISION = 50
+    const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+    const ammPegMultiplier = new BN(50_000);
+    const ammPeriodicity = new BN(3600);
+
+    let testCli: TestClient;
+    let oracle: anchor.web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(9, true);
+        await testCli.initialize(true);
+        oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.initializeMarket(new BN(0), oracle, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail with unequal reserves', async () => {
+        await requireCustomError(
+            testCli.initializeMarket(new BN(0), oracle, ammReserve, ammReserve.addn(1), ammPeriodicity, ammPegMultiplier),
+            'InvalidInitialPeg'
+        );
+    });
+
+    it('Fail with market index out of range', async () => {
+        await requireCustomError(
+            testCli.initializeMarket(new BN(64), oracle, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier),
+            'MarketIndexOutOfRange'
+        );
+    });
+
+    it('Fail with invalid oracle', async () => {
+        // oracle价格必须为正
+        const invalidOracle = await testCli.createPriceFeed(pythProgram, new BN(0), -6, new BN(0));
+        await requireCustomError(
+            testCli.initializeMarket(new BN(0), invalidOracle, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier),
+            'InvalidOracle'
+        );
+    });
+
+    it('Pass initialize market', async () => {
+        await testCli.initializeMarket(new BN(0), oracle, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        const state = await testCli.getState();
+        const market = (await testCli.getMarkets()).markets[0];
+        expect(market.initialized).eq(1);
+        expect(market.status).deep.eq({ active: {} });
+        expect(market.marginRatioInitial).eq(state.marginRatioInitial.toNumber());
+        requireBNEq(market.amm.baseAssetReserve, ammReserve);
+        requireBNEq(market.amm.quoteAssetReserve, ammReserve);
+        requireBNEq(market.amm.sqrtK, ammReserve);
+        requireBNEq(market.amm.pegMultiplier, ammPegMultiplier);
+        requireBNEq(market.amm.fundingPeriod, ammPeriodicity);
+        // 50 * MARK_PRICE_PRECISION
+        requireBNEq(market.amm.lastMarkPriceTwap, new BN(500_000_000_000));
+        requireBNEq(market.amm.lastOraclePrice, new BN(500_000_000_000));
+        requirePublickeyEq(market.amm.oracle, oracle);
+    });
+
+    it('Fail if market already initialized', async () => {
+        await requireCustomError(
+            testCli.initializeMarket(new BN(0), oracle, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier),
+            'MarketIndexAlreadyInitialized'
+        );
+    });
+});
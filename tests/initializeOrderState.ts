@@ -43,6 +43,8 @@ describe("clearing house: initialize_order_state", () => {
         requireBNEq(orderState.orderFillerRewardStructure.rewardDenominator, new BN(10));
         requireBNEq(orderState.orderFillerRewardStructure.timeBasedRewardLowerBound, new BN(10000));
         requireBNEq(orderState.minOrderQuoteAssetAmount, new BN(500000));
+        expect(orderState.defaultMaxOrderAge).eq(0);
+        expect(orderState.version).eq(1);
 
         const orderHistory = await testCli.getOrderHistory();
         requireBNEq(orderHistory.head, ZERO_BN);
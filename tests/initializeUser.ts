@@ -0,0 +1,103 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, web3 } from "@coral-xyz/anchor";
+import { ClearingHouse } from "../target/types/clearing_house";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { requireCustomError, requirePublickeyEq } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+type PublicKey = web3.PublicKey;
+
+describe("clearing house: initialize_user && update_whitelist_mint", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+
+    let testCli: TestClient;
+    let whitelistMint: PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 4);
+        await testCli.initializeRelevantAccounts(9, true);
+        await testCli.initialize(true);
+        whitelistMint = await testCli.createMint(0);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.updateWhitelistMint(whitelistMint),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass initialize user without whitelist', async () => {
+        testCli.changeCurrentSigner(1);
+        const authority = testCli.getCurrentSigner().publicKey;
+        await testCli.initializeUser();
+
+        const user = await testCli.getUser(authority);
+        requirePublickeyEq(user.authority, authority);
+        requirePublickeyEq(user.positions, testCli.getUserPositionsAddress(authority));
+        const userPositions = await testCli.getUserPositions(authority);
+        requirePublickeyEq(userPositions.user, testCli.getUserAddress(authority));
+        expect(userPositions.positions.length).eq(5);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail to initialize user when exchange paused', async () => {
+        await testCli.updateExchangePaused(true);
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(
+            testCli.initializeUser(),
+            'ExchangePaused'
+        );
+        testCli.changeCurrentSigner(0);
+        await testCli.updateExchangePaused(false);
+    });
+
+    it('Pass update whitelist mint', async () => {
+        await testCli.updateWhitelistMint(whitelistMint);
+        requirePublickeyEq((await testCli.getState()).whitelistMint, whitelistMint);
+    });
+
+    it('Fail to initialize user without whitelist token', async () => {
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(
+            testCli.initializeUser(),
+            'WhitelistTokenNotFound'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail to initialize user with empty whitelist token', async () => {
+        const authority = testCli.signers[2];
+        const whitelistToken = await createAccount(provider.connection, authority, whitelistMint, authority.publicKey);
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(
+            testCli.initializeUser(whitelistToken),
+            'InvalidWhitelistToken'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass initialize user with whitelist token', async () => {
+        const authority = testCli.signers[3];
+        const whitelistToken = await createAccount(provider.connection, authority, whitelistMint, authority.publicKey);
+        await mintTo(provider.connection, testCli.signers[0], whitelistMint, whitelistToken, testCli.signers[0], 1);
+
+        testCli.changeCurrentSigner(3);
+        await testCli.initializeUser(whitelistToken);
+        requirePublickeyEq((await testCli.getUser(authority.publicKey)).authority, authority.publicKey);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass disable whitelist with default pubkey', async () => {
+        await testCli.updateWhitelistMint(web3.PublicKey.default);
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+        requirePublickeyEq((await testCli.getUser(testCli.signers[2].publicKey)).authority, testCli.signers[2].publicKey);
+        testCli.changeCurrentSigner(0);
+    });
+});
@@ -0,0 +1,59 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { ClearingHouse } from "../target/types/clearing_house";
+import { requireBNEq, requireCustomError, requirePublickeyEq, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+describe("clearing house: initialize_user_orders && delete_user_orders", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+
+    let testCli: TestClient;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 3);
+        await testCli.initializeRelevantAccounts(9, true);
+        await testCli.initialize(true);
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if user not initialized', async () => {
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(
+            testCli.initializeUserOrders(),
+            'AccountNotInitialized'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass initialize user orders', async () => {
+        testCli.changeCurrentSigner(1);
+        const authority = testCli.getCurrentSigner().publicKey;
+        await testCli.initializeUserOrders();
+
+        const userOrders = await testCli.getUserOrders(authority);
+        requirePublickeyEq(userOrders.user, testCli.getUserAddress(authority));
+        expect(userOrders.orders.length).eq(32);
+        for (const order of userOrders.orders) {
+            requireBNEq(order.orderId, ZERO_BN);
+        }
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass delete empty user orders', async () => {
+        testCli.changeCurrentSigner(1);
+        const authority = testCli.getCurrentSigner().publicKey;
+        await testCli.deleteUserOrders();
+        expect(await provider.connection.getAccountInfo(testCli.getUserOrdersAddress(authority))).eq(null);
+
+        // 删除后可以重新创建
+        await testCli.initializeUserOrders();
+        requirePublickeyEq((await testCli.getUserOrders(authority)).user, testCli.getUserAddress(authority));
+        testCli.changeCurrentSigner(0);
+    });
+});
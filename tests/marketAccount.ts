@@ -0,0 +1,162 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes } from "@coral-xyz/anchor";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError, requirePublickeyEq, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type OrderParams = IdlTypes<ClearingHouse>['orderParams'];
+
+const AMM_RESERVE_PRECISION = new BN(10_000_000_000_000);
+const QUOTE_PRECISION = new BN(1_000_000);
+
+function marketOrderParams(marketIndex: BN, baseAssetAmount: BN): OrderParams {
+    return {
+        orderType: { market: {} },
+        direction: { long: {} },
+        userOrderId: 0,
+        quoteAssetAmount: ZERO_BN,
+        baseAssetAmount,
+        price: ZERO_BN,
+        marketIndex,
+        reduceOnly: false,
+        postOnly: false,
+        immediateOrCancel: false,
+        triggerPrice: ZERO_BN,
+        triggerCondition: { above: {} },
+        triggerPriceSource: { oracle: {} },
+        oraclePriceOffset: ZERO_BN,
+        maxAge: null,
+    };
+}
+
+describe("clearing house: market accounts", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+    let userAuthority: anchor.web3.PublicKey;
+    let oracle: anchor.web3.PublicKey;
+    const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+
+        oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        await testCli.initializeMarket(ZERO_BN, oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        userAuthority = testCli.signers[1].publicKey;
+        const amount = QUOTE_PRECISION.muln(1000);
+        const userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority);
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, userCollateralAccount, testCli.signers[0], BigInt(amount.toString()));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        await testCli.depositCollateral(amount, userCollateralAccount);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.migrateMarketToAccount(ZERO_BN),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.initializeMarketAccount(new BN(64), oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000)),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail migrate uninitialized market', async () => {
+        await requireCustomError(
+            testCli.migrateMarketToAccount(new BN(1)),
+            'MarketIndexNotInitialized'
+        );
+    });
+
+    it('Pass migrate market to market account', async () => {
+        const slotBefore = (await testCli.getMarkets()).markets[0];
+        await testCli.migrateMarketToAccount(ZERO_BN);
+
+        const marketAccount = await testCli.getMarketAccount(ZERO_BN);
+        requireBNEq(marketAccount.marketIndex, ZERO_BN);
+        expect(marketAccount.version).eq(1);
+        expect(marketAccount.market.initialized).eq(1);
+        expect(marketAccount.market.migrated).eq(0);
+        requirePublickeyEq(marketAccount.market.amm.oracle, slotBefore.amm.oracle);
+        requireBNEq(marketAccount.market.amm.baseAssetReserve, slotBefore.amm.baseAssetReserve);
+        requireBNEq(marketAccount.market.amm.pegMultiplier, slotBefore.amm.pegMultiplier);
+
+        expect((await testCli.getMarkets()).markets[0].migrated).eq(1);
+    });
+
+    it('Fail use migrated slot in markets', async () => {
+        const signer = testCli.getCurrentSigner();
+        await requireCustomError(
+            program.methods.updateMarketStatus(ZERO_BN, { reduceOnly: {} })
+                .accounts({
+                    admin: signer.publicKey,
+                    state: testCli.state,
+                    markets: testCli.markets,
+                } as any)
+                .signers([signer])
+                .rpc(),
+            'MarketMigrated'
+        );
+    });
+
+    it('Pass update market through market account', async () => {
+        await testCli.updateMarketStatus(ZERO_BN, { reduceOnly: {} });
+        expect((await testCli.getMarketAccount(ZERO_BN)).market.status).deep.eq({ reduceOnly: {} });
+        await testCli.updateMarketStatus(ZERO_BN, { active: {} });
+        expect((await testCli.getMarketAccount(ZERO_BN)).market.status).deep.eq({ active: {} });
+    });
+
+    it('Pass place and fill order without markets', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.placeAndFillOrder(marketOrderParams(ZERO_BN, AMM_RESERVE_PRECISION));
+        testCli.changeCurrentSigner(0);
+
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].baseAssetAmount, AMM_RESERVE_PRECISION);
+        const market = (await testCli.getMarketAccount(ZERO_BN)).market;
+        requireBNEq(market.baseAssetAmountLong, AMM_RESERVE_PRECISION);
+        requireBNEq(market.openInterest, new BN(1));
+        // markets中的旧数据不再更新
+        requireBNEq((await testCli.getMarkets()).markets[0].baseAssetAmountLong, ZERO_BN);
+    });
+
+    it('Fail initialize market account inside markets slots', async () => {
+        await requireCustomError(
+            testCli.initializeMarketAccount(new BN(1), oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000)),
+            'InvalidMarketAccountIndex'
+        );
+    });
+
+    it('Pass initialize market account beyond 64 markets', async () => {
+        const marketIndex = new BN(64);
+        await testCli.initializeMarketAccount(marketIndex, oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        const marketAccount = await testCli.getMarketAccount(marketIndex);
+        requireBNEq(marketAccount.marketIndex, marketIndex);
+        expect(marketAccount.market.initialized).eq(1);
+        requirePublickeyEq(marketAccount.market.amm.oracle, oracle);
+
+        testCli.changeCurrentSigner(1);
+        await testCli.placeAndFillOrder(marketOrderParams(marketIndex, AMM_RESERVE_PRECISION));
+        testCli.changeCurrentSigner(0);
+        requireBNEq((await testCli.getMarketAccount(marketIndex)).market.baseAssetAmountLong, AMM_RESERVE_PRECISION);
+    });
+});
@@ -0,0 +1,65 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { ClearingHouse } from "../target/types/clearing_house";
+import { requireCustomError } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+describe("clearing house: migrate_state && migrate_markets && migrate_order_state", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+
+    let testCli: TestClient;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeOrderState();
+    });
+
+    it('Pass new accounts are created at the current version', async () => {
+        expect((await testCli.getState()).version).eq(1);
+        expect((await testCli.getMarkets()).version).eq(1);
+        expect((await testCli.getOrderState()).version).eq(1);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.migrateState(),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.migrateMarkets(),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.migrateOrderState(),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if already migrated', async () => {
+        await requireCustomError(
+            testCli.migrateState(),
+            'AccountAlreadyMigrated'
+        );
+        await requireCustomError(
+            testCli.migrateMarkets(),
+            'AccountAlreadyMigrated'
+        );
+        await requireCustomError(
+            testCli.migrateOrderState(),
+            'AccountAlreadyMigrated'
+        );
+
+        // 迁移失败不会改变账户大小
+        expect((await provider.connection.getAccountInfo(testCli.state)).data.length).eq(8 + 1200);
+        expect((await provider.connection.getAccountInfo(testCli.orderState)).data.length).eq(8 + 112);
+    });
+});
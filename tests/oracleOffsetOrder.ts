@@ -0,0 +1,145 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes } from "@coral-xyz/anchor";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type OrderParams = IdlTypes<ClearingHouse>['orderParams'];
+
+const MARK_PRICE_PRECISION = new BN(10_000_000_000);
+const AMM_RESERVE_PRECISION = new BN(10_000_000_000_000);
+const QUOTE_PRECISION = new BN(1_000_000);
+
+function oracleOffsetOrderParams(direction: IdlTypes<ClearingHouse>['positionDirection'], oraclePriceOffset: BN): OrderParams {
+    return {
+        orderType: { limit: {} },
+        direction,
+        userOrderId: 0,
+        quoteAssetAmount: ZERO_BN,
+        baseAssetAmount: AMM_RESERVE_PRECISION,
+        price: ZERO_BN,
+        marketIndex: ZERO_BN,
+        reduceOnly: false,
+        postOnly: false,
+        immediateOrCancel: false,
+        triggerPrice: ZERO_BN,
+        triggerCondition: { above: {} },
+        triggerPriceSource: { oracle: {} },
+        oraclePriceOffset,
+        maxAge: null,
+    };
+}
+
+describe("clearing house: oracle price offset orders", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+    let oracle: anchor.web3.PublicKey;
+    let userAuthority: anchor.web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 3);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+
+        oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+        await testCli.initializeMarket(ZERO_BN, oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        userAuthority = testCli.signers[1].publicKey;
+        const amount = QUOTE_PRECISION.muln(1000);
+        const userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority);
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, userCollateralAccount, testCli.signers[0], BigInt(amount.toString()));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        await testCli.depositCollateral(amount, userCollateralAccount);
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if both price and oracle price offset set', async () => {
+        testCli.changeCurrentSigner(1);
+        const params = oracleOffsetOrderParams({ long: {} }, MARK_PRICE_PRECISION);
+        params.price = MARK_PRICE_PRECISION.muln(50);
+        await requireCustomError(testCli.placeOrder(params), 'InvalidOrder');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if oracle price offset used by non limit order', async () => {
+        testCli.changeCurrentSigner(1);
+        const params = oracleOffsetOrderParams({ long: {} }, MARK_PRICE_PRECISION);
+        params.orderType = { market: {} };
+        await requireCustomError(testCli.placeOrder(params), 'InvalidOrder');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if post only offset on the taker side of oracle', async () => {
+        testCli.changeCurrentSigner(1);
+        const params = oracleOffsetOrderParams({ long: {} }, MARK_PRICE_PRECISION);
+        params.postOnly = true;
+        await requireCustomError(testCli.placeOrder(params), 'InvalidOraclePriceOffset');
+
+        params.direction = { short: {} };
+        params.oraclePriceOffset = MARK_PRICE_PRECISION.neg();
+        await requireCustomError(testCli.placeOrder(params), 'InvalidOraclePriceOffset');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if resolved limit price not positive', async () => {
+        testCli.changeCurrentSigner(1);
+        // oracle价格为50，偏移-60后的限价为负数（非post only订单同样校验）
+        const params = oracleOffsetOrderParams({ long: {} }, MARK_PRICE_PRECISION.muln(60).neg());
+        await requireCustomError(testCli.placeOrder(params), 'InvalidOraclePriceOffset');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass fill oracle offset order at resolved price', async () => {
+        testCli.changeCurrentSigner(1);
+        // 限价为oracle价格（50）加上1
+        await testCli.placeOrder(oracleOffsetOrderParams({ long: {} }, MARK_PRICE_PRECISION));
+        const order = (await testCli.getUserOrders(userAuthority)).orders[0];
+        requireBNEq(order.price, ZERO_BN);
+        requireBNEq(order.oraclePriceOffset, MARK_PRICE_PRECISION);
+
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(1), oracle);
+
+        const orderRecord = (await testCli.getOrderHistory()).orderRecords[1];
+        expect(orderRecord.action).deep.eq({ fill: {} });
+        requireBNEq(orderRecord.order.price, MARK_PRICE_PRECISION.muln(51));
+        requireBNEq(orderRecord.baseAssetAmountFilled, AMM_RESERVE_PRECISION);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass non post only offset order on either side of oracle', async () => {
+        testCli.changeCurrentSigner(1);
+        // maker一侧的偏移订单挂单等待成交
+        const params = oracleOffsetOrderParams({ long: {} }, MARK_PRICE_PRECISION.neg());
+        params.userOrderId = 1;
+        await testCli.placeOrder(params);
+        const order = (await testCli.getUserOrders(userAuthority)).orders.find((order) => order.userOrderId == 1);
+        expect(order.status).deep.eq({ open: {} });
+        requireBNEq(order.oraclePriceOffset, MARK_PRICE_PRECISION.neg());
+
+        // taker一侧的偏移订单作为可立即成交的订单，oracle价格加偏移量作为限价
+        const takerParams = oracleOffsetOrderParams({ short: {} }, MARK_PRICE_PRECISION.neg());
+        takerParams.userOrderId = 2;
+        await testCli.placeOrder(takerParams);
+        await testCli.cancelOrderByUserOrderId(1);
+        await testCli.cancelOrderByUserOrderId(2);
+        testCli.changeCurrentSigner(0);
+    });
+});
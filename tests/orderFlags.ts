@@ -0,0 +1,156 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes } from "@coral-xyz/anchor";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type OrderParams = IdlTypes<ClearingHouse>['orderParams'];
+
+const MARK_PRICE_PRECISION = new BN(10_000_000_000);
+const AMM_RESERVE_PRECISION = new BN(10_000_000_000_000);
+const QUOTE_PRECISION = new BN(1_000_000);
+
+function orderParams(
+    orderType: IdlTypes<ClearingHouse>['orderType'],
+    direction: IdlTypes<ClearingHouse>['positionDirection'],
+    baseAssetAmount: BN,
+    price: BN,
+): OrderParams {
+    return {
+        orderType,
+        direction,
+        userOrderId: 0,
+        quoteAssetAmount: ZERO_BN,
+        baseAssetAmount,
+        price,
+        marketIndex: ZERO_BN,
+        reduceOnly: false,
+        postOnly: false,
+        immediateOrCancel: false,
+        triggerPrice: ZERO_BN,
+        triggerCondition: { above: {} },
+        triggerPriceSource: { oracle: {} },
+        oraclePriceOffset: ZERO_BN,
+        maxAge: null,
+    };
+}
+
+describe("clearing house: reduce only, post only and immediate or cancel orders", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+    let oracle: anchor.web3.PublicKey;
+    let userAuthority: anchor.web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 3);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+
+        oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+        await testCli.initializeMarket(ZERO_BN, oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        userAuthority = testCli.signers[1].publicKey;
+        const amount = QUOTE_PRECISION.muln(1000);
+        const userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority);
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, userCollateralAccount, testCli.signers[0], BigInt(amount.toString()));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        await testCli.depositCollateral(amount, userCollateralAccount);
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if post only order crosses the AMM', async () => {
+        testCli.changeCurrentSigner(1);
+        const params = orderParams({ limit: {} }, { long: {} }, AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION.muln(51));
+        params.postOnly = true;
+        await requireCustomError(testCli.placeOrder(params), 'PostOnlyOrderWouldCross');
+
+        params.direction = { short: {} };
+        params.price = MARK_PRICE_PRECISION.muln(49);
+        await requireCustomError(testCli.placeOrder(params), 'PostOnlyOrderWouldCross');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass place post only order resting away from the AMM', async () => {
+        testCli.changeCurrentSigner(1);
+        const params = orderParams({ limit: {} }, { long: {} }, AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION.muln(49));
+        params.postOnly = true;
+        await testCli.placeOrder(params);
+        expect((await testCli.getUserOrders(userAuthority)).orders[0].postOnly).eq(1);
+
+        await testCli.cancelOrder(new BN(1));
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass cancel reduce only order without position on fill', async () => {
+        testCli.changeCurrentSigner(1);
+        const params = orderParams({ market: {} }, { long: {} }, AMM_RESERVE_PRECISION, ZERO_BN);
+        params.reduceOnly = true;
+        await testCli.placeOrder(params);
+
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(2), oracle);
+
+        const orderRecords = (await testCli.getOrderHistory()).orderRecords;
+        expect(orderRecords[3].action).deep.eq({ cancel: {} });
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].baseAssetAmount, ZERO_BN);
+        requireBNEq((await testCli.getUserOrders(userAuthority)).orders[0].orderId, ZERO_BN);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass clamp reduce only order to the position size', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.placeOrder(orderParams({ market: {} }, { long: {} }, AMM_RESERVE_PRECISION, ZERO_BN));
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(3), oracle);
+
+        // 卖出两倍头寸数量，只会成交头寸大小，剩余部分被取消
+        testCli.changeCurrentSigner(1);
+        const params = orderParams({ market: {} }, { short: {} }, AMM_RESERVE_PRECISION.muln(2), ZERO_BN);
+        params.reduceOnly = true;
+        await testCli.placeOrder(params);
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(4), oracle);
+
+        const orderRecords = (await testCli.getOrderHistory()).orderRecords;
+        expect(orderRecords[7].action).deep.eq({ fill: {} });
+        requireBNEq(orderRecords[7].baseAssetAmountFilled, AMM_RESERVE_PRECISION);
+        expect(orderRecords[8].action).deep.eq({ cancel: {} });
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].baseAssetAmount, ZERO_BN);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass cancel unfilled remainder of immediate or cancel order', async () => {
+        testCli.changeCurrentSigner(1);
+        // 限价略高于mark price，只有一部分能成交
+        const params = orderParams({ limit: {} }, { long: {} }, AMM_RESERVE_PRECISION.muln(100), new BN(500_000_500_000));
+        params.immediateOrCancel = true;
+        await testCli.placeOrder(params);
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(5), oracle);
+
+        const orderRecords = (await testCli.getOrderHistory()).orderRecords;
+        expect(orderRecords[10].action).deep.eq({ fill: {} });
+        expect(orderRecords[11].action).deep.eq({ cancel: {} });
+        expect(orderRecords[10].baseAssetAmountFilled.lt(AMM_RESERVE_PRECISION.muln(100))).true;
+        requireBNEq((await testCli.getUserOrders(userAuthority)).orders[0].orderId, ZERO_BN);
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].openOrders, ZERO_BN);
+        testCli.changeCurrentSigner(0);
+    });
+});
@@ -0,0 +1,156 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes } from "@coral-xyz/anchor";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError, requirePublickeyEq, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type OrderParams = IdlTypes<ClearingHouse>['orderParams'];
+
+const MARK_PRICE_PRECISION = new BN(10_000_000_000);
+const AMM_RESERVE_PRECISION = new BN(10_000_000_000_000);
+const QUOTE_PRECISION = new BN(1_000_000);
+
+function marketOrderParams(direction: IdlTypes<ClearingHouse>['positionDirection'], baseAssetAmount: BN, price: BN): OrderParams {
+    return {
+        orderType: { market: {} },
+        direction,
+        userOrderId: 0,
+        quoteAssetAmount: ZERO_BN,
+        baseAssetAmount,
+        price,
+        marketIndex: ZERO_BN,
+        reduceOnly: false,
+        postOnly: false,
+        immediateOrCancel: false,
+        triggerPrice: ZERO_BN,
+        triggerCondition: { above: {} },
+        triggerPriceSource: { oracle: {} },
+        oraclePriceOffset: ZERO_BN,
+        maxAge: null,
+    };
+}
+
+describe("clearing house: place and fill order", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+    let userAuthority: anchor.web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+
+        const oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+        await testCli.initializeMarket(ZERO_BN, oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        userAuthority = testCli.signers[1].publicKey;
+        const amount = QUOTE_PRECISION.muln(1000);
+        const userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority);
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, userCollateralAccount, testCli.signers[0], BigInt(amount.toString()));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        await testCli.depositCollateral(amount, userCollateralAccount);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass place and fill market order in one instruction', async () => {
+        testCli.changeCurrentSigner(1);
+        const collateralBefore = (await testCli.getUser(userAuthority)).collateral;
+        await testCli.placeAndFillOrder(marketOrderParams({ long: {} }, AMM_RESERVE_PRECISION, ZERO_BN));
+
+        const orderRecords = (await testCli.getOrderHistory()).orderRecords;
+        expect(orderRecords[0].action).deep.eq({ place: {} });
+        expect(orderRecords[1].action).deep.eq({ fill: {} });
+        requireBNEq(orderRecords[1].baseAssetAmountFilled, AMM_RESERVE_PRECISION);
+        // 用户作为自己的filler，没有filler奖励
+        requirePublickeyEq(orderRecords[1].filler, testCli.getUserAddress(userAuthority));
+        requireBNEq(orderRecords[1].fillerReward, ZERO_BN);
+        requireBNEq(orderRecords[2].recordId, ZERO_BN);
+
+        const user = await testCli.getUser(userAuthority);
+        requireBNEq(user.collateral, collateralBefore.sub(orderRecords[1].fee));
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].baseAssetAmount, AMM_RESERVE_PRECISION);
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].openOrders, ZERO_BN);
+        requireBNEq((await testCli.getUserOrders(userAuthority)).orders[0].orderId, ZERO_BN);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass cancel leftover beyond the slippage limit', async () => {
+        testCli.changeCurrentSigner(1);
+        const markPriceLimit = new BN(500_010_000_000); // 50.001
+        await testCli.placeAndFillOrder(marketOrderParams({ long: {} }, AMM_RESERVE_PRECISION.muln(100), markPriceLimit));
+
+        const orderRecords = (await testCli.getOrderHistory()).orderRecords;
+        expect(orderRecords[2].action).deep.eq({ place: {} });
+        expect(orderRecords[3].action).deep.eq({ fill: {} });
+        expect(orderRecords[4].action).deep.eq({ cancel: {} });
+        expect(orderRecords[3].baseAssetAmountFilled.lt(AMM_RESERVE_PRECISION.muln(100))).true;
+
+        const markets = await testCli.getMarkets();
+        const amm = markets.markets[0].amm;
+        const markPrice = amm.quoteAssetReserve.mul(amm.pegMultiplier).mul(new BN(10_000_000)).div(amm.baseAssetReserve);
+        expect(markPrice.lte(markPriceLimit)).true;
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].openOrders, ZERO_BN);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass cancel whole order if nothing can be filled within the slippage limit', async () => {
+        testCli.changeCurrentSigner(1);
+        const positionBefore = (await testCli.getUserPositions(userAuthority)).positions[0];
+        await testCli.placeAndFillOrder(marketOrderParams({ long: {} }, AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION.muln(49)));
+
+        // 只有Place与Cancel两条记录，没有成交
+        const orderRecords = (await testCli.getOrderHistory()).orderRecords;
+        expect(orderRecords[5].action).deep.eq({ place: {} });
+        expect(orderRecords[6].action).deep.eq({ cancel: {} });
+        requireBNEq(orderRecords[6].order.orderId, orderRecords[5].order.orderId);
+
+        const positionAfter = (await testCli.getUserPositions(userAuthority)).positions[0];
+        requireBNEq(positionAfter.baseAssetAmount, positionBefore.baseAssetAmount);
+        requireBNEq(positionAfter.openOrders, ZERO_BN);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass place and fill IOC limit order', async () => {
+        testCli.changeCurrentSigner(1);
+        const params = marketOrderParams({ long: {} }, AMM_RESERVE_PRECISION.muln(100), new BN(500_020_000_000)); // 50.002
+        params.orderType = { limit: {} };
+        params.immediateOrCancel = true;
+        await testCli.placeAndFillOrder(params);
+
+        // 成交到限价为止，剩余部分被取消，不会留在订单簿上
+        const orderRecords = (await testCli.getOrderHistory()).orderRecords;
+        expect(orderRecords[7].action).deep.eq({ place: {} });
+        expect(orderRecords[8].action).deep.eq({ fill: {} });
+        expect(orderRecords[9].action).deep.eq({ cancel: {} });
+        expect(orderRecords[8].baseAssetAmountFilled.gt(ZERO_BN)).true;
+        expect(orderRecords[8].baseAssetAmountFilled.lt(AMM_RESERVE_PRECISION.muln(100))).true;
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].openOrders, ZERO_BN);
+        requireBNEq((await testCli.getUserOrders(userAuthority)).orders[0].orderId, ZERO_BN);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if order is a trigger order', async () => {
+        testCli.changeCurrentSigner(1);
+        const params = marketOrderParams({ long: {} }, AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION.muln(49));
+        params.orderType = { triggerMarket: {} };
+        params.price = ZERO_BN;
+        params.triggerPrice = MARK_PRICE_PRECISION.muln(60);
+        await requireCustomError(testCli.placeAndFillOrder(params), 'InvalidOrder');
+        testCli.changeCurrentSigner(0);
+    });
+});
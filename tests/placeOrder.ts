@@ -0,0 +1,225 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes } from "@coral-xyz/anchor";
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError, requirePublickeyEq, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type OrderParams = IdlTypes<ClearingHouse>['orderParams'];
+
+const MARK_PRICE_PRECISION = new BN(10_000_000_000);
+const AMM_RESERVE_PRECISION = new BN(10_000_000_000_000);
+
+// 在market 0上以50的价格做多1个base资产的限价单
+function limitOrderParams(): OrderParams {
+    return {
+        orderType: { limit: {} },
+        direction: { long: {} },
+        userOrderId: 0,
+        quoteAssetAmount: ZERO_BN,
+        baseAssetAmount: AMM_RESERVE_PRECISION,
+        price: MARK_PRICE_PRECISION.muln(50),
+        marketIndex: ZERO_BN,
+        reduceOnly: false,
+        postOnly: false,
+        immediateOrCancel: false,
+        triggerPrice: ZERO_BN,
+        triggerCondition: { above: {} },
+        triggerPriceSource: { oracle: {} },
+        oraclePriceOffset: ZERO_BN,
+        maxAge: null,
+    };
+}
+
+describe("clearing house: place_order", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 3);
+        await testCli.initializeRelevantAccounts(9, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeOrderState();
+
+        const oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+        await testCli.initializeMarket(ZERO_BN, oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if market not initialized', async () => {
+        testCli.changeCurrentSigner(1);
+        const params = limitOrderParams();
+        params.marketIndex = new BN(1);
+        await requireCustomError(testCli.placeOrder(params), 'MarketIndexNotInitialized');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail with invalid order params', async () => {
+        testCli.changeCurrentSigner(1);
+        // 限价单必须设置价格
+        let params = limitOrderParams();
+        params.price = ZERO_BN;
+        await requireCustomError(testCli.placeOrder(params), 'InvalidOrder');
+
+        // 市价单的base与quote数量只能二选一
+        params = limitOrderParams();
+        params.orderType = { market: {} };
+        params.quoteAssetAmount = new BN(50_000_000);
+        await requireCustomError(testCli.placeOrder(params), 'InvalidOrder');
+
+        // 触发单必须设置触发价格
+        params = limitOrderParams();
+        params.orderType = { triggerLimit: {} };
+        await requireCustomError(testCli.placeOrder(params), 'InvalidOrder');
+
+        // IOC订单只能通过place_and_fill_order下单
+        params = limitOrderParams();
+        params.immediateOrCancel = true;
+        await requireCustomError(testCli.placeOrder(params), 'InvalidOrder');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if order amount too small', async () => {
+        testCli.changeCurrentSigner(1);
+        // 价值约0.01，小于min_order_quote_asset_amount（0.5）
+        const params = limitOrderParams();
+        params.baseAssetAmount = AMM_RESERVE_PRECISION.divn(5000);
+        await requireCustomError(testCli.placeOrder(params), 'OrderAmountTooSmall');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if referrer is user self', async () => {
+        testCli.changeCurrentSigner(1);
+        const authority = testCli.getCurrentSigner().publicKey;
+        await requireCustomError(
+            testCli.placeOrder(limitOrderParams(), null, testCli.getUserAddress(authority)),
+            'InvalidReferrer'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass place limit order', async () => {
+        testCli.changeCurrentSigner(1);
+        const authority = testCli.getCurrentSigner().publicKey;
+        const params = limitOrderParams();
+        params.userOrderId = 1;
+        await testCli.placeOrder(params);
+
+        const order = (await testCli.getUserOrders(authority)).orders[0];
+        requireBNEq(order.orderId, new BN(1));
+        expect(order.userOrderId).eq(1);
+        expect(order.status).deep.eq({ open: {} });
+        expect(order.discountTier).deep.eq({ none: {} });
+        requireBNEq(order.baseAssetAmount, AMM_RESERVE_PRECISION);
+        requireBNEq(order.userBaseAssetAmount, AMM_RESERVE_PRECISION);
+        requireBNEq(order.price, MARK_PRICE_PRECISION.muln(50));
+
+        const position = (await testCli.getUserPositions(authority)).positions[0];
+        requireBNEq(position.marketIndex, ZERO_BN);
+        requireBNEq(position.openOrders, new BN(1));
+
+        const orderHistory = await testCli.getOrderHistory();
+        requireBNEq(orderHistory.lastOrderId, new BN(1));
+        const record = orderHistory.orderRecords[0];
+        expect(record.action).deep.eq({ place: {} });
+        requireBNEq(record.recordId, new BN(1));
+        requirePublickeyEq(record.user, testCli.getUserAddress(authority));
+        requireBNEq(record.order.orderId, new BN(1));
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail with duplicate user order id', async () => {
+        testCli.changeCurrentSigner(1);
+        const params = limitOrderParams();
+        params.userOrderId = 1;
+        await requireCustomError(testCli.placeOrder(params), 'DuplicateUserOrderId');
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass place trigger order with init status', async () => {
+        testCli.changeCurrentSigner(1);
+        const authority = testCli.getCurrentSigner().publicKey;
+        const params = limitOrderParams();
+        params.orderType = { triggerMarket: {} };
+        params.direction = { short: {} };
+        params.price = ZERO_BN;
+        params.triggerPrice = MARK_PRICE_PRECISION.muln(40);
+        params.triggerCondition = { below: {} };
+        await testCli.placeOrder(params);
+
+        const order = (await testCli.getUserOrders(authority)).orders[1];
+        requireBNEq(order.orderId, new BN(2));
+        expect(order.status).deep.eq({ init: {} });
+        requireBNEq(order.userBaseAssetAmount, AMM_RESERVE_PRECISION.neg());
+        requireBNEq((await testCli.getUserPositions(authority)).positions[0].openOrders, new BN(2));
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if exchange paused', async () => {
+        await testCli.updateExchangePaused(true);
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(testCli.placeOrder(limitOrderParams()), 'ExchangePaused');
+        testCli.changeCurrentSigner(0);
+        await testCli.updateExchangePaused(false);
+    });
+
+    it('Pass place reduce only order and cancel orders while exchange paused', async () => {
+        await testCli.updateExchangePaused(true);
+        testCli.changeCurrentSigner(1);
+        const authority = testCli.getCurrentSigner().publicKey;
+        const openOrdersBefore = (await testCli.getUserPositions(authority)).positions[0].openOrders;
+
+        // 暂停期间只减仓模式下仍可以挂reduce only订单
+        const params = limitOrderParams();
+        params.userOrderId = 9;
+        params.direction = { short: {} };
+        params.reduceOnly = true;
+        await testCli.placeOrder(params);
+        requireBNEq((await testCli.getUserPositions(authority)).positions[0].openOrders, openOrdersBefore.addn(1));
+
+        // 取消订单不受暂停限制
+        await testCli.cancelOrderByUserOrderId(9);
+        requireBNEq((await testCli.getUserPositions(authority)).positions[0].openOrders, openOrdersBefore);
+        testCli.changeCurrentSigner(0);
+        await testCli.updateExchangePaused(false);
+    });
+
+    it('Pass lock referrer on first referred order', async () => {
+        // signer 0与signer 2各自创建User作为推荐人
+        await testCli.initializeUser();
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+        testCli.changeCurrentSigner(1);
+        const authority = testCli.getCurrentSigner().publicKey;
+        const referrer = testCli.getUserAddress(testCli.signers[0].publicKey);
+        const otherReferrer = testCli.getUserAddress(testCli.signers[2].publicKey);
+
+        const params = limitOrderParams();
+        params.userOrderId = 10;
+        await testCli.placeOrder(params, null, referrer);
+        requirePublickeyEq((await testCli.getUser(authority)).referrer, referrer);
+        const orders = (await testCli.getUserOrders(authority)).orders;
+        requirePublickeyEq(orders.find((order) => order.userOrderId == 10).referrer, referrer);
+
+        // 推荐人锁定后不能更换
+        params.userOrderId = 11;
+        await requireCustomError(
+            testCli.placeOrder(params, null, otherReferrer),
+            'InvalidReferrer'
+        );
+        await testCli.placeOrder(params, null, referrer);
+        testCli.changeCurrentSigner(0);
+    });
+});
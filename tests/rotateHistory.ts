@@ -0,0 +1,122 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, web3 } from "@coral-xyz/anchor";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { ClearingHouse } from "../target/types/clearing_house";
+import { createAccounts, requireBNEq, requireCustomError, requirePublickeyEq, ZERO_BN } from "./utils";
+import { TestClient } from "./testClient";
+
+const QUOTE_PRECISION = new BN(1_000_000);
+
+describe("clearing house: initialize_extended_curve_history && rotate_history", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+
+    let testCli: TestClient;
+    let extendedCurveHistory: web3.PublicKey;
+    let userCollateralAccount: web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+
+        const userAuthority = testCli.signers[1].publicKey;
+        userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority);
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, userCollateralAccount, testCli.signers[0], BigInt(QUOTE_PRECISION.muln(1000).toString()));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        testCli.changeCurrentSigner(0);
+
+        [extendedCurveHistory] = await createAccounts(provider, [8 + 311312], program.programId);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.initializeExtendedCurveHistory(extendedCurveHistory),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass initialize extended curve history', async () => {
+        requirePublickeyEq((await testCli.getState()).extendedCurveHistory, web3.PublicKey.default);
+
+        await testCli.initializeExtendedCurveHistory(extendedCurveHistory);
+        requirePublickeyEq((await testCli.getState()).extendedCurveHistory, extendedCurveHistory);
+        const history = await program.account.curveHistory.fetch(extendedCurveHistory);
+        requireBNEq(history.head, ZERO_BN);
+    });
+
+    it('Fail if reinitialize extended curve history', async () => {
+        const [newExtendedCurveHistory] = await createAccounts(provider, [8 + 311312], program.programId);
+        await requireCustomError(
+            testCli.initializeExtendedCurveHistory(newExtendedCurveHistory),
+            'ExtendedCurveHistoryAlreadyInitialized'
+        );
+    });
+
+    it('Fail if old history not in state', async () => {
+        const [newDepositHistory] = await createAccounts(provider, [8 + 147472], program.programId);
+        await requireCustomError(
+            testCli.rotateHistory({ deposit: {} }, testCli.tradeHistory, newDepositHistory),
+            'InvalidHistoryAccount'
+        );
+    });
+
+    it('Fail if new history has wrong size', async () => {
+        const [newDepositHistory] = await createAccounts(provider, [8 + 262160], program.programId);
+        await requireCustomError(
+            testCli.rotateHistory({ deposit: {} }, testCli.depositHistory, newDepositHistory),
+            'InvalidHistoryAccount'
+        );
+    });
+
+    it('Pass rotate deposit history', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.depositCollateral(QUOTE_PRECISION.muln(100), userCollateralAccount);
+        testCli.changeCurrentSigner(0);
+
+        const oldDepositHistory = testCli.depositHistory;
+        const [newDepositHistory] = await createAccounts(provider, [8 + 147472], program.programId);
+        await testCli.rotateHistory({ deposit: {} }, oldDepositHistory, newDepositHistory);
+        requirePublickeyEq((await testCli.getState()).depositHistory, newDepositHistory);
+        testCli.depositHistory = newDepositHistory;
+
+        let depositHistory = await testCli.getDepositHistory();
+        requireBNEq(depositHistory.head, ZERO_BN);
+        requireBNEq(depositHistory.lastRecordId, new BN(1));
+
+        // 新账户中的record_id接着旧账户继续递增
+        testCli.changeCurrentSigner(1);
+        await testCli.depositCollateral(QUOTE_PRECISION.muln(200), userCollateralAccount);
+        testCli.changeCurrentSigner(0);
+        depositHistory = await testCli.getDepositHistory();
+        requireBNEq(depositHistory.head, new BN(1));
+        requireBNEq(depositHistory.depositRecords[0].recordId, new BN(2));
+
+        // 旧账户作为存档保持不变
+        const archived = await program.account.depositHistory.fetch(oldDepositHistory);
+        requireBNEq(archived.head, new BN(1));
+        requireBNEq(archived.depositRecords[0].recordId, new BN(1));
+    });
+
+    it('Fail if new history already initialized', async () => {
+        const [newDepositHistory] = await createAccounts(provider, [8 + 147472], program.programId);
+        await requireCustomError(
+            testCli.rotateHistory({ deposit: {} }, testCli.depositHistory, testCli.tradeHistory),
+            'InvalidHistoryAccount'
+        );
+        await testCli.rotateHistory({ deposit: {} }, testCli.depositHistory, newDepositHistory);
+        await requireCustomError(
+            testCli.rotateHistory({ deposit: {} }, newDepositHistory, newDepositHistory),
+            'ConstraintZero'
+        );
+        testCli.depositHistory = newDepositHistory;
+    });
+});
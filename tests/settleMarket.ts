@@ -0,0 +1,287 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes, web3 } from "@coral-xyz/anchor";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError, requirePublickeyEq, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type OrderParams = IdlTypes<ClearingHouse>['orderParams'];
+
+const AMM_RESERVE_PRECISION = new BN(10_000_000_000_000);
+const MARK_PRICE_PRECISION = new BN(10_000_000_000);
+const QUOTE_PRECISION = new BN(1_000_000);
+
+function orderParams(orderType: IdlTypes<ClearingHouse>['orderType'], price: BN, marketIndex: BN = ZERO_BN): OrderParams {
+    return {
+        orderType,
+        direction: { long: {} },
+        userOrderId: 0,
+        quoteAssetAmount: ZERO_BN,
+        baseAssetAmount: AMM_RESERVE_PRECISION,
+        price,
+        marketIndex,
+        reduceOnly: false,
+        postOnly: false,
+        immediateOrCancel: false,
+        triggerPrice: ZERO_BN,
+        triggerCondition: { above: {} },
+        triggerPriceSource: { oracle: {} },
+        oraclePriceOffset: ZERO_BN,
+        maxAge: null,
+    };
+}
+
+describe("clearing house: settle_market && settle_position", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+    let userAuthority: web3.PublicKey;
+    let orderOnlyAuthority: web3.PublicKey;
+    let oracle: web3.PublicKey;
+    const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 3);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+
+        oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        await testCli.initializeMarket(ZERO_BN, oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        userAuthority = testCli.signers[1].publicKey;
+        const amount = QUOTE_PRECISION.muln(1000);
+        const userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority);
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, userCollateralAccount, testCli.signers[0], BigInt(amount.toString()));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        await testCli.depositCollateral(amount, userCollateralAccount);
+        // 一笔市价单开多，一笔限价单挂在AMM价格之下保持未成交
+        await testCli.placeAndFillOrder(orderParams({ market: {} }, ZERO_BN));
+        await testCli.placeOrder(orderParams({ limit: {} }, MARK_PRICE_PRECISION.muln(40)));
+
+        // 另一个用户只挂了一笔限价单、没有头寸，结算时不需要settle_position，订单会保留到market重置之后
+        orderOnlyAuthority = testCli.signers[2].publicKey;
+        const orderOnlyCollateralAccount = await createAccount(provider.connection, testCli.signers[2], testCli.collateralMint, orderOnlyAuthority);
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, orderOnlyCollateralAccount, testCli.signers[0], BigInt(amount.toString()));
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        await testCli.depositCollateral(amount, orderOnlyCollateralAccount);
+        await testCli.placeOrder(orderParams({ limit: {} }, MARK_PRICE_PRECISION.muln(70)));
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.settleMarket(ZERO_BN),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail set settled status through update_market_status', async () => {
+        await requireCustomError(
+            testCli.updateMarketStatus(ZERO_BN, { settled: {} }),
+            'InvalidMarketStatusUpdate'
+        );
+    });
+
+    it('Fail settle position before market settled', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.settlePosition(ZERO_BN),
+            'MarketNotSettled'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass put market into reduce only', async () => {
+        await testCli.settleMarket(ZERO_BN);
+        expect((await testCli.getMarket(ZERO_BN)).status).deep.eq({ reduceOnly: {} });
+
+        const tradeHistory = await testCli.getTradeHistory();
+        const record = tradeHistory.tradeRecord[tradeHistory.head.toNumber() - 1];
+        expect(record.settlement).eq(2);
+        requireBNEq(record.marketIndex, ZERO_BN);
+        requirePublickeyEq(record.user, web3.PublicKey.default);
+
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.placeAndFillOrder(orderParams({ market: {} }, ZERO_BN)),
+            'MarketReduceOnly'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass freeze market at oracle twap', async () => {
+        await testCli.setPrice(pythProgram, oracle, new BN(60_000_000));
+        await testCli.settleMarket(ZERO_BN);
+
+        const market = await testCli.getMarket(ZERO_BN);
+        expect(market.status).deep.eq({ settled: {} });
+        requireBNEq(market.settlementPrice, MARK_PRICE_PRECISION.muln(60));
+
+        const tradeHistory = await testCli.getTradeHistory();
+        const record = tradeHistory.tradeRecord[tradeHistory.head.toNumber() - 1];
+        expect(record.settlement).eq(1);
+        requireBNEq(record.marketIndex, ZERO_BN);
+        requireBNEq(record.oraclePrice, MARK_PRICE_PRECISION.muln(60));
+        requirePublickeyEq(record.user, web3.PublicKey.default);
+    });
+
+    it('Fail update status of settled market', async () => {
+        await requireCustomError(
+            testCli.updateMarketStatus(ZERO_BN, { active: {} }),
+            'InvalidMarketStatusUpdate'
+        );
+    });
+
+    it('Fail trade or reset market with open interest', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.placeAndFillOrder(orderParams({ market: {} }, ZERO_BN)),
+            'MarketSettled'
+        );
+        testCli.changeCurrentSigner(0);
+
+        await requireCustomError(
+            testCli.settleMarket(ZERO_BN),
+            'MarketHasOpenInterest'
+        );
+    });
+
+    it('Pass settle position at settlement price', async () => {
+        const position = (await testCli.getUserPositions(userAuthority)).positions[0];
+        const collateralBefore = (await testCli.getUser(userAuthority)).collateral;
+        const baseAssetReserveBefore = (await testCli.getMarket(ZERO_BN)).amm.baseAssetReserve;
+
+        testCli.changeCurrentSigner(1);
+        await testCli.settlePosition(ZERO_BN);
+        testCli.changeCurrentSigner(0);
+
+        // 1个base按60结算，盈亏为结算价值减去开仓成本
+        const settledValue = QUOTE_PRECISION.muln(60);
+        requireBNEq((await testCli.getUser(userAuthority)).collateral, collateralBefore.add(settledValue).sub(position.quoteAssetAmount));
+
+        const positionAfter = (await testCli.getUserPositions(userAuthority)).positions[0];
+        requireBNEq(positionAfter.baseAssetAmount, ZERO_BN);
+        requireBNEq(positionAfter.openOrders, ZERO_BN);
+        expect((await testCli.getUserOrders(userAuthority)).orders.every((order) => order.orderId.isZero())).eq(true);
+
+        const market = await testCli.getMarket(ZERO_BN);
+        requireBNEq(market.openInterest, ZERO_BN);
+        requireBNEq(market.baseAssetAmountLong, ZERO_BN);
+        requireBNEq(market.baseAssetAmount, ZERO_BN);
+        // 结算不经过AMM
+        requireBNEq(market.amm.baseAssetReserve, baseAssetReserveBefore);
+
+        const tradeHistory = await testCli.getTradeHistory();
+        const record = tradeHistory.tradeRecord[tradeHistory.head.toNumber() - 1];
+        expect(record.settlement).eq(1);
+        expect(record.direction).deep.eq({ short: {} });
+        requirePublickeyEq(record.userAuthority, userAuthority);
+        requireBNEq(record.baseAssetAmount, AMM_RESERVE_PRECISION);
+        requireBNEq(record.quoteAssetAmount, settledValue);
+        requireBNEq(record.fee, ZERO_BN);
+    });
+
+    it('Fail settle position twice', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.settlePosition(ZERO_BN),
+            'UserHasNoPositionInMarket'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass reset settled market and reuse the slot', async () => {
+        const historyBefore = await testCli.getTradeHistory();
+        const markPrice = historyBefore.tradeRecord[historyBefore.head.toNumber() - 1].markPriceAfter;
+        await testCli.settleMarket(ZERO_BN);
+        const market = await testCli.getMarket(ZERO_BN);
+        expect(market.initialized).eq(0);
+        expect(market.status).deep.eq({ active: {} });
+        requireBNEq(market.settlementPrice, ZERO_BN);
+
+        // 重置前写入记录，保留重置前的mark price
+        const tradeHistory = await testCli.getTradeHistory();
+        const record = tradeHistory.tradeRecord[tradeHistory.head.toNumber() - 1];
+        expect(record.settlement).eq(3);
+        requireBNEq(record.marketIndex, ZERO_BN);
+        requireBNEq(record.markPriceAfter, markPrice);
+
+        await requireCustomError(
+            testCli.settleMarket(ZERO_BN),
+            'MarketIndexNotInitialized'
+        );
+
+        await testCli.initializeMarket(ZERO_BN, oracle, ammReserve, ammReserve, new BN(3600), new BN(60_000));
+        expect((await testCli.getMarket(ZERO_BN)).initialized).eq(1);
+    });
+
+    it('Fail fill order placed before market reset', async () => {
+        // 重置前挂的限价单仍在用户的订单中，但不能在重新初始化的market上成交
+        const order = (await testCli.getUserOrders(orderOnlyAuthority)).orders[0];
+        expect(order.orderId.lt((await testCli.getMarket(ZERO_BN)).minOrderId)).eq(true);
+
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.fillOrder(orderOnlyAuthority, order.orderId, oracle),
+            'OrderPlacedBeforeMarketReset'
+        );
+        testCli.changeCurrentSigner(0);
+
+        // 用户仍可以取消该订单
+        testCli.changeCurrentSigner(2);
+        await testCli.cancelOrder(order.orderId);
+        requireBNEq((await testCli.getUserPositions(orderOnlyAuthority)).positions[0].openOrders, ZERO_BN);
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass settle market migrated to market account but never reset it', async () => {
+        const marketIndex = new BN(1);
+        const marketOracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        await testCli.initializeMarket(marketIndex, marketOracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+        await testCli.migrateMarketToAccount(marketIndex);
+
+        testCli.changeCurrentSigner(1);
+        await testCli.placeAndFillOrder(orderParams({ market: {} }, ZERO_BN, marketIndex));
+        testCli.changeCurrentSigner(0);
+
+        await testCli.settleMarket(marketIndex);
+        expect((await testCli.getMarketAccount(marketIndex)).market.status).deep.eq({ reduceOnly: {} });
+
+        await testCli.settleMarket(marketIndex);
+        let market = (await testCli.getMarketAccount(marketIndex)).market;
+        expect(market.status).deep.eq({ settled: {} });
+        requireBNEq(market.settlementPrice, MARK_PRICE_PRECISION.muln(50));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.settlePosition(marketIndex);
+        testCli.changeCurrentSigner(0);
+        const position = (await testCli.getUserPositions(userAuthority)).positions.find((position) => position.marketIndex.eq(marketIndex));
+        requireBNEq(position.baseAssetAmount, ZERO_BN);
+        requireBNEq((await testCli.getMarketAccount(marketIndex)).market.openInterest, ZERO_BN);
+
+        // MarketAccount无法重新初始化，所以不允许重置
+        await requireCustomError(
+            testCli.settleMarket(marketIndex),
+            'MarketAccountNotResettable'
+        );
+        market = (await testCli.getMarketAccount(marketIndex)).market;
+        expect(market.initialized).eq(1);
+        expect(market.status).deep.eq({ settled: {} });
+    });
+});
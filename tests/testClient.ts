@@ -1,9 +1,12 @@
-import { AnchorProvider, web3, Program, IdlTypes } from "@coral-xyz/anchor";
+import { AnchorProvider, web3, Program, IdlTypes, BN } from "@coral-xyz/anchor";
 import { createMint } from '@solana/spl-token';
 import { createAccounts, getSeedFromNumber } from './utils';
 import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
 type PublicKey = web3.PublicKey;
 
+export const MARKETS_ACCOUNT_SIZE = 8 + 31760;
+
 export class TestClient {
     provider: AnchorProvider;
     signers: Array<web3.Keypair>;
@@ -78,13 +81,8 @@ orderHistory: ${this.orderHistory}`);
         [this.insuranceVault,] = web3.PublicKey.findProgramAddressSync([Buffer.from('insurance_vault')], this.program.programId);
         [this.insuranceVaultAuthority,] = web3.PublicKey.findProgramAddressSync([this.insuranceVault.toBuffer()], this.program.programId);
         [this.orderState,] = web3.PublicKey.findProgramAddressSync([Buffer.from('order_state')], this.program.programId);
-
-        // create state && markets accounts
-        [this.state, this.markets] = await createAccounts(
-            this.provider,
-            [8 + 1200, 8 + 31744],
-            this.program.programId
-        );
+        [this.state,] = web3.PublicKey.findProgramAddressSync([Buffer.from('clearing_house')], this.program.programId);
+        [this.markets,] = web3.PublicKey.findProgramAddressSync([Buffer.from('markets')], this.program.programId);
 
         if (logAddrs) {
             console.log(`collateral mint: ${this.collateralMint}
@@ -110,9 +108,44 @@ order state: ${this.orderState}`);
             .rpc();
     }
 
+    async migrateState() {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.migrateState()
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async migrateMarkets() {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.migrateMarkets()
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                markets: this.markets,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async migrateOrderState() {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.migrateOrderState()
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                orderState: this.orderState,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
     async initializeHistory() {
         const signer = this.getCurrentSigner();
-        await this.program.methods.intializeHistory()
+        await this.program.methods.initializeHistory()
             .accounts({
                 admin: signer.publicKey,
                 state: this.state,
@@ -137,11 +170,600 @@ order state: ${this.orderState}`);
                 collateralVaultAuthority: this.collateralVaultAuthority,
                 insuranceVaultAuthority: this.insuranceVaultAuthority,
                 markets: this.markets,
-            })
+            } as any)
+            .signers([signer])
+            .rpc();
+
+        // markets在initialize中只分配了10240字节，需要多次扩容到完整大小
+        while ((await this.provider.connection.getAccountInfo(this.markets)).data.length < MARKETS_ACCOUNT_SIZE) {
+            await this.resizeMarkets();
+        }
+    }
+
+    async resizeMarkets() {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.resizeMarkets()
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                markets: this.markets,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateMarginRatio(marginRatioInitial: BN, marginRatioPartial: BN, marginRatioMaintenance: BN) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateMarginRatio(marginRatioInitial, marginRatioPartial, marginRatioMaintenance)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateMarketMarginRatio(
+        marketIndex: BN,
+        marginRatioInitial: number,
+        marginRatioPartial: number,
+        marginRatioMaintenance: number,
+        force: boolean
+    ) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateMarketMarginRatio(marketIndex, marginRatioInitial, marginRatioPartial, marginRatioMaintenance, force)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                markets: this.markets,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updatePartialLiquidationClosePercentage(numerator: BN, denominator: BN) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updatePartialLiquidationClosePercentage(numerator, denominator)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updatePartialLiquidationPenaltyPercentage(numerator: BN, denominator: BN) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updatePartialLiquidationPenaltyPercentage(numerator, denominator)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateFullLiquidationPenaltyPercentage(numerator: BN, denominator: BN) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateFullLiquidationPenaltyPercentage(numerator, denominator)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updatePartialLiquidationLiquidatorShareDenominator(denominator: BN) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updatePartialLiquidationLiquidatorShareDenominator(denominator)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateFullLiquidationLiquidatorShareDenominator(denominator: BN) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateFullLiquidationLiquidatorShareDenominator(denominator)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateFee(feeStructure: IdlTypes<ClearingHouse>['feeStructure']) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateFee(feeStructure)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateDiscountMint(discountMint: PublicKey) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateDiscountMint(discountMint)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateOracleGuardRails(oracleGuardRails: IdlTypes<ClearingHouse>['oracleGuardRails']) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateOracleGuardRails(oracleGuardRails)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateMarketOracle(marketIndex: BN, oracle: PublicKey, oracleSource: IdlTypes<ClearingHouse>['oracleSource']) {
+        const signer = this.getCurrentSigner();
+        const currentOracle = (await this.getMarket(marketIndex))?.amm.oracle ?? web3.PublicKey.default;
+        await this.program.methods.updateMarketOracle(marketIndex, oracle, oracleSource)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                markets: this.markets,
+                oracle,
+                currentOracle,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateExchangePaused(exchangePaused: boolean) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateExchangePaused(exchangePaused)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateHistoryRingBuffersDisabled(historyRingBuffersDisabled: boolean) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateHistoryRingBuffersDisabled(historyRingBuffersDisabled)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async initializeExtendedCurveHistory(extendedCurveHistory: PublicKey) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.initializeExtendedCurveHistory()
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                extendedCurveHistory,
+            } as any)
             .signers([signer])
             .rpc();
     }
 
+    async rotateHistory(historyType: IdlTypes<ClearingHouse>['historyType'], oldHistory: PublicKey, newHistory: PublicKey) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.rotateHistory(historyType)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                oldHistory,
+                newHistory,
+                orderState: 'order' in historyType ? this.orderState : null,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateFundingPaused(fundingPaused: boolean) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateFundingPaused(fundingPaused)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateMarketStatus(marketIndex: BN, status: IdlTypes<ClearingHouse>['marketStatus']) {
+        const signer = this.getCurrentSigner();
+        const { markets, remainingAccounts } = await this.getMarketsAccounts([marketIndex]);
+        await this.program.methods.updateMarketStatus(marketIndex, status)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                markets,
+            } as any)
+            .remainingAccounts(remainingAccounts)
+            .signers([signer])
+            .rpc();
+    }
+
+    async settleMarket(marketIndex: BN) {
+        const signer = this.getCurrentSigner();
+        const oracle = (await this.getMarket(marketIndex))?.amm.oracle ?? web3.PublicKey.default;
+        const { markets, remainingAccounts } = await this.getMarketsAccounts([marketIndex]);
+        await this.program.methods.settleMarket(marketIndex)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                markets,
+                tradeHistory: this.tradeHistory,
+                orderState: this.orderState,
+                orderHistory: this.orderHistory,
+                oracle,
+            } as any)
+            .remainingAccounts(remainingAccounts)
+            .signers([signer])
+            .rpc();
+    }
+
+    async settlePosition(marketIndex: BN) {
+        const signer = this.getCurrentSigner();
+        const { markets, remainingAccounts } = await this.getMarketsAccounts([marketIndex]);
+        await this.program.methods.settlePosition(marketIndex)
+            .accounts({
+                state: this.state,
+                authority: signer.publicKey,
+                markets,
+                userPositions: this.getUserPositionsAddress(signer.publicKey),
+                userOrders: this.getUserOrdersAddress(signer.publicKey),
+                orderState: this.orderState,
+                orderHistory: this.orderHistory,
+                tradeHistory: this.tradeHistory,
+            } as any)
+            .remainingAccounts(remainingAccounts)
+            .signers([signer])
+            .rpc();
+    }
+
+    async proposeAdmin(newAdmin: PublicKey) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.proposeAdmin(newAdmin)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async acceptAdmin() {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.acceptAdmin()
+            .accounts({
+                pendingAdmin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async cancelAdminProposal() {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.cancelAdminProposal()
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateWhitelistMint(whitelistMint: PublicKey) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateWhitelistMint(whitelistMint)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateDefaultMaxOrderAge(defaultMaxOrderAge: number) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.updateDefaultMaxOrderAge(defaultMaxOrderAge)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                orderState: this.orderState,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async initializeUser(whitelistToken: PublicKey = null) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.initializeUser()
+            .accounts({
+                state: this.state,
+                authority: signer.publicKey,
+                whitelistToken,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    getUserAddress(authority: PublicKey): PublicKey {
+        const [user,] = web3.PublicKey.findProgramAddressSync([Buffer.from('user'), authority.toBuffer()], this.program.programId);
+        return user;
+    }
+
+    getUserPositionsAddress(authority: PublicKey): PublicKey {
+        const [userPositions,] = web3.PublicKey.findProgramAddressSync([Buffer.from('user_positions'), authority.toBuffer()], this.program.programId);
+        return userPositions;
+    }
+
+    async getUser(authority: PublicKey): Promise<IdlTypes<ClearingHouse>['user']> {
+        return await this.program.account.user.fetch(this.getUserAddress(authority));
+    }
+
+    async getUserPositions(authority: PublicKey): Promise<IdlTypes<ClearingHouse>['userPositions']> {
+        return await this.program.account.userPositions.fetch(this.getUserPositionsAddress(authority));
+    }
+
+    async initializeUserOrders() {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.initializeUserOrders()
+            .accounts({
+                state: this.state,
+                authority: signer.publicKey,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async deleteUserOrders() {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.deleteUserOrders()
+            .accounts({
+                state: this.state,
+                authority: signer.publicKey,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    getUserOrdersAddress(authority: PublicKey): PublicKey {
+        const [userOrders,] = web3.PublicKey.findProgramAddressSync([Buffer.from('user_orders'), this.getUserAddress(authority).toBuffer()], this.program.programId);
+        return userOrders;
+    }
+
+    async getUserOrders(authority: PublicKey): Promise<IdlTypes<ClearingHouse>['userOrders']> {
+        return await this.program.account.userOrders.fetch(this.getUserOrdersAddress(authority));
+    }
+
+    async initializeMarket(
+        marketIndex: BN,
+        oracle: PublicKey,
+        ammBaseAssetReserve: BN,
+        ammQuoteAssetReserve: BN,
+        ammPeriodicity: BN,
+        ammPegMultiplier: BN,
+        oracleSource: IdlTypes<ClearingHouse>['oracleSource'] = { pyth: {} },
+    ) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.initializeMarket(marketIndex, ammBaseAssetReserve, ammQuoteAssetReserve, ammPeriodicity, ammPegMultiplier, oracleSource)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                markets: this.markets,
+                oracle,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async initializeMarketAccount(
+        marketIndex: BN,
+        oracle: PublicKey,
+        ammBaseAssetReserve: BN,
+        ammQuoteAssetReserve: BN,
+        ammPeriodicity: BN,
+        ammPegMultiplier: BN,
+        oracleSource: IdlTypes<ClearingHouse>['oracleSource'] = { pyth: {} },
+    ) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.initializeMarketAccount(marketIndex, ammBaseAssetReserve, ammQuoteAssetReserve, ammPeriodicity, ammPegMultiplier, oracleSource)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                marketAccount: this.getMarketAccountAddress(marketIndex),
+                oracle,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async migrateMarketToAccount(marketIndex: BN) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.migrateMarketToAccount(marketIndex)
+            .accounts({
+                admin: signer.publicKey,
+                state: this.state,
+                markets: this.markets,
+                marketAccount: this.getMarketAccountAddress(marketIndex),
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async placeOrder(params: IdlTypes<ClearingHouse>['orderParams'], discountToken: PublicKey = null, referrer: PublicKey = null) {
+        const signer = this.getCurrentSigner();
+        const oracle = (await this.getMarket(params.marketIndex))?.amm.oracle ?? web3.PublicKey.default;
+        // 保证金检查需要用户所有持仓所在的market
+        const userPositions = await this.program.account.userPositions.fetchNullable(this.getUserPositionsAddress(signer.publicKey));
+        const positionMarketIndexes = (userPositions?.positions ?? [])
+            .filter((position) => !position.baseAssetAmount.isZero() && !position.marketIndex.eq(params.marketIndex))
+            .map((position) => position.marketIndex);
+        const { markets, remainingAccounts } = await this.getMarketsAccounts([params.marketIndex, ...positionMarketIndexes]);
+        await this.program.methods.placeOrder(params)
+            .accounts({
+                state: this.state,
+                authority: signer.publicKey,
+                markets,
+                userPositions: this.getUserPositionsAddress(signer.publicKey),
+                userOrders: this.getUserOrdersAddress(signer.publicKey),
+                orderState: this.orderState,
+                orderHistory: this.orderHistory,
+                oracle,
+                discountToken,
+                referrer,
+            } as any)
+            .remainingAccounts(remainingAccounts)
+            .signers([signer])
+            .rpc();
+    }
+
+    async placeAndFillOrder(params: IdlTypes<ClearingHouse>['orderParams'], discountToken: PublicKey = null, referrer: PublicKey = null) {
+        const signer = this.getCurrentSigner();
+        const oracle = (await this.getMarket(params.marketIndex))?.amm.oracle ?? web3.PublicKey.default;
+        // 保证金检查需要用户所有持仓所在的market
+        const userPositions = await this.program.account.userPositions.fetchNullable(this.getUserPositionsAddress(signer.publicKey));
+        const positionMarketIndexes = (userPositions?.positions ?? [])
+            .filter((position) => !position.baseAssetAmount.isZero() && !position.marketIndex.eq(params.marketIndex))
+            .map((position) => position.marketIndex);
+        const { markets, remainingAccounts } = await this.getMarketsAccounts([params.marketIndex, ...positionMarketIndexes]);
+        await this.program.methods.placeAndFillOrder(params)
+            .accounts({
+                state: this.state,
+                authority: signer.publicKey,
+                markets,
+                userPositions: this.getUserPositionsAddress(signer.publicKey),
+                userOrders: this.getUserOrdersAddress(signer.publicKey),
+                orderState: this.orderState,
+                orderHistory: this.orderHistory,
+                tradeHistory: this.tradeHistory,
+                oracle,
+                discountToken,
+                referrer,
+            } as any)
+            .remainingAccounts(remainingAccounts)
+            .signers([signer])
+            .rpc();
+    }
+
+    async depositCollateral(amount: BN, userCollateralAccount: PublicKey) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.depositCollateral(amount)
+            .accounts({
+                state: this.state,
+                authority: signer.publicKey,
+                collateralVault: this.collateralVault,
+                userCollateralAccount,
+                depositHistory: this.depositHistory,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async fillOrder(userAuthority: PublicKey, orderId: BN, oracle: PublicKey, referrer: PublicKey = null) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.fillOrder(orderId)
+            .accounts({
+                state: this.state,
+                authority: signer.publicKey,
+                filler: this.getUserAddress(signer.publicKey),
+                user: this.getUserAddress(userAuthority),
+                markets: this.markets,
+                userPositions: this.getUserPositionsAddress(userAuthority),
+                userOrders: this.getUserOrdersAddress(userAuthority),
+                orderState: this.orderState,
+                orderHistory: this.orderHistory,
+                tradeHistory: this.tradeHistory,
+                oracle,
+                referrer,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async expireOrders(userAuthority: PublicKey) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.expireOrders()
+            .accounts({
+                state: this.state,
+                authority: signer.publicKey,
+                filler: this.getUserAddress(signer.publicKey),
+                user: this.getUserAddress(userAuthority),
+                userPositions: this.getUserPositionsAddress(userAuthority),
+                userOrders: this.getUserOrdersAddress(userAuthority),
+                orderState: this.orderState,
+                orderHistory: this.orderHistory,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async cancelOrder(orderId: BN) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.cancelOrder(orderId)
+            .accounts(this.cancelOrderAccounts(signer.publicKey))
+            .signers([signer])
+            .rpc();
+    }
+
+    async cancelOrderByUserOrderId(userOrderId: number) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.cancelOrderByUserOrderId(userOrderId)
+            .accounts(this.cancelOrderAccounts(signer.publicKey))
+            .signers([signer])
+            .rpc();
+    }
+
+    async cancelAllOrders(marketIndex: BN = null, direction: IdlTypes<ClearingHouse>['positionDirection'] = null) {
+        const signer = this.getCurrentSigner();
+        await this.program.methods.cancelAllOrders(marketIndex, direction)
+            .accounts(this.cancelOrderAccounts(signer.publicKey))
+            .signers([signer])
+            .rpc();
+    }
+
+    cancelOrderAccounts(authority: PublicKey): any {
+        return {
+            state: this.state,
+            authority,
+            userPositions: this.getUserPositionsAddress(authority),
+            userOrders: this.getUserOrdersAddress(authority),
+            orderState: this.orderState,
+            orderHistory: this.orderHistory,
+        };
+    }
+
+    // 通过mock pyth program创建一个price账户
+    async createPriceFeed(pythProgram: Program<Pyth>, price: BN, expo: number, conf: BN): Promise<PublicKey> {
+        const [priceFeed] = await createAccounts(this.provider, [3312], pythProgram.programId);
+        await pythProgram.methods.initialize(price, expo, conf)
+            .accounts({ price: priceFeed })
+            .rpc();
+        return priceFeed;
+    }
+
+    async setPrice(pythProgram: Program<Pyth>, priceFeed: PublicKey, price: BN) {
+        await pythProgram.methods.setPrice(price)
+            .accounts({ price: priceFeed })
+            .rpc();
+    }
+
     async getState(): Promise<IdlTypes<ClearingHouse>['state']> {
         return await this.program.account.state.fetch(this.state);
     }
@@ -150,6 +772,41 @@ order state: ${this.orderState}`);
         return await this.program.account.markets.fetch(this.markets);
     }
 
+    getMarketAccountAddress(marketIndex: BN): PublicKey {
+        return web3.PublicKey.findProgramAddressSync(
+            [Buffer.from('market'), marketIndex.toArrayLike(Buffer, 'le', 8)],
+            this.program.programId
+        )[0];
+    }
+
+    async getMarketAccount(marketIndex: BN): Promise<IdlTypes<ClearingHouse>['marketAccount']> {
+        return await this.program.account.marketAccount.fetch(this.getMarketAccountAddress(marketIndex));
+    }
+
+    // market已迁移到MarketAccount（或直接创建在MarketAccount中）时从MarketAccount读取，否则从markets中读取
+    async getMarket(marketIndex: BN): Promise<IdlTypes<ClearingHouse>['market']> {
+        const marketAccount = await this.program.account.marketAccount.fetchNullable(this.getMarketAccountAddress(marketIndex));
+        if (marketAccount !== null) {
+            return marketAccount.market;
+        }
+        return (await this.getMarkets()).markets[marketIndex.toNumber()];
+    }
+
+    // 涉及的market都在MarketAccount中时不传markets，MarketAccount通过remaining accounts传入
+    async getMarketsAccounts(marketIndexes: BN[]): Promise<{ markets: PublicKey, remainingAccounts: web3.AccountMeta[] }> {
+        let markets: PublicKey = null;
+        const remainingAccounts: web3.AccountMeta[] = [];
+        for (const marketIndex of marketIndexes) {
+            const marketAccount = this.getMarketAccountAddress(marketIndex);
+            if (await this.provider.connection.getAccountInfo(marketAccount) !== null) {
+                remainingAccounts.push({ pubkey: marketAccount, isSigner: false, isWritable: true });
+            } else {
+                markets = this.markets;
+            }
+        }
+        return { markets, remainingAccounts };
+    }
+
     async getFundingPaymentHistory(): Promise<IdlTypes<ClearingHouse>['fundingPaymentHistory']> {
         return await this.program.account.fundingPaymentHistory.fetch(this.fundingPaymentHistory);
     }
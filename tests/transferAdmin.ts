@@ -0,0 +1,92 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, web3 } from "@coral-xyz/anchor";
+import { ClearingHouse } from "../target/types/clearing_house";
+import { requireCustomError, requirePublickeyEq } from "./utils";
+import { TestClient } from "./testClient";
+
+describe("clearing house: propose_admin && accept_admin && cancel_admin_proposal", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+
+    let testCli: TestClient;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 3);
+        await testCli.initializeRelevantAccounts(9, true);
+        await testCli.initialize(true);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.proposeAdmin(testCli.signers[1].publicKey),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.cancelAdminProposal(),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail to propose default pubkey or current admin', async () => {
+        await requireCustomError(
+            testCli.proposeAdmin(web3.PublicKey.default),
+            'InvalidPendingAdmin'
+        );
+        await requireCustomError(
+            testCli.proposeAdmin(testCli.signers[0].publicKey),
+            'InvalidPendingAdmin'
+        );
+    });
+
+    it('Fail to cancel without pending admin', async () => {
+        await requireCustomError(
+            testCli.cancelAdminProposal(),
+            'NoPendingAdmin'
+        );
+    });
+
+    it('Pass propose and cancel', async () => {
+        await testCli.proposeAdmin(testCli.signers[1].publicKey);
+        requirePublickeyEq((await testCli.getState()).pendingAdmin, testCli.signers[1].publicKey);
+
+        await testCli.cancelAdminProposal();
+        requirePublickeyEq((await testCli.getState()).pendingAdmin, web3.PublicKey.default);
+
+        // 提名被撤销后无法再接受
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.acceptAdmin(),
+            'NotPendingAdmin'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if signer not pending admin', async () => {
+        await testCli.proposeAdmin(testCli.signers[1].publicKey);
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(
+            testCli.acceptAdmin(),
+            'NotPendingAdmin'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass accept admin', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.acceptAdmin();
+        let state = await testCli.getState();
+        requirePublickeyEq(state.admin, testCli.signers[1].publicKey);
+        requirePublickeyEq(state.pendingAdmin, web3.PublicKey.default);
+
+        // 原admin失去权限
+        testCli.changeCurrentSigner(0);
+        await requireCustomError(
+            testCli.proposeAdmin(testCli.signers[2].publicKey),
+            'ConstraintHasOne'
+        );
+    });
+});
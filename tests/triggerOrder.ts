@@ -0,0 +1,150 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes } from "@coral-xyz/anchor";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type OrderParams = IdlTypes<ClearingHouse>['orderParams'];
+
+const MARK_PRICE_PRECISION = new BN(10_000_000_000);
+const AMM_RESERVE_PRECISION = new BN(10_000_000_000_000);
+const QUOTE_PRECISION = new BN(1_000_000);
+
+function triggerLimitOrderParams(
+    direction: IdlTypes<ClearingHouse>['positionDirection'],
+    price: BN,
+    triggerPrice: BN,
+    triggerCondition: IdlTypes<ClearingHouse>['orderTriggerCondition'],
+): OrderParams {
+    return {
+        ...triggerMarketOrderParams(direction, triggerPrice, triggerCondition),
+        orderType: { triggerLimit: {} },
+        price,
+    };
+}
+
+function triggerMarketOrderParams(
+    direction: IdlTypes<ClearingHouse>['positionDirection'],
+    triggerPrice: BN,
+    triggerCondition: IdlTypes<ClearingHouse>['orderTriggerCondition'],
+    triggerPriceSource: IdlTypes<ClearingHouse>['orderTriggerPriceSource'] = { oracle: {} },
+): OrderParams {
+    return {
+        orderType: { triggerMarket: {} },
+        direction,
+        userOrderId: 0,
+        quoteAssetAmount: ZERO_BN,
+        baseAssetAmount: AMM_RESERVE_PRECISION,
+        price: ZERO_BN,
+        marketIndex: ZERO_BN,
+        reduceOnly: false,
+        postOnly: false,
+        immediateOrCancel: false,
+        triggerPrice,
+        triggerCondition,
+        triggerPriceSource,
+        oraclePriceOffset: ZERO_BN,
+        maxAge: null,
+    };
+}
+
+describe("clearing house: trigger orders", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+    let oracle: anchor.web3.PublicKey;
+    let userAuthority: anchor.web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 3);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+
+        oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+        await testCli.initializeMarket(ZERO_BN, oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        userAuthority = testCli.signers[1].publicKey;
+        const amount = QUOTE_PRECISION.muln(1000);
+        const userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority);
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, userCollateralAccount, testCli.signers[0], BigInt(amount.toString()));
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        await testCli.depositCollateral(amount, userCollateralAccount);
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail if trigger price on the wrong side of current price', async () => {
+        testCli.changeCurrentSigner(1);
+        // 当前oracle价格为50，Below的触发价格必须低于50
+        await requireCustomError(
+            testCli.placeOrder(triggerMarketOrderParams({ short: {} }, MARK_PRICE_PRECISION.muln(51), { below: {} })),
+            'InvalidTriggerPrice'
+        );
+        // 当前mark price为50，Above的触发价格必须高于50
+        await requireCustomError(
+            testCli.placeOrder(triggerMarketOrderParams({ long: {} }, MARK_PRICE_PRECISION.muln(49), { above: {} }, { mark: {} })),
+            'InvalidTriggerPrice'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail to fill untriggered order', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.placeOrder(triggerMarketOrderParams({ short: {} }, MARK_PRICE_PRECISION.muln(49), { below: {} }));
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(testCli.fillOrder(userAuthority, new BN(1), oracle), 'OrderNotTriggerable');
+
+        const order = (await testCli.getUserOrders(userAuthority)).orders[0];
+        expect(order.status).deep.eq({ init: {} });
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass fill triggered order', async () => {
+        await testCli.setPrice(pythProgram, oracle, new BN(48_000_000));
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(1), oracle);
+
+        requireBNEq((await testCli.getUserOrders(userAuthority)).orders[0].orderId, ZERO_BN);
+        const position = (await testCli.getUserPositions(userAuthority)).positions[0];
+        requireBNEq(position.baseAssetAmount, AMM_RESERVE_PRECISION.neg());
+
+        const orderRecord = (await testCli.getOrderHistory()).orderRecords[1];
+        expect(orderRecord.action).deep.eq({ fill: {} });
+        expect(orderRecord.order.status).deep.eq({ open: {} });
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass keep triggered limit order open when limit price not reachable', async () => {
+        testCli.changeCurrentSigner(1);
+        // 触发后限价60高于当前mark price，无法成交
+        await testCli.placeOrder(triggerLimitOrderParams({ short: {} }, MARK_PRICE_PRECISION.muln(60), MARK_PRICE_PRECISION.muln(47), { below: {} }));
+        await testCli.setPrice(pythProgram, oracle, new BN(46_000_000));
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(2), oracle);
+
+        const order = (await testCli.getUserOrders(userAuthority)).orders.find(order => order.orderId.eqn(2));
+        expect(order.status).deep.eq({ open: {} });
+        requireBNEq(order.baseAssetAmountFilled, ZERO_BN);
+        const position = (await testCli.getUserPositions(userAuthority)).positions[0];
+        requireBNEq(position.baseAssetAmount, AMM_RESERVE_PRECISION.neg());
+
+        // 已转为Open的订单再次成交时按普通限价单处理
+        await requireCustomError(testCli.fillOrder(userAuthority, new BN(2), oracle), 'CouldNotFillOrder');
+        testCli.changeCurrentSigner(0);
+    });
+});
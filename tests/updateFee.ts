@@ -0,0 +1,108 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, web3, BN, IdlTypes } from "@coral-xyz/anchor";
+import { ClearingHouse } from "../target/types/clearing_house";
+import { requireBNEq, requireCustomError, requirePublickeyEq } from "./utils";
+import { TestClient } from "./testClient";
+
+type FeeStructure = IdlTypes<ClearingHouse>['feeStructure'];
+
+function discountTokenTier(minimunBalance: number, discountNumerator: number) {
+    return {
+        discountNumerator: new BN(discountNumerator),
+        discountDenominator: new BN(100),
+        minimunBalance: new BN(minimunBalance),
+        padding: [0, 0, 0, 0, 0, 0, 0, 0],
+    };
+}
+
+function validFeeStructure(): FeeStructure {
+    return {
+        feeNumerator: new BN(5),
+        feeDenominator: new BN(10000),
+        discountTokenTiers: {
+            firstTier: discountTokenTier(1000, 40),
+            secondTier: discountTokenTier(100, 30),
+            thirdTier: discountTokenTier(10, 20),
+            fourthTier: discountTokenTier(1, 10),
+        },
+        referralDiscount: {
+            referralRewardNumerator: new BN(10),
+            referralRewardDenominator: new BN(100),
+            refereeDiscountNumerator: new BN(10),
+            refereeDiscountDenominator: new BN(100),
+        },
+    };
+}
+
+describe("clearing house: update_fee && update_discount_mint", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+
+    let testCli: TestClient;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(9, true);
+        await testCli.initialize(true);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.updateFee(validFeeStructure()),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.updateDiscountMint(web3.Keypair.generate().publicKey),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail with base fee not below 100%', async () => {
+        const feeStructure = validFeeStructure();
+        feeStructure.feeNumerator = new BN(10000);
+        await requireCustomError(testCli.updateFee(feeStructure), 'InvalidFeeStructure');
+
+        feeStructure.feeDenominator = new BN(0);
+        await requireCustomError(testCli.updateFee(feeStructure), 'InvalidFeeStructure');
+    });
+
+    it('Fail with discount token tiers not strictly ordered', async () => {
+        const feeStructure = validFeeStructure();
+        feeStructure.discountTokenTiers.secondTier.minimunBalance = new BN(1000);
+        await requireCustomError(testCli.updateFee(feeStructure), 'InvalidFeeStructure');
+    });
+
+    it('Fail with 100% token discount', async () => {
+        const feeStructure = validFeeStructure();
+        feeStructure.discountTokenTiers.firstTier.discountNumerator = new BN(100);
+        await requireCustomError(testCli.updateFee(feeStructure), 'InvalidFeeStructure');
+    });
+
+    it('Fail with referral reward + referee discount not below base fee', async () => {
+        const feeStructure = validFeeStructure();
+        feeStructure.referralDiscount.referralRewardNumerator = new BN(50);
+        feeStructure.referralDiscount.refereeDiscountNumerator = new BN(50);
+        await requireCustomError(testCli.updateFee(feeStructure), 'InvalidFeeStructure');
+    });
+
+    it('Pass update fee', async () => {
+        await testCli.updateFee(validFeeStructure());
+        const feeStructure = (await testCli.getState()).feeStructure;
+        requireBNEq(feeStructure.feeNumerator, new BN(5));
+        requireBNEq(feeStructure.feeDenominator, new BN(10000));
+        requireBNEq(feeStructure.discountTokenTiers.firstTier.minimunBalance, new BN(1000));
+        requireBNEq(feeStructure.discountTokenTiers.fourthTier.discountNumerator, new BN(10));
+        requireBNEq(feeStructure.referralDiscount.referralRewardNumerator, new BN(10));
+        requireBNEq(feeStructure.referralDiscount.refereeDiscountNumerator, new BN(10));
+    });
+
+    it('Pass update discount mint', async () => {
+        const discountMint = await testCli.createMint(6);
+        await testCli.updateDiscountMint(discountMint);
+        requirePublickeyEq((await testCli.getState()).discountMint, discountMint);
+    });
+});
@@ -0,0 +1,101 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN } from "@coral-xyz/anchor";
+import { ClearingHouse } from "../target/types/clearing_house";
+import { requireBNEq, requireCustomError, ZERO_BN } from "./utils";
+import { TestClient } from "./testClient";
+
+describe("clearing house: update liquidation parameters", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+
+    let testCli: TestClient;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(9, true);
+        await testCli.initialize(true);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.updatePartialLiquidationClosePercentage(new BN(1), new BN(2)),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.updatePartialLiquidationPenaltyPercentage(new BN(1), new BN(2)),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.updateFullLiquidationPenaltyPercentage(new BN(1), new BN(2)),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.updatePartialLiquidationLiquidatorShareDenominator(new BN(2)),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.updateFullLiquidationLiquidatorShareDenominator(new BN(2)),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail with zero denominator', async () => {
+        await requireCustomError(
+            testCli.updatePartialLiquidationClosePercentage(ZERO_BN, ZERO_BN),
+            'InvalidLiquidationParameter'
+        );
+        await requireCustomError(
+            testCli.updatePartialLiquidationPenaltyPercentage(ZERO_BN, ZERO_BN),
+            'InvalidLiquidationParameter'
+        );
+        await requireCustomError(
+            testCli.updateFullLiquidationPenaltyPercentage(ZERO_BN, ZERO_BN),
+            'InvalidLiquidationParameter'
+        );
+        await requireCustomError(
+            testCli.updatePartialLiquidationLiquidatorShareDenominator(ZERO_BN),
+            'InvalidLiquidationParameter'
+        );
+        await requireCustomError(
+            testCli.updateFullLiquidationLiquidatorShareDenominator(ZERO_BN),
+            'InvalidLiquidationParameter'
+        );
+    });
+
+    it('Fail with numerator greater than denominator', async () => {
+        await requireCustomError(
+            testCli.updatePartialLiquidationClosePercentage(new BN(101), new BN(100)),
+            'InvalidLiquidationParameter'
+        );
+        await requireCustomError(
+            testCli.updatePartialLiquidationPenaltyPercentage(new BN(1001), new BN(1000)),
+            'InvalidLiquidationParameter'
+        );
+        await requireCustomError(
+            testCli.updateFullLiquidationPenaltyPercentage(new BN(2), new BN(1)),
+            'InvalidLiquidationParameter'
+        );
+    });
+
+    it('Pass update liquidation parameters', async () => {
+        await testCli.updatePartialLiquidationClosePercentage(new BN(50), new BN(100));
+        await testCli.updatePartialLiquidationPenaltyPercentage(new BN(5), new BN(100));
+        await testCli.updateFullLiquidationPenaltyPercentage(new BN(1), new BN(2));
+        await testCli.updatePartialLiquidationLiquidatorShareDenominator(new BN(4));
+        await testCli.updateFullLiquidationLiquidatorShareDenominator(new BN(10));
+
+        const state = await testCli.getState();
+        requireBNEq(state.partialLiquidationClosePercentageNumerator, new BN(50));
+        requireBNEq(state.partialLiquidationClosePercentageDenominator, new BN(100));
+        requireBNEq(state.partialLiquidationPenaltyPercentageNumberator, new BN(5));
+        requireBNEq(state.partialLiquidationPenaltyPercentageDenominator, new BN(100));
+        requireBNEq(state.fullLiquidationPenaltyPercentageNumerator, new BN(1));
+        requireBNEq(state.fullLiquidationPenaltyPercentageDenominator, new BN(2));
+        requireBNEq(state.partialLiquidationLiquidatorShareDenominator, new BN(4));
+        requireBNEq(state.fullLiquidationLiquidatorShareDenominator, new BN(10));
+    });
+});
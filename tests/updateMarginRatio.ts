@@ -0,0 +1,150 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes } from "@coral-xyz/anchor";
+import { createAccount, mintTo } from '@solana/spl-token';
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError, ZERO_BN } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+type OrderParams = IdlTypes<ClearingHouse>['orderParams'];
+
+const AMM_RESERVE_PRECISION = new BN(10_000_000_000_000);
+
+describe("clearing house: update_margin_ratio && update_market_margin_ratio", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(9, true);
+        await testCli.initialize(true);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.updateMarginRatio(new BN(1000), new BN(500), new BN(625)),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.updateMarketMarginRatio(new BN(0), 1000, 500, 625, false),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail with margin ratio out of range', async () => {
+        // 大于100%
+        await requireCustomError(
+            testCli.updateMarginRatio(new BN(10001), new BN(500), new BN(625)),
+            'InvalidMarginRatio'
+        );
+        // 小于2%
+        await requireCustomError(
+            testCli.updateMarginRatio(new BN(2000), new BN(199), new BN(625)),
+            'InvalidMarginRatio'
+        );
+    });
+
+    it('Fail with wrong margin ratio order', async () => {
+        // initial < maintenance
+        await requireCustomError(
+            testCli.updateMarginRatio(new BN(600), new BN(500), new BN(625)),
+            'InvalidMarginRatio'
+        );
+        // maintenance < partial
+        await requireCustomError(
+            testCli.updateMarginRatio(new BN(2000), new BN(700), new BN(625)),
+            'InvalidMarginRatio'
+        );
+    });
+
+    it('Pass update margin ratio', async () => {
+        await testCli.updateMarginRatio(new BN(1000), new BN(400), new BN(500));
+        const state = await testCli.getState();
+        requireBNEq(state.marginRatioInitial, new BN(1000));
+        requireBNEq(state.marginRatioPartial, new BN(400));
+        requireBNEq(state.marginRatioMaintenance, new BN(500));
+    });
+
+    it('Fail to update margin ratio of uninitialized market', async () => {
+        await requireCustomError(
+            testCli.updateMarketMarginRatio(new BN(0), 1000, 500, 625, false),
+            'MarketIndexNotInitialized'
+        );
+    });
+
+    it('Fail to update margin ratio of market out of range', async () => {
+        await requireCustomError(
+            testCli.updateMarketMarginRatio(new BN(64), 1000, 500, 625, false),
+            'MarketIndexOutOfRange'
+        );
+    });
+
+    it('Pass tighten margin ratio of market with open interest only if forced', async () => {
+        // 在market 1上开一个头寸，使其open_interest大于0
+        const marketIndex = new BN(1);
+        const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+        const oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        await testCli.initializeMarket(marketIndex, oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        const userAuthority = testCli.signers[1].publicKey;
+        const amount = new BN(1000_000_000_000);
+        const userCollateralAccount = await createAccount(provider.connection, testCli.signers[1], testCli.collateralMint, userAuthority);
+        await mintTo(provider.connection, testCli.signers[0], testCli.collateralMint, userCollateralAccount, testCli.signers[0], BigInt(amount.toString()));
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        await testCli.depositCollateral(amount, userCollateralAccount);
+        const params: OrderParams = {
+            orderType: { market: {} },
+            direction: { long: {} },
+            userOrderId: 0,
+            quoteAssetAmount: ZERO_BN,
+            baseAssetAmount: AMM_RESERVE_PRECISION,
+            price: ZERO_BN,
+            marketIndex,
+            reduceOnly: false,
+            postOnly: false,
+            immediateOrCancel: false,
+            triggerPrice: ZERO_BN,
+            triggerCondition: { above: {} },
+            triggerPriceSource: { oracle: {} },
+            oraclePriceOffset: ZERO_BN,
+            maxAge: null,
+        };
+        await testCli.placeAndFillOrder(params);
+        testCli.changeCurrentSigner(0);
+        requireBNEq((await testCli.getMarket(marketIndex)).openInterest, new BN(1));
+
+        // 调高维持保证金比例必须传入force
+        await requireCustomError(
+            testCli.updateMarketMarginRatio(marketIndex, 1000, 500, 625, false),
+            'MarginRatioTighteningNotForced'
+        );
+        let market = await testCli.getMarket(marketIndex);
+        expect(market.marginRatioPartial).eq(400);
+        expect(market.marginRatioMaintenance).eq(500);
+
+        await testCli.updateMarketMarginRatio(marketIndex, 1000, 500, 625, true);
+        market = await testCli.getMarket(marketIndex);
+        expect(market.marginRatioInitial).eq(1000);
+        expect(market.marginRatioPartial).eq(500);
+        expect(market.marginRatioMaintenance).eq(625);
+
+        // 调低保证金比例不需要force
+        await testCli.updateMarketMarginRatio(marketIndex, 1000, 400, 500, false);
+        market = await testCli.getMarket(marketIndex);
+        expect(market.marginRatioPartial).eq(400);
+        expect(market.marginRatioMaintenance).eq(500);
+    });
+});
@@ -0,0 +1,116 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN, IdlTypes } from "@coral-xyz/anchor";
+import { ClearingHouse } from "../target/types/clearing_house";
+import { Pyth } from "../target/types/pyth";
+import { requireBNEq, requireCustomError } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+const MARK_PRICE_PRECISION = new BN(10_000_000_000);
+
+type OracleGuardRails = IdlTypes<ClearingHouse>['oracleGuardRails'];
+
+function validOracleGuardRails(): OracleGuardRails {
+    return {
+        priceDivergence: {
+            markOracleDivergenceNumerator: new BN(1),
+            markOracleDivergenceDenominator: new BN(5),
+        },
+        validity: {
+            confidenceIntervalMaxSize: new BN(10),
+            tooVolatileRatio: new BN(3),
+            slotsBeforeStable: new BN(500),
+            padding: [0, 0, 0, 0, 0, 0, 0, 0],
+        },
+        useForLiquidations: 0,
+        padding: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    };
+}
+
+describe("clearing house: update_oracle_guard_rails && update_market_oracle", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+    const pythProgram = anchor.workspace.Pyth as Program<Pyth>;
+
+    let testCli: TestClient;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(9, true);
+        await testCli.initialize(true);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.updateOracleGuardRails(validOracleGuardRails()),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Fail with invalid price divergence', async () => {
+        const oracleGuardRails = validOracleGuardRails();
+        oracleGuardRails.priceDivergence.markOracleDivergenceNumerator = new BN(0);
+        await requireCustomError(testCli.updateOracleGuardRails(oracleGuardRails), 'InvalidOracleGuardRails');
+
+        oracleGuardRails.priceDivergence.markOracleDivergenceNumerator = new BN(6);
+        await requireCustomError(testCli.updateOracleGuardRails(oracleGuardRails), 'InvalidOracleGuardRails');
+    });
+
+    it('Fail with invalid validity guard rails', async () => {
+        const oracleGuardRails = validOracleGuardRails();
+        oracleGuardRails.validity.tooVolatileRatio = new BN(1);
+        await requireCustomError(testCli.updateOracleGuardRails(oracleGuardRails), 'InvalidOracleGuardRails');
+    });
+
+    it('Fail with invalid use for liquidations flag', async () => {
+        const oracleGuardRails = validOracleGuardRails();
+        oracleGuardRails.useForLiquidations = 2;
+        await requireCustomError(testCli.updateOracleGuardRails(oracleGuardRails), 'InvalidOracleGuardRails');
+    });
+
+    it('Pass update oracle guard rails', async () => {
+        await testCli.updateOracleGuardRails(validOracleGuardRails());
+        const oracleGuardRails = (await testCli.getState()).oracleGuardRails;
+        requireBNEq(oracleGuardRails.priceDivergence.markOracleDivergenceNumerator, new BN(1));
+        requireBNEq(oracleGuardRails.priceDivergence.markOracleDivergenceDenominator, new BN(5));
+        requireBNEq(oracleGuardRails.validity.confidenceIntervalMaxSize, new BN(10));
+        requireBNEq(oracleGuardRails.validity.tooVolatileRatio, new BN(3));
+        requireBNEq(oracleGuardRails.validity.slotsBeforeStable, new BN(500));
+        expect(oracleGuardRails.useForLiquidations).eq(0);
+    });
+
+    it('Fail to update oracle of uninitialized market', async () => {
+        const priceFeed = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(1000));
+        await requireCustomError(
+            testCli.updateMarketOracle(new BN(0), priceFeed, { pyth: {} }),
+            'MarketIndexNotInitialized'
+        );
+    });
+
+    it('Pass update oracle after price moved since market initialized', async () => {
+        const oracle = await testCli.createPriceFeed(pythProgram, new BN(50_000_000), -6, new BN(0));
+        const ammReserve = new BN(5).mul(new BN(10).pow(new BN(19)));
+        await testCli.initializeMarket(new BN(0), oracle, ammReserve, ammReserve, new BN(3600), new BN(50_000));
+
+        // 当前oracle的价格从50涨到70，新oracle与当前oracle的实时TWAP比较，而不是创建market时的TWAP
+        await testCli.setPrice(pythProgram, oracle, new BN(70_000_000));
+        const newOracle = await testCli.createPriceFeed(pythProgram, new BN(70_000_000), -6, new BN(0));
+        await testCli.updateMarketOracle(new BN(0), newOracle, { pyth: {} });
+
+        const market = await testCli.getMarket(new BN(0));
+        expect(market.amm.oracle.toBase58()).eq(newOracle.toBase58());
+        requireBNEq(market.amm.lastOraclePriceTwap, MARK_PRICE_PRECISION.muln(70));
+    });
+
+    it('Fail if new oracle diverges from current oracle twap', async () => {
+        const priceFeed = await testCli.createPriceFeed(pythProgram, new BN(100_000_000), -6, new BN(0));
+        await requireCustomError(
+            testCli.updateMarketOracle(new BN(0), priceFeed, { pyth: {} }),
+            'OracleTwapDivergenceTooLarge'
+        );
+    });
+});
@@ -0,0 +1,61 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN } from "@coral-xyz/anchor";
+import { ClearingHouse } from "../target/types/clearing_house";
+import { requireCustomError } from "./utils";
+import { expect } from "chai";
+import { TestClient } from "./testClient";
+
+describe("clearing house: update_exchange_paused && update_funding_paused && update_market_status", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+
+    const program = anchor.workspace.ClearingHouse as Program<ClearingHouse>;
+
+    let testCli: TestClient;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, program, 2);
+        await testCli.initializeRelevantAccounts(9, true);
+        await testCli.initialize(true);
+    });
+
+    it('Fail if signer not admin in state', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.updateExchangePaused(true),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.updateFundingPaused(true),
+            'ConstraintHasOne'
+        );
+        await requireCustomError(
+            testCli.updateMarketStatus(new BN(0), { paused: {} }),
+            'ConstraintHasOne'
+        );
+        testCli.changeCurrentSigner(0);
+    });
+
+    it('Pass pause and unpause exchange', async () => {
+        await testCli.updateExchangePaused(true);
+        expect((await testCli.getState()).exchangePaused).eq(1);
+
+        await testCli.updateExchangePaused(false);
+        expect((await testCli.getState()).exchangePaused).eq(0);
+    });
+
+    it('Pass pause and unpause funding', async () => {
+        await testCli.updateFundingPaused(true);
+        expect((await testCli.getState()).fundingPaused).eq(1);
+
+        await testCli.updateFundingPaused(false);
+        expect((await testCli.getState()).fundingPaused).eq(0);
+    });
+
+    it('Fail to update status of uninitialized market', async () => {
+        await requireCustomError(
+            testCli.updateMarketStatus(new BN(0), { reduceOnly: {} }),
+            'MarketIndexNotInitialized'
+        );
+    });
+});